@@ -0,0 +1,444 @@
+use crate::util::io::{json,lock};
+use crate::util::io::savefile::get_savedata;
+use crate::util::objects::paths::{Paths,PathBase,ModulePaths};
+use crate::util::objects::configuration::Configuration;
+use crate::util::objects::trigger::RunRequest;
+use crate::util::command;
+use crate::{Arguments, vbackup};
+
+use crate::{try_option};
+
+use std::collections::VecDeque;
+use std::io::Cursor;
+use std::net::{IpAddr, SocketAddr};
+use std::os::unix::fs::PermissionsExt;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use serde::{Deserialize,Serialize};
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+fn default_socket_mode() -> String { String::from("660") }
+fn default_history_size() -> usize { 50 }
+
+#[derive(Deserialize)]
+struct ApiConfiguration {
+    listen: Option<String>, // TCP address, only loopback addresses are accepted
+    socket: Option<String>, // Path of the unix socket
+
+    #[serde(default="default_socket_mode")]
+    socket_mode: String,
+
+    #[serde(default="default_history_size")]
+    history_size: usize
+}
+
+#[derive(Serialize,Clone)]
+struct RunRecord {
+    id: String,
+    operation: String,
+    name: Option<String>,
+    status: &'static str,
+    error: Option<String>,
+    started: i64,
+    finished: Option<i64>
+}
+
+struct ApiState {
+    current: Option<RunRecord>,
+    history: VecDeque<RunRecord>,
+    next_id: u64
+}
+
+struct ApiContext {
+    args: Arguments,
+    paths: Paths,
+    history_size: usize,
+    state: Mutex<ApiState>
+}
+
+type ApiResponse = Response<Cursor<Vec<u8>>>;
+
+/**
+ * Serve the control API on the configured TCP address and / or unix socket until terminated
+ */
+pub fn main(args: Arguments) -> Result<(),String> {
+    let base_paths = json::from_file::<PathBase>(Path::new(args.base_config.as_str()))?;
+    let paths = Paths::from(base_paths);
+
+    let config_option = json::from_file_checked::<ApiConfiguration>(Path::new(paths.api_file.as_str()))?;
+    let config = try_option!(config_option, format!("No api configuration found at '{}'", paths.api_file.as_str()));
+
+    // Runs can be cancelled over the api, which has to reach every process of the commands
+    command::use_process_groups();
+
+    let mut servers = vec![];
+
+    if let Some(listen) = config.listen.as_ref() {
+        let address = match listen.parse::<SocketAddr>() {
+            Ok(address) => address,
+            Err(err) => return Err(format!("Could not parse api listen address '{}': {}", listen, err))
+        };
+
+        // There is no authentication, so do not allow to expose the api to the network
+        if !address.ip().is_loopback() {
+            return Err(format!("The api may only listen on a loopback address, got '{}'", listen));
+        }
+
+        let server = Server::http(address).map_err(|err| format!("Could not listen on '{}': {}", listen, err))?;
+        info!("Api is listening on 'http://{}'", listen);
+        servers.push(server);
+    }
+
+    if let Some(socket) = config.socket.as_ref() {
+        // Remove a stale socket from a previous run
+        if Path::new(socket).exists() {
+            if let Err(err) = std::fs::remove_file(socket) {
+                return Err(format!("Could not remove existing socket '{}': {}", socket, err));
+            }
+        }
+
+        let server = Server::http_unix(Path::new(socket)).map_err(|err| format!("Could not listen on socket '{}': {}", socket, err))?;
+
+        let mode = u32::from_str_radix(config.socket_mode.as_str(), 8).map_err(|err| format!("Could not parse socket mode '{}': {}", config.socket_mode, err))?;
+        if let Err(err) = std::fs::set_permissions(socket, std::fs::Permissions::from_mode(mode)) {
+            return Err(format!("Could not set permissions on socket '{}': {}", socket, err));
+        }
+
+        info!("Api is listening on socket '{}'", socket);
+        servers.push(server);
+    }
+
+    if servers.is_empty() {
+        return Err(String::from("Neither a listen address nor a socket is configured for the api"));
+    }
+
+    let context = Arc::new(ApiContext {
+        args,
+        paths,
+        history_size: config.history_size,
+        state: Mutex::new(ApiState {
+            current: None,
+            history: VecDeque::new(),
+            next_id: 1
+        })
+    });
+
+    let handles = servers.into_iter().map(|server| {
+        let context = context.clone();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                handle_request(request, &context);
+            }
+        })
+    }).collect::<Vec<_>>();
+
+    for handle in handles {
+        if handle.join().is_err() {
+            error!("Api server thread terminated unexpectedly");
+        }
+    }
+
+    return Ok(());
+}
+
+fn handle_request(mut request: Request, context: &Arc<ApiContext>) {
+    let url = String::from(request.url());
+    let path = url.split('?').next().unwrap_or("");
+    let segments = path.split('/').filter(|segment| !segment.is_empty()).collect::<Vec<&str>>();
+
+    debug!("Api request: {} {}", request.method(), path);
+
+    let response = match (request.method(), segments.as_slice()) {
+        _ if !is_local_request(&request) => error_response(403, "Only local requests are accepted"),
+        (Method::Get, ["list"]) => list(context),
+        (Method::Get, ["status"]) => status(context, None),
+        (Method::Get, ["status", name]) => status(context, Some(name)),
+        (Method::Get, ["history"]) => history(context),
+        (Method::Post, ["cancel"]) => cancel(context),
+        (Method::Post, [operation]) if is_run_operation(operation) => {
+            start_run(context, operation, None, read_body(&mut request))
+        },
+        (Method::Post, [operation, name]) if is_run_operation(operation) => {
+            start_run(context, operation, Some(name), read_body(&mut request))
+        },
+        _ => error_response(404, "Not found")
+    };
+
+    if let Err(err) = request.respond(response) {
+        warn!("Could not send api response: {}", err);
+    }
+}
+
+/** Browsers send an Origin header with cross site requests and the Host of a rebound DNS name, plain local clients do neither */
+fn is_local_request(request: &Request) -> bool {
+    let mut host = None;
+    for header in request.headers() {
+        if header.field.equiv("Origin") {
+            return false;
+        }
+        if header.field.equiv("Host") {
+            host = Some(header.value.as_str());
+        }
+    }
+
+    // The unix socket can not be reached from a browser
+    if request.remote_addr().is_none() {
+        return true;
+    }
+
+    let host = match host {
+        Some(host) => host,
+        None => return false
+    };
+    // Strip the port, IPv6 addresses are enclosed in brackets
+    let hostname = if host.starts_with('[') {
+        host.split(']').next().unwrap_or("").trim_start_matches('[')
+    } else {
+        host.split(':').next().unwrap_or("")
+    };
+    if hostname.eq_ignore_ascii_case("localhost") {
+        return true;
+    }
+    return match hostname.parse::<IpAddr>() {
+        Ok(address) => address.is_loopback(),
+        Err(_) => false
+    };
+}
+
+fn is_run_operation(operation: &str) -> bool {
+    return operation == "run" || operation == "backup" || operation == "sync" || operation == "verify";
+}
+
+fn read_body(request: &mut Request) -> Result<String, String> {
+    let mut body = String::new();
+    if let Err(err) = request.as_reader().read_to_string(&mut body) {
+        return Err(format!("Could not read request body: {}", err));
+    }
+    return Ok(body);
+}
+
+fn list(context: &Arc<ApiContext>) -> ApiResponse {
+    let mut args = context.args.clone();
+    args.name = None;
+
+    let configurations = match vbackup::get_config_list(&Rc::new(args), &context.paths) {
+        Ok(configurations) => configurations,
+        Err(err) => return error_response(500, err.as_str())
+    };
+
+    let result = configurations.iter().map(|config| {
+        json!({
+            "name": config.name,
+            "disabled": config.disabled,
+            "manual": config.manual,
+            "backup": config.backup.as_ref().map(|backup| json!({
+                "type": backup.backup_type,
+                "disabled": backup.disabled
+            })),
+            "sync": config.sync.as_ref().map(|sync| json!({
+                "type": sync.sync_type,
+                "disabled": sync.disabled
+            }))
+        })
+    }).collect::<Vec<Value>>();
+
+    return json_response(200, &Value::Array(result));
+}
+
+fn status(context: &Arc<ApiContext>, name: Option<&str>) -> ApiResponse {
+    let mut args = context.args.clone();
+
+    if let Some(name) = name {
+        if !is_valid_name(name) {
+            return error_response(400, "Invalid configuration name");
+        }
+
+        // The configuration has to exist, otherwise loading the list fails
+        if !Path::new(format!("{}/volumes/{}.json", context.paths.config_dir, name).as_str()).exists() {
+            return error_response(404, "Configuration not found");
+        }
+    }
+    args.name = name.map(String::from);
+
+    let paths = Rc::new(context.paths.clone());
+    let configurations = match vbackup::get_config_list(&Rc::new(args), paths.as_ref()) {
+        Ok(configurations) => configurations,
+        Err(err) => return error_response(500, err.as_str())
+    };
+
+    let states = configurations.iter()
+        .map(|config| get_config_status(&paths, config))
+        .collect::<Vec<Value>>();
+
+    let current = get_state(context).current.clone();
+
+    return if name.is_some() {
+        match states.into_iter().next() {
+            Some(state) => json_response(200, &state),
+            None => error_response(404, "Configuration not found")
+        }
+    } else {
+        json_response(200, &json!({
+            "running": current,
+            "configurations": states
+        }))
+    };
+}
+
+fn get_config_status(paths: &Rc<Paths>, config: &Configuration) -> Value {
+    // The savedata path is the same for backup and sync
+    let module_paths = ModulePaths::for_backup_module(paths, "backup", config);
+
+    return match get_savedata(module_paths.save_data.as_str()) {
+        Ok(savedata) => json!({
+            "name": config.name,
+            "disabled": config.disabled,
+            "lastsave": savedata.lastsave,
+            "nextsave": savedata.nextsave,
+            "lastsync": savedata.lastsync
+        }),
+        Err(err) => json!({
+            "name": config.name,
+            "disabled": config.disabled,
+            "error": err
+        })
+    };
+}
+
+fn history(context: &Arc<ApiContext>) -> ApiResponse {
+    let history = get_state(context).history.iter().cloned().collect::<Vec<RunRecord>>();
+    return json_response(200, &json!(history));
+}
+
+fn cancel(context: &Arc<ApiContext>) -> ApiResponse {
+    let mut state = get_state(context);
+
+    return if let Some(current) = state.current.as_mut() {
+        info!("Cancelling run '{}'", current.id);
+        current.status = "cancelling";
+        let terminated = command::cancel_all();
+        json_response(202, &json!({
+            "id": current.id,
+            "terminated_processes": terminated
+        }))
+    } else {
+        error_response(409, "No run in progress")
+    };
+}
+
+fn start_run(context: &Arc<ApiContext>, operation: &str, name: Option<&str>, body: Result<String, String>) -> ApiResponse {
+    // The body contains the same flags as a trigger request, operation and name are taken from the path
+    let mut request_value = match body {
+        Ok(body) if body.trim().is_empty() => json!({}),
+        Ok(body) => match serde_json::from_str::<Value>(body.as_str()) {
+            Ok(value) => value,
+            Err(err) => return error_response(400, format!("Could not parse request body: {}", err).as_str())
+        },
+        Err(err) => return error_response(400, err.as_str())
+    };
+
+    if let Some(object) = request_value.as_object_mut() {
+        object.insert(String::from("operation"), json!(operation));
+        if let Some(name) = name {
+            object.insert(String::from("name"), json!(name));
+        }
+    } else {
+        return error_response(400, "Expected a JSON object as request body");
+    }
+
+    let request = match serde_json::from_value::<RunRequest>(request_value) {
+        Ok(request) => request,
+        Err(err) => return error_response(400, format!("Invalid run request: {}", err).as_str())
+    };
+
+    let args = match request.to_arguments(&context.args) {
+        Ok(args) => args,
+        Err(err) => return error_response(400, err.as_str())
+    };
+
+    let mut state = get_state(context);
+    if state.current.is_some() {
+        return error_response(409, "Another run is in progress");
+    }
+
+    // Use the same lock as runs from the command line, such that they can not overlap
+    let lock_file = match lock::try_acquire() {
+        Ok(Some(file)) => file,
+        Ok(None) => return error_response(409, "Another run is in progress"),
+        Err(err) => return error_response(500, err.as_str())
+    };
+
+    let id = request.id.clone().unwrap_or(state.next_id.to_string());
+    state.next_id += 1;
+
+    let record = RunRecord {
+        id: id.clone(),
+        operation: request.operation.clone(),
+        name: request.name.clone(),
+        status: "running",
+        error: None,
+        started: chrono::Local::now().timestamp(),
+        finished: None
+    };
+    state.current = Some(record.clone());
+    drop(state);
+
+    command::reset_cancel();
+
+    let response = json_response(202, &json!({ "id": id }));
+    let context_clone = context.clone();
+    thread::spawn(move || {
+        info!("Starting '{}' ({}) from api", args.operation.as_str(), id);
+        // A panic has to release the lock and finish the record as well, otherwise every later run would be refused
+        let result = panic::catch_unwind(AssertUnwindSafe(|| vbackup::main(args)))
+            .unwrap_or_else(|_| Err(String::from("The run was aborted by a panic")));
+
+        if let Err(err) = lock::release(lock_file) {
+            error!("{}", err);
+        }
+
+        let mut state = get_state(&context_clone);
+        let mut record = state.current.take().unwrap_or(record);
+        record.finished = Some(chrono::Local::now().timestamp());
+        // Failures of single configurations are only logged, so the cancel flag has to be checked separately
+        record.status = if command::is_cancelled() {
+            "cancelled"
+        } else if result.is_ok() {
+            "done"
+        } else {
+            "failed"
+        };
+        record.error = result.err();
+
+        info!("Run '{}' from api finished with status '{}'", record.id, record.status);
+
+        state.history.push_front(record);
+        state.history.truncate(context_clone.history_size);
+    });
+
+    return response;
+}
+
+fn get_state(context: &Arc<ApiContext>) -> MutexGuard<'_, ApiState> {
+    // A panic in a run thread should not render the api unusable
+    return context.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+}
+
+fn is_valid_name(name: &str) -> bool {
+    return !name.is_empty() && !name.contains('/') && !name.starts_with('.');
+}
+
+fn json_response(code: u16, value: &Value) -> ApiResponse {
+    let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+    return Response::from_string(value.to_string())
+        .with_status_code(code)
+        .with_header(header);
+}
+
+fn error_response(code: u16, message: &str) -> ApiResponse {
+    return json_response(code, &json!({ "error": message }));
+}
@@ -0,0 +1,192 @@
+use std::rc::Rc;
+use crate::{Arguments, log_error, restore};
+use crate::modules::backup::{BackupModule, BackupWrapper};
+use crate::modules::sync::{SyncModule, SyncWrapper};
+use crate::util::objects::configuration::Configuration;
+use crate::util::objects::paths::{ModulePaths, Paths};
+use crate::util::objects::restore::{ArchiveEntry, ArchiveSelector, FileEntry};
+use crate::vbackup::get_config_list;
+
+use glob::{MatchOptions, Pattern};
+
+/**
+ * What to show for every configuration, set by the operation (ls or find) and its options
+ */
+enum Browse {
+    Archives,
+    Files(ArchiveSelector),
+    Find(Pattern, Option<ArchiveSelector>)
+}
+
+/**
+ * List archives and their content (ls) or search for files in the archives (find)
+ */
+pub fn main(args: Rc<Arguments>, paths: Rc<Paths>) -> Result<(),String> {
+    let (do_backup, do_sync) = restore::parse_only(args.as_ref())?;
+
+    let selector = args.archive.as_ref()
+        .map(|archive| ArchiveSelector::parse(archive.as_str()))
+        .transpose()?;
+
+    let browse = if args.operation == "find" {
+        let pattern_string = args.pattern.as_ref().ok_or(String::from("Please set a pattern to search for with --pattern"))?;
+        let pattern = Pattern::new(pattern_string.as_str())
+            .map_err(|err| format!("Invalid search pattern '{}': {}", pattern_string, err))?;
+        Browse::Find(pattern, selector)
+    } else if let Some(selector) = selector {
+        Browse::Files(selector)
+    } else {
+        Browse::Archives
+    };
+
+    let mut failed = 0;
+
+    for config in get_config_list(&args, paths.as_ref())? {
+        if do_backup {
+            if let Some(backup_config) = config.backup.as_ref() {
+                let module_paths = ModulePaths::for_backup_module(&paths, "backup", &config);
+                let result = BackupModule::new(backup_config.backup_type.as_str(), config.name.as_str(), &backup_config.config, module_paths, &args)
+                    .and_then(|mut module| {
+                        module.init()?;
+                        let result = print_module(&config, "backup", module.get_module_name(), &browse,
+                                                  &|| module.list_archives(),
+                                                  &|archive| module.list_files(archive));
+                        log_error!(module.clear());
+                        result
+                    });
+
+                if let Err(err) = result {
+                    error!("Could not browse backup of '{}': {}", config.name.as_str(), err);
+                    failed += 1;
+                }
+            }
+        }
+
+        if do_sync {
+            if let Some(sync_config) = config.sync.as_ref() {
+                if sync_config.controller.is_some() {
+                    debug!("The controller of '{}' is not started for browsing, the remote has to be reachable", config.name.as_str());
+                }
+
+                let module_paths = ModulePaths::for_sync_module(&paths, "sync", &config);
+                let result = SyncModule::new(sync_config.sync_type.as_str(), config.name.as_str(), &sync_config.config, module_paths, &args)
+                    .and_then(|mut module| {
+                        module.init()?;
+                        let result = print_module(&config, "sync", module.get_module_name(), &browse,
+                                                  &|| module.list_archives(),
+                                                  &|archive| module.list_files(archive));
+                        log_error!(module.clear());
+                        result
+                    });
+
+                if let Err(err) = result {
+                    error!("Could not browse sync of '{}': {}", config.name.as_str(), err);
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    return if failed > 0 {
+        Err(format!("Browsing failed for {} module(s)", failed))
+    } else {
+        Ok(())
+    };
+}
+
+fn print_module(config: &Configuration,
+                module_type: &str,
+                module_name: &str,
+                browse: &Browse,
+                list_archives: &dyn Fn() -> Result<Vec<ArchiveEntry>, String>,
+                list_files: &dyn Fn(&ArchiveEntry) -> Result<Vec<FileEntry>, String>) -> Result<(), String> {
+
+    let archives = list_archives()?;
+
+    match browse {
+        Browse::Archives => {
+            println!("{} ({}, {}): {} archive(s)", config.name, module_type, module_name, archives.len());
+            for archive in archives.iter() {
+                println!("  {:<19}  {}", format_time(&archive.time), archive.id);
+            }
+        },
+        Browse::Files(selector) => {
+            let archive = &archives[select(selector, &archives)?];
+            println!("{} ({}, {}): {}", config.name, module_type, module_name, archive.id);
+            for file in list_files(archive)?.iter() {
+                print_file(file);
+            }
+        },
+        Browse::Find(pattern, selector) => {
+            let selected: Vec<&ArchiveEntry> = if let Some(selector) = selector {
+                vec![&archives[select(selector, &archives)?]]
+            } else {
+                archives.iter().collect()
+            };
+
+            println!("{} ({}, {}):", config.name, module_type, module_name);
+            for archive in selected {
+                let matches: Vec<FileEntry> = list_files(archive)?.into_iter()
+                    .filter(|file| matches_pattern(pattern, file.path.as_str()))
+                    .collect();
+
+                if !matches.is_empty() {
+                    println!(" {}:", archive.id);
+                    matches.iter().for_each(print_file);
+                }
+            }
+        }
+    }
+
+    return Ok(());
+}
+
+fn select(selector: &ArchiveSelector, archives: &Vec<ArchiveEntry>) -> Result<usize, String> {
+    return selector.select(archives, &|archive| archive.id.as_str(), &|archive| archive.time);
+}
+
+// Patterns without a slash are matched against the file name only, otherwise against the whole path
+fn matches_pattern(pattern: &Pattern, path: &str) -> bool {
+    let options = MatchOptions {
+        case_sensitive: true,
+        require_literal_separator: true,
+        require_literal_leading_dot: false
+    };
+
+    return if pattern.as_str().contains('/') {
+        pattern.matches_with(path, options)
+    } else {
+        pattern.matches_with(path.rsplit('/').next().unwrap_or(path), options)
+    };
+}
+
+fn print_file(file: &FileEntry) {
+    let size = if file.is_dir {
+        String::from("-")
+    } else {
+        file.size.map(format_size).unwrap_or(String::from("?"))
+    };
+
+    println!("  {:>10}  {:<19}  {}{}", size, format_time(&file.mtime), file.path, if file.is_dir {"/"} else {""});
+}
+
+fn format_time(time: &Option<chrono::DateTime<chrono::Local>>) -> String {
+    return time.map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or(String::from("-"));
+}
+
+fn format_size(size: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = size as f64;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    return if unit == 0 {
+        format!("{} {}", size, units[unit])
+    } else {
+        format!("{:.1} {}", value, units[unit])
+    };
+}
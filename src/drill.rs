@@ -0,0 +1,360 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::rc::Rc;
+use rand::seq::SliceRandom;
+use crate::{Arguments, dry_run, log_error, try_result, restore, vbackup};
+use crate::modules::backup::{BackupModule, BackupWrapper};
+use crate::modules::reporting::ReportingModule;
+use crate::modules::sync::{SyncModule, SyncWrapper};
+use crate::util::command::CommandWrapper;
+use crate::util::docker;
+use crate::util::io::file;
+use crate::util::io::savefile::get_savedata;
+use crate::util::objects::configuration::Configuration;
+use crate::util::objects::paths::{ModulePaths, Paths, SourcePath};
+use crate::util::objects::reporting::{RunType, Status};
+use crate::util::objects::restore::RestoreRequest;
+
+// Number of files passed to a single sha256sum call
+const CHECKSUM_CHUNK_SIZE: usize = 200;
+// Number of file names to list in the error message
+const MAX_LISTED_FILES: usize = 5;
+
+struct FileInfo {
+    size: u64,
+    mtime: i64
+}
+
+#[derive(Default)]
+struct Comparison {
+    compared: usize,
+    checksums: usize,
+    changed: usize,
+    excluded: usize,
+    extra: usize,
+    missing: Vec<String>,
+    differing: Vec<String>
+}
+
+pub fn main(args: Rc<Arguments>, paths: Rc<Paths>, reporter: &mut ReportingModule) -> Result<(),String> {
+    let (do_backup, do_sync) = restore::parse_only(args.as_ref())?;
+
+    let mut failed = 0;
+    for config in vbackup::get_config_list(&args, paths.as_ref())? {
+        if config.disabled && !args.override_disabled {
+            info!("Configuration for '{}' is disabled, skipping drill", config.name.as_str());
+            reporter.report_status(RunType::DRILL, Some(config.name.clone()), Status::DISABLED);
+            continue;
+        }
+
+        let parts = vec![
+            ("backup", do_backup, config.backup.as_ref().map(|backup| backup.disabled)),
+            ("sync", do_sync, config.sync.as_ref().map(|sync| sync.disabled))
+        ];
+
+        for (part, selected, disabled_opt) in parts {
+            let disabled = if let Some(disabled) = disabled_opt {
+                disabled
+            } else {
+                continue;
+            };
+
+            if !selected {
+                debug!("Skipping {} drill for '{}' due to --only", part, config.name.as_str());
+                continue;
+            }
+
+            if disabled && !args.override_disabled {
+                info!("{} for '{}' is disabled, skipping drill", part, config.name.as_str());
+                reporter.report_status(RunType::DRILL, Some(config.name.clone()), Status::DISABLED);
+                continue;
+            }
+
+            // The drill restores into another directory, which is not possible for every module
+            if let Err(reason) = check_restore_to(&args, &config, part) {
+                info!("Skipping the drill of the {} for '{}': {}", part, config.name.as_str(), reason);
+                reporter.report_status(RunType::DRILL, Some(config.name.clone()), Status::SKIP);
+                continue;
+            }
+
+            reporter.report_status(RunType::DRILL, Some(config.name.clone()), Status::START);
+
+            match drill(&args, &paths, &config, part) {
+                Ok(_) => {
+                    info!("drill of the {} for '{}' passed", part, config.name.as_str());
+                    reporter.report_status(RunType::DRILL, Some(config.name.clone()), Status::DONE);
+                },
+                Err(err) => {
+                    error!("drill of the {} for '{}' failed: {}", part, config.name.as_str(), err);
+                    reporter.report_status(RunType::DRILL, Some(config.name.clone()), Status::ERROR);
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} drill(s) failed", failed));
+    }
+
+    return Ok(());
+}
+
+/**
+ * Restore the latest backup / sync into a temporary directory and compare it against the live source
+ */
+fn drill(args: &Rc<Arguments>, paths: &Rc<Paths>, config: &Configuration, part: &str) -> Result<(),String> {
+    let drill_dir = format!("{}/drill/{}/{}", paths.tmp_dir, config.name, part);
+
+    // Restore the latest archive, the same as with --restore-to
+    let mut drill_args = args.as_ref().clone();
+    drill_args.is_restore = true;
+    drill_args.restore_to = Some(drill_dir.clone());
+    drill_args.archive = Some(String::from("latest"));
+    drill_args.restore_path = None;
+    drill_args.assume_yes = true;
+    let drill_args = Rc::new(drill_args);
+    let request = RestoreRequest::from_args(drill_args.as_ref())?;
+
+    let module_paths = if part == "backup" {
+        ModulePaths::for_backup_module(paths, "backup", config)
+    } else {
+        ModulePaths::for_sync_module(paths, "sync", config)
+    };
+
+    if args.dry_run {
+        dry_run!(format!("Creating drill directory '{}'", drill_dir.as_str()));
+    } else {
+        remove_drill_dir(drill_dir.as_str())?;
+        file::create_dir_if_missing(drill_dir.as_str(), true)?;
+    }
+
+    info!("Restoring the latest {} of '{}' to '{}'", part, config.name.as_str(), drill_dir.as_str());
+
+    // The files of the archive are listed as well, files excluded by the configuration are not expected after the restore
+    let restore_result = if let (Some(backup_config), "backup") = (config.backup.as_ref(), part) {
+        restore::with_backup_module(&drill_args, paths, config, backup_config, &|module| {
+            module.restore(&request)?;
+            return if args.dry_run { Ok(None) } else { module.included_files() };
+        })
+    } else if let (Some(sync_config), "sync") = (config.sync.as_ref(), part) {
+        restore::with_sync_module(&drill_args, paths, config, sync_config, &|module| {
+            module.restore(&request)?;
+            return if args.dry_run { Ok(None) } else { module.included_files() };
+        })
+    } else {
+        Err(format!("There is no {} configured", part))
+    };
+
+    let result = restore_result.and_then(|included_files| {
+        if args.dry_run {
+            dry_run!(format!("Comparing '{}' with the source of '{}'", drill_dir.as_str(), config.name.as_str()));
+            return Ok(());
+        }
+
+        // Files that changed after the last run can not be in the archive, the savedata is stored with the backup paths for both parts
+        let savedata_paths = ModulePaths::for_backup_module(paths, "backup", config);
+        let savedata = get_savedata(savedata_paths.save_data.as_str())?;
+        let entries = if part == "backup" { &savedata.lastsave } else { &savedata.lastsync };
+        let last_run = entries.values().map(|entry| entry.timestamp).max();
+
+        let included: Option<HashSet<String>> = included_files.map(|files| files.into_iter().collect());
+        compare_source(args, &module_paths.source, drill_dir.as_str(), last_run, included.as_ref())
+    });
+
+    // Clean up regardless of the result
+    if args.dry_run {
+        dry_run!(format!("Removing drill directory '{}'", drill_dir.as_str()));
+    } else {
+        log_error!(remove_drill_dir(drill_dir.as_str()));
+    }
+
+    return result;
+}
+
+fn check_restore_to(args: &Rc<Arguments>, config: &Configuration, part: &str) -> Result<(),String> {
+    return if let (Some(backup_config), "backup") = (config.backup.as_ref(), part) {
+        BackupModule::check_restore_to(backup_config.backup_type.as_str(), args.as_ref())
+    } else if let (Some(sync_config), "sync") = (config.sync.as_ref(), part) {
+        SyncModule::check_restore_to(sync_config.sync_type.as_str(), args.as_ref())
+    } else {
+        Err(format!("There is no {} configured", part))
+    };
+}
+
+fn compare_source(args: &Rc<Arguments>, source: &SourcePath, drill_dir: &str, last_run: Option<i64>, included: Option<&HashSet<String>>) -> Result<(),String> {
+    let mut comparison = Comparison::default();
+
+    match source {
+        SourcePath::Single(path) => {
+            let source_dir = docker::host_path(path.as_str(), args.no_docker)?;
+            compare_dir(args, source_dir.as_str(), drill_dir, None, last_run, included, &mut comparison)?;
+        },
+        SourcePath::Multiple(mappings) => {
+            // Multiple sources are restored into a directory per name
+            for mapping in mappings {
+                let source_dir = docker::host_path(mapping.path.as_str(), args.no_docker)?;
+                let restored_dir = format!("{}/{}", drill_dir, mapping.name);
+                compare_dir(args, source_dir.as_str(), restored_dir.as_str(), Some(mapping.name.as_str()), last_run, included, &mut comparison)?;
+            }
+        }
+    }
+
+    info!("Compared {} file(s) and the checksums of {} file(s), skipped {} file(s) changed since the last run and {} excluded file(s), {} restored file(s) are no longer in the source",
+          comparison.compared, comparison.checksums, comparison.changed, comparison.excluded, comparison.extra);
+
+    if comparison.compared == 0 && comparison.changed == 0 {
+        warn!("The source does not contain any files, the drill is meaningless");
+    }
+
+    let mut problems = vec![];
+    if !comparison.missing.is_empty() {
+        problems.push(format!("{} file(s) missing ({})", comparison.missing.len(), list_files(&comparison.missing)));
+    }
+    if !comparison.differing.is_empty() {
+        problems.push(format!("{} file(s) differ ({})", comparison.differing.len(), list_files(&comparison.differing)));
+    }
+
+    if problems.is_empty() {
+        return Ok(());
+    } else {
+        return Err(format!("Restored files do not match the source: {}", problems.join(", ")));
+    }
+}
+
+/**
+ * Compare the restored directory with the source directory
+ *  the included files are given relative to the source, with the name of the source path as prefix for multiple source paths
+ */
+fn compare_dir(args: &Rc<Arguments>, source_dir: &str, restored_dir: &str, prefix: Option<&str>, last_run: Option<i64>, included: Option<&HashSet<String>>, comparison: &mut Comparison) -> Result<(),String> {
+    debug!("Comparing restored files in '{}' with '{}'", restored_dir, source_dir);
+
+    let source_files = list_dir(source_dir)?;
+    let restored_files = if Path::new(restored_dir).is_dir() {
+        list_dir(restored_dir)?
+    } else {
+        HashMap::new()
+    };
+
+    let mut candidates = vec![];
+    for (path, source_info) in source_files.iter() {
+        if let Some(included) = included {
+            let archived_path = prefix.map_or(path.clone(), |prefix| format!("{}/{}", prefix, path));
+            if !included.contains(&archived_path) {
+                trace!("Skipping '{}' as it is not part of the archive", path);
+                comparison.excluded += 1;
+                continue;
+            }
+        }
+
+        if last_run.map_or(false, |last| source_info.mtime > last) {
+            trace!("Skipping '{}' as it changed after the last run", path);
+            comparison.changed += 1;
+            continue;
+        }
+
+        comparison.compared += 1;
+        match restored_files.get(path) {
+            None => comparison.missing.push(path.clone()),
+            Some(restored_info) if restored_info.size != source_info.size => comparison.differing.push(path.clone()),
+            Some(_) => candidates.push(path.clone())
+        }
+    }
+
+    comparison.extra += restored_files.keys()
+        .filter(|path| !source_files.contains_key(path.as_str()))
+        .count();
+
+    // Only compare the checksums of a random sample if requested
+    if let Some(sample) = args.drill_sample {
+        if sample < candidates.len() {
+            candidates = candidates.choose_multiple(&mut rand::thread_rng(), sample).cloned().collect();
+        }
+    }
+
+    for chunk in candidates.chunks(CHECKSUM_CHUNK_SIZE) {
+        let source_checksums = get_checksums(source_dir, chunk)?;
+        let restored_checksums = get_checksums(restored_dir, chunk)?;
+
+        for (path, (source_checksum, restored_checksum)) in chunk.iter().zip(source_checksums.iter().zip(restored_checksums.iter())) {
+            if source_checksum != restored_checksum {
+                comparison.differing.push(path.clone());
+            }
+        }
+        comparison.checksums += chunk.len();
+    }
+
+    return Ok(());
+}
+
+// Recursively list all regular files, keyed by the path relative to the base directory
+fn list_dir(base: &str) -> Result<HashMap<String,FileInfo>, String> {
+    let mut files = HashMap::new();
+    let mut directories = vec![String::new()];
+
+    while let Some(relative) = directories.pop() {
+        let directory = if relative.is_empty() { String::from(base) } else { format!("{}/{}", base, relative) };
+        let entries = try_result!(fs::read_dir(directory.as_str()), format!("Could not read directory '{}'", directory));
+
+        for entry in entries {
+            let entry = try_result!(entry, format!("Could not read entry in directory '{}'", directory));
+            let name = if let Some(name) = entry.file_name().to_str() {
+                String::from(name)
+            } else {
+                warn!("Skipping file with a name that is not valid UTF-8 in '{}'", directory);
+                continue;
+            };
+            let path = if relative.is_empty() { name } else { format!("{}/{}", relative, name) };
+
+            let metadata = try_result!(entry.metadata(), format!("Could not read metadata of '{}'", path));
+            if metadata.is_dir() {
+                directories.push(path);
+            } else if metadata.is_file() {
+                files.insert(path, FileInfo { size: metadata.len(), mtime: metadata.mtime() });
+            }
+        }
+    }
+
+    return Ok(files);
+}
+
+// Get the checksums in the order of the given files
+fn get_checksums(base: &str, files: &[String]) -> Result<Vec<String>,String> {
+    let mut cmd = CommandWrapper::new("sha256sum");
+    cmd.arg_str("--");
+    files.iter().for_each(|path| {
+        cmd.arg_string(format!("{}/{}", base, path));
+    });
+
+    let output = cmd.run_get_output()?;
+
+    // Special characters in the file name are escaped with a leading backslash
+    let checksums: Vec<String> = output.lines()
+        .map(|line| line.trim_start_matches('\\').split_whitespace().next().unwrap_or("").to_string())
+        .collect();
+
+    if checksums.len() != files.len() {
+        return Err(format!("Could not get the checksums of all files in '{}'", base));
+    }
+
+    return Ok(checksums);
+}
+
+fn remove_drill_dir(drill_dir: &str) -> Result<(),String> {
+    if Path::new(drill_dir).exists() {
+        try_result!(fs::remove_dir_all(drill_dir), format!("Could not remove drill directory '{}'", drill_dir));
+    }
+
+    return Ok(());
+}
+
+fn list_files(files: &[String]) -> String {
+    let mut listed: Vec<&str> = files.iter().take(MAX_LISTED_FILES).map(|file| file.as_str()).collect();
+    if files.len() > MAX_LISTED_FILES {
+        listed.push("...");
+    }
+
+    return listed.join(", ");
+}
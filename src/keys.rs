@@ -0,0 +1,221 @@
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use crate::{Arguments, dry_run, log_error, try_result, vbackup};
+use crate::modules::backup::{BackupModule, BackupWrapper};
+use crate::modules::sync::{SyncModule, SyncWrapper};
+use crate::modules::shared::archive::checked_pipeline;
+use crate::modules::shared::encryption::PublicKeyEncryption;
+use crate::util::command::CommandWrapper;
+use crate::util::io::{file, json};
+use crate::util::objects::configuration::Configuration;
+use crate::util::objects::paths::{ModulePaths, Paths};
+use crate::util::objects::recovery::RecoveryInformation;
+
+use serde::{Deserialize, Serialize};
+
+/**
+ * Only the key export is of interest here, everything else of the base configuration is read as paths
+ */
+#[derive(Deserialize)]
+struct KeyExportBase {
+    key_export: Option<KeyExportConfiguration>
+}
+
+#[derive(Deserialize)]
+struct KeyExportConfiguration {
+    path: Option<String>, // default: $save_dir/vbackup-keys.tar.<age|gpg>
+    encryption: PublicKeyEncryption
+}
+
+#[derive(Serialize)]
+struct ManifestEntry {
+    name: String,
+    part: String, // backup or sync
+    module: String,
+    repository: String,
+    key_files: Vec<String>, // relative to the root of the bundle
+    passphrase: Option<String> // where to find the passphrase, it is not part of the bundle
+}
+
+/**
+ * Collect the keys and passphrase references of all configurations into one bundle, encrypted with public keys
+ */
+pub fn main(args: Rc<Arguments>, paths: Rc<Paths>) -> Result<(),String> {
+    let base = json::from_file::<KeyExportBase>(Path::new(args.base_config.as_str()))?;
+    let export_config = base.key_export.ok_or(String::from("Exporting keys requires the 'key_export' option in the base configuration"))?;
+    export_config.encryption.validate()?;
+
+    let bundle_file = export_config.path.clone().unwrap_or(format!("{}/vbackup-keys.tar.{}", paths.save_dir, export_config.encryption.encryption_type.extension()));
+    let staging_dir = format!("{}/vbackup-keys", paths.tmp_dir);
+
+    // The keys are only unencrypted in the staging directory, which is removed afterwards
+    if !args.dry_run {
+        if file::exists(staging_dir.as_str()) {
+            try_result!(fs::remove_dir_all(staging_dir.as_str()), "Could not remove the leftover staging directory for the keys");
+        }
+        file::create_dir_if_missing(staging_dir.as_str(), true)?;
+        file::set_permission(staging_dir.as_str(), "700")?;
+    }
+
+    let mut manifest = vec![];
+    let mut failed = 0;
+
+    for config in vbackup::get_config_list(&args, paths.as_ref())? {
+        if let Some(backup_config) = config.backup.as_ref() {
+            let module_paths = ModulePaths::for_backup_module(&paths, "backup", &config);
+            let result = BackupModule::new(backup_config.backup_type.as_str(), config.name.as_str(), &backup_config.config, module_paths, &args)
+                .and_then(|mut module| {
+                    module.init()?;
+                    let result = module.recovery_information()
+                        .map(|information| information.map(|information| (String::from(module.get_module_name()), information)));
+                    log_error!(module.clear());
+                    result
+                })
+                .and_then(|information| collect(&args, &paths, &config, "backup", information, staging_dir.as_str()));
+
+            match result {
+                Ok(Some(entry)) => manifest.push(entry),
+                Ok(None) => trace!("The backup of '{}' has no keys to export", config.name.as_str()),
+                Err(err) => {
+                    error!("Could not export the keys of the backup of '{}': {}", config.name.as_str(), err);
+                    failed += 1;
+                }
+            }
+        }
+
+        if let Some(sync_config) = config.sync.as_ref() {
+            let module_paths = ModulePaths::for_sync_module(&paths, "sync", &config);
+            let result = SyncModule::new(sync_config.sync_type.as_str(), config.name.as_str(), &sync_config.config, module_paths, &args)
+                .and_then(|mut module| {
+                    module.init()?;
+                    let result = module.recovery_information()
+                        .map(|information| information.map(|information| (String::from(module.get_module_name()), information)));
+                    log_error!(module.clear());
+                    result
+                })
+                .and_then(|information| collect(&args, &paths, &config, "sync", information, staging_dir.as_str()));
+
+            match result {
+                Ok(Some(entry)) => manifest.push(entry),
+                Ok(None) => trace!("The sync of '{}' has no keys to export", config.name.as_str()),
+                Err(err) => {
+                    error!("Could not export the keys of the sync of '{}': {}", config.name.as_str(), err);
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    // An incomplete bundle must not replace the previous one, which may still hold the keys that failed now
+    let result = if failed > 0 {
+        Err(format!("Exporting keys failed for {} module(s), the previous bundle is kept", failed))
+    } else {
+        write_bundle(&args, &export_config.encryption, &manifest, staging_dir.as_str(), bundle_file.as_str())
+    };
+
+    if !args.dry_run {
+        if let Err(err) = fs::remove_dir_all(staging_dir.as_str()) {
+            error!("Could not remove the staging directory for the keys '{}': {}", staging_dir, err);
+        }
+    }
+
+    return result;
+}
+
+/*
+ * Copy the key files of a module to <staging>/<name>/<part>/ and describe them in a manifest entry
+ */
+fn collect(args: &Arguments,
+           paths: &Paths,
+           config: &Configuration,
+           part: &str,
+           information: Option<(String, RecoveryInformation)>,
+           staging_dir: &str) -> Result<Option<ManifestEntry>, String> {
+
+    let (module_name, information) = if let Some(information) = information {
+        information
+    } else {
+        return Ok(None);
+    };
+
+    let target_dir = format!("{}/{}", config.name, part);
+    if !args.dry_run && !information.key_files.is_empty() {
+        file::create_dir_if_missing(format!("{}/{}", staging_dir, target_dir).as_str(), true)?;
+    }
+
+    let mut key_files = vec![];
+    for key_file in information.key_files.iter() {
+        let file_name = get_file_name(key_file)?;
+        let target = format!("{}/{}", target_dir, file_name);
+
+        if args.dry_run {
+            dry_run!(format!("Adding '{}' to the key bundle as '{}'", key_file, target));
+        } else {
+            file::copy_file(key_file.as_str(), format!("{}/{}", staging_dir, target).as_str())?;
+        }
+
+        key_files.push(target);
+    }
+
+    let passphrase = information.passphrase_option.map(|option| {
+        format!("'{}.config.{}' in {}/volumes/{}.json", part, option, paths.config_dir, config.name)
+    });
+
+    info!("Collected {} key file(s) of the {} of '{}'", key_files.len(), part, config.name.as_str());
+
+    return Ok(Some(ManifestEntry {
+        name: config.name.clone(),
+        part: String::from(part),
+        module: module_name,
+        repository: information.repository,
+        key_files,
+        passphrase
+    }));
+}
+
+fn get_file_name(path: &str) -> Result<&str, String> {
+    return Path::new(path).file_name()
+        .and_then(|name| name.to_str())
+        .ok_or(format!("Could not get the file name of '{}'", path));
+}
+
+/*
+ * Write the manifest and pack the staging directory into the encrypted bundle
+ */
+fn write_bundle(args: &Arguments, encryption: &PublicKeyEncryption, manifest: &Vec<ManifestEntry>, staging_dir: &str, bundle_file: &str) -> Result<(), String> {
+    if manifest.is_empty() {
+        warn!("There are no keys to export");
+    }
+
+    let manifest_file = format!("{}/manifest.json", staging_dir);
+    if args.dry_run {
+        dry_run!(format!("Writing the manifest of {} module(s) to '{}'", manifest.len(), manifest_file));
+    } else {
+        json::to_file(Path::new(manifest_file.as_str()), manifest)?;
+    }
+
+    // Write to a temporary file first to keep a previous bundle if this fails
+    let tmp_file = format!("{}.tmp", bundle_file);
+    let pipeline = checked_pipeline(&[
+        format!("tar -cf - -C '{}' .", staging_dir),
+        format!("{} > '{}'", encryption.encrypt_command(&|path| String::from(path)), tmp_file)
+    ]);
+
+    let mut command = CommandWrapper::new_with_args("sh", vec!["-c"]);
+    command.arg_string(pipeline);
+    if let Err(err) = command.run_with_args(args) {
+        log_error!(file::checked_remove(tmp_file.as_str()));
+        return Err(err);
+    }
+
+    if args.dry_run {
+        dry_run!(format!("Moving '{}' to '{}'", tmp_file, bundle_file));
+    } else {
+        file::set_permission(tmp_file.as_str(), "600")?;
+        file::move_file(tmp_file.as_str(), bundle_file)?;
+    }
+
+    info!("Exported the keys of {} module(s) to '{}'", manifest.len(), bundle_file);
+    return Ok(());
+}
@@ -11,20 +11,22 @@ extern crate fs2;
 mod processing;
 mod vbackup;
 mod restore;
+mod browse;
+mod drill;
+mod keys;
+mod trigger;
+mod api;
 mod modules;
 mod util;
 
 use log::LevelFilter;
-use env_logger::Builder;
-use std::fs::OpenOptions;
-use fs2::FileExt;
-use std::os::unix::fs::OpenOptionsExt;
+use crate::util::io::lock;
 
 use std::process::exit;
 use argparse::{ArgumentParser, Store, StoreOption, StoreTrue};
 use serde::{Deserialize};
 
-#[derive(Deserialize)]
+#[derive(Deserialize,Clone)]
 pub struct Arguments {
     pub operation: String,
     pub dry_run: bool,
@@ -39,6 +41,13 @@ pub struct Arguments {
     pub override_disabled: bool,
     pub is_restore: bool,
     pub restore_to: Option<String>,
+    pub archive: Option<String>,
+    pub restore_path: Option<String>,
+    pub assume_yes: bool,
+    pub only: Option<String>,
+    pub pattern: Option<String>,
+    pub drill_sample: Option<usize>,
+    pub identity: Option<String>,
     pub show_command: bool,
     pub show_command_output: bool,
     pub hide_command: bool,
@@ -65,6 +74,13 @@ fn main() {
         override_disabled: false,
         is_restore: false,
         restore_to: None,
+        archive: None,
+        restore_path: None,
+        assume_yes: false,
+        only: None,
+        pattern: None,
+        drill_sample: None,
+        identity: None,
         show_command: false,
         show_command_output: false,
         hide_command: false,
@@ -76,11 +92,16 @@ fn main() {
         ignore_additional_check: false
     };
 
+    let mut log_target = String::from("stderr");
+    let mut log_file: Option<String> = None;
+    let mut log_file_size: u64 = 10;
+    let mut log_file_keep: usize = 5;
+
     {
         let mut parser = ArgumentParser::new();
         parser.set_description("Client to interact with a MQTT device controller");
         parser.refer(&mut args.operation)
-            .add_argument("operation", Store, "Operation to perform (run,backup,sync,list,version)")
+            .add_argument("operation", Store, "Operation to perform (run,backup,sync,verify,drill,list,ls,find,restore,export-keys,listen,serve,version)")
             .required();
         parser.refer(&mut args.name)
             .add_option(&["-n", "--name"], StoreOption, "Name of the specific backup to run");
@@ -104,6 +125,20 @@ fn main() {
             .add_option(&["--override-disabled", "--run-disabled"], StoreTrue, "Ignore the disabled status on configurations");
         parser.refer(&mut args.restore_to)
             .add_option(&["--restore-to"], StoreOption, "Restore only: Restore to the given directory");
+        parser.refer(&mut args.archive)
+            .add_option(&["--archive"], StoreOption, "Restore, ls and find: Archive to use (latest, before:DATE or the archive name)");
+        parser.refer(&mut args.restore_path)
+            .add_option(&["--path"], StoreOption, "Restore only: Only restore this path, relative to the source directory");
+        parser.refer(&mut args.assume_yes)
+            .add_option(&["-y", "--yes"], StoreTrue, "Restore only: Answer all confirmations with yes");
+        parser.refer(&mut args.only)
+            .add_option(&["--only"], StoreOption, "Restore, ls, find, verify and drill: Only use the backup or sync (backup,sync)");
+        parser.refer(&mut args.pattern)
+            .add_option(&["--pattern"], StoreOption, "Find only: Glob pattern to search for, matched against the file name or the path if it contains a slash");
+        parser.refer(&mut args.identity)
+            .add_option(&["--identity"], StoreOption, "Restore, ls, find, verify and drill: Private key file to decrypt archives and files encrypted with public keys (age or gpg)");
+        parser.refer(&mut args.drill_sample)
+            .add_option(&["--sample"], StoreOption, "Drill only: Only compare the checksums of this many randomly chosen files");
         parser.refer(&mut args.show_command)
             .add_option(&["--show-command", "--print-command"], StoreTrue, "Print the commands that are executed. Default for debug and verbose log level");
         parser.refer(&mut args.show_command_output)
@@ -120,17 +155,17 @@ fn main() {
             .add_option(&["--ignore-time-check", "--ignore-time-checks"], StoreTrue, "Disable all time checks");
         parser.refer(&mut args.ignore_additional_check)
             .add_option(&["--ignore-additional-check", "--ignore-additional-checks"],StoreTrue, "Disable all additional checks");
+        parser.refer(&mut log_target)
+            .add_option(&["--log-target"], Store, "Where to send log output (stderr,journald,syslog)");
+        parser.refer(&mut log_file)
+            .add_option(&["--log-file"], StoreOption, "Additionally write log output to the given file");
+        parser.refer(&mut log_file_size)
+            .add_option(&["--log-file-size"], Store, "Rotate the log file when it exceeds this size in MiB (0 to disable rotation)");
+        parser.refer(&mut log_file_keep)
+            .add_option(&["--log-file-keep"], Store, "Number of rotated log files to keep");
         parser.parse_args_or_exit();
     }
 
-    // all implies manual, but manual implies not all
-    if args.run_all {
-        args.run_manual = true;
-        args.run_manual_only = false;
-    } else if args.run_manual {
-        args.run_manual_only = true;
-    }
-
     let log_level = if args.verbose {
         LevelFilter::Trace
     } else if args.debug {
@@ -141,23 +176,26 @@ fn main() {
         LevelFilter::Info
     };
 
-    // set defaults for command printing
-    if !args.hide_command {
-        args.show_command_output |= args.verbose | args.debug;
-    }
-    args.show_command |= args.show_command_output;
+    normalize_arguments(&mut args);
 
-    // ignore checks implies ignoring both time checks and additional checks
-    if args.ignore_checks {
-        args.ignore_time_check = true;
-        args.ignore_additional_check = true;
-    }
+    let target = match util::logging::LogTarget::from_str(log_target.as_str()) {
+        Ok(target) => target,
+        Err(err) => {
+            eprintln!("{}", err);
+            exit(1);
+        }
+    };
 
-    // TODO: Always prints timestamps in UTC
-    Builder::new()
-        .filter_level(LevelFilter::Warn)
-        .filter_module("vbackup", log_level)
-        .init();
+    let file_options = log_file.map(|path| util::logging::LogFileOptions {
+        path,
+        max_size: log_file_size * 1024 * 1024,
+        keep: log_file_keep
+    });
+
+    if let Err(err) = util::logging::init(target, log_level, file_options) {
+        eprintln!("{}", err);
+        exit(1);
+    }
 
     run(args);
 }
@@ -173,32 +211,44 @@ fn run(args: Arguments) {
 
     info!("Starting '{}' (v{})", operation.as_str(), version);
 
-    // Ensure only one instance of this executable is running
-    let lock_file_result = OpenOptions::new()
-        .create(true) // Create file if it does not exist
-        .read(true)
-        .write(true)
-        .mode(u32::from_str_radix("600", 8).unwrap()) // Only sets mode when creating the file...
-        .open("/run/vbackup.lock");
-    if let Err(err) = lock_file_result {
-        error!("Could not access lock file for vbackup: {}", err.to_string());
-        exit(1);
+    // The trigger listener acquires the lock separately for every run it starts
+    if operation == "listen" {
+        if let Err(error) = trigger::main(args) {
+            error!("vbackup trigger listener failed: {}", error);
+            exit(3);
+        }
+        return;
     }
 
-    let lock_file = lock_file_result.unwrap();
-
-    if lock_file.try_lock_exclusive().is_err() {
-        error!("Could not acquire file lock for vbackup, is it already running?");
-        exit(2);
+    // The api server acquires the lock separately for every run it starts
+    if operation == "serve" {
+        if let Err(error) = api::main(args) {
+            error!("vbackup api server failed: {}", error);
+            exit(3);
+        }
+        return;
     }
 
+    // Ensure only one instance of this executable is running
+    let lock_file = match lock::try_acquire() {
+        Ok(Some(file)) => file,
+        Ok(None) => {
+            error!("Could not acquire file lock for vbackup, is it already running?");
+            exit(2);
+        },
+        Err(err) => {
+            error!("{}", err);
+            exit(1);
+        }
+    };
+
     let result = vbackup::main(args);
     if let Err(error) = result.as_ref() {
         error!("vbackup run failed: {}", error);
     }
 
-    if lock_file.unlock().is_err() {
-        error!("Releasing file lock failed");
+    if let Err(err) = lock::release(lock_file) {
+        error!("{}", err);
         exit(4);
     }
 
@@ -207,4 +257,29 @@ fn run(args: Arguments) {
     }
 
     info!("Done with '{}'", operation.as_str());
-}
\ No newline at end of file
+}
+
+/**
+ * Resolve options that imply other options, used for the command line and triggered runs
+ */
+pub fn normalize_arguments(args: &mut Arguments) {
+    // all implies manual, but manual implies not all
+    if args.run_all {
+        args.run_manual = true;
+        args.run_manual_only = false;
+    } else if args.run_manual {
+        args.run_manual_only = true;
+    }
+
+    // set defaults for command printing
+    if !args.hide_command {
+        args.show_command_output |= args.verbose | args.debug;
+    }
+    args.show_command |= args.show_command_output;
+
+    // ignore checks implies ignoring both time checks and additional checks
+    if args.ignore_checks {
+        args.ignore_time_check = true;
+        args.ignore_additional_check = true;
+    }
+}
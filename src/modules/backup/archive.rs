@@ -0,0 +1,485 @@
+use crate::modules::traits::Backup;
+use crate::modules::shared::archive;
+use crate::modules::shared::archive::{ArchiveInfo, LinkMode, default_link_mode, checked_pipeline};
+use crate::modules::shared::encryption::{self, EncryptionType, PublicKeyEncryption};
+use crate::util::io::{json,file};
+use crate::util::command::CommandWrapper;
+use crate::util::docker;
+use crate::util::objects::time::{ExecutionTiming};
+use crate::util::objects::paths::{ModulePaths,SourcePath};
+use crate::util::io::user::{confirm_abort, select_archive};
+use crate::util::objects::restore::{parse_time_prefix, parse_tar_listing_line, RestoreRequest, ArchiveEntry, FileEntry, latest_archive_files};
+use crate::util::objects::statistics::RunStatistics;
+use crate::Arguments;
+
+use crate::{dry_run};
+
+use serde_json::Value;
+use serde::{Deserialize};
+use core::borrow::{Borrow};
+use std::rc::Rc;
+
+/**
+ * Backup to tar archives with exchangeable compression and public key encryption
+ */
+pub struct Archive {
+    name: String,
+    config: Configuration,
+    paths: ModulePaths,
+    args: Rc<Arguments>
+}
+
+#[derive(Deserialize)]
+struct Configuration {
+    #[serde(default="default_compression")]
+    compression: Compression,
+    level: Option<u32>,
+    threads: Option<u32>,
+    encryption: Option<PublicKeyEncryption>,
+    exclude: Option<Vec<String>>,
+    #[serde(default="default_link_mode")]
+    link_mode: LinkMode
+}
+
+#[derive(Deserialize,Clone,Copy,PartialEq)]
+#[serde(rename_all="lowercase")]
+enum Compression {
+    Zstd,
+    Gzip,
+    Xz,
+    None
+}
+
+fn default_compression() -> Compression { Compression::Zstd }
+
+const COMPRESSIONS: [Compression; 4] = [Compression::Zstd, Compression::Gzip, Compression::Xz, Compression::None];
+
+impl Compression {
+    fn extension(&self) -> &'static str {
+        return match self {
+            Compression::Zstd => "tar.zst",
+            Compression::Gzip => "tar.gz",
+            Compression::Xz => "tar.xz",
+            Compression::None => "tar"
+        };
+    }
+
+    fn level_range(&self) -> Option<(u32, u32)> {
+        return match self {
+            Compression::Zstd => Some((1, 19)),
+            Compression::Gzip => Some((1, 9)),
+            Compression::Xz => Some((0, 9)),
+            Compression::None => None
+        };
+    }
+
+    fn compress_command(&self, level: Option<u32>, threads: Option<u32>) -> Option<String> {
+        let mut command = match self {
+            Compression::Zstd => String::from("zstd -q -c"),
+            Compression::Gzip => String::from("gzip -c"),
+            Compression::Xz => String::from("xz -c"),
+            Compression::None => return None
+        };
+
+        if let Some(level) = level {
+            command.push_str(format!(" -{}", level).as_str());
+        }
+
+        if let Some(threads) = threads {
+            command.push_str(format!(" -T{}", threads).as_str());
+        }
+
+        return Some(command);
+    }
+
+    fn decompress_command(&self) -> Option<String> {
+        return match self {
+            Compression::Zstd => Some(String::from("zstd -q -d -c")),
+            Compression::Gzip => Some(String::from("gzip -d -c")),
+            Compression::Xz => Some(String::from("xz -d -c")),
+            Compression::None => None
+        };
+    }
+}
+
+/**
+ * Get the format of an archive from its file name: <...>.<compression extension>[.<encryption extension>]
+ */
+fn parse_archive_name(file_name: &str) -> Option<(Compression, Option<EncryptionType>)> {
+    let encryption = EncryptionType::from_file_name(file_name);
+    let without_encryption = if let Some(encryption_type) = encryption.as_ref() {
+        file_name.strip_suffix(format!(".{}", encryption_type.extension()).as_str())?
+    } else {
+        file_name
+    };
+
+    return COMPRESSIONS.iter()
+        .find(|compression| without_encryption.ends_with(format!(".{}", compression.extension()).as_str()))
+        .map(|compression| (*compression, encryption));
+}
+
+impl Backup for Archive {
+    const MODULE_NAME: &'static str = "archive";
+
+    fn new(name: &str, config_json: &Value, paths: ModulePaths, args: &Rc<Arguments>) -> Result<Box<Self>, String> {
+        let config = json::from_value::<Configuration>(config_json.clone())?; // TODO: - clone
+
+        if let Some(level) = config.level {
+            if let Some((min, max)) = config.compression.level_range() {
+                if level < min || level > max {
+                    return Err(format!("The compression level for {} has to be between {} and {}", config.compression.extension(), min, max));
+                }
+            } else {
+                return Err(String::from("A compression level can only be set if the archive is compressed"));
+            }
+        }
+
+        if config.threads.is_some() && !(config.compression == Compression::Zstd || config.compression == Compression::Xz) {
+            return Err(String::from("The number of threads can only be set for zstd and xz compression"));
+        }
+
+        if let Some(encryption) = config.encryption.as_ref() {
+            encryption.validate()?;
+
+            if !args.no_docker {
+                encryption.validate_docker()?;
+            }
+        }
+
+        if let Some(identity) = args.identity.as_ref() {
+            if !file::exists(identity) {
+                return Err(format!("The identity file '{}' does not exist", identity));
+            }
+        }
+
+        return Ok(Box::new(Self {
+            name: String::from(name),
+            config,
+            paths,
+            args: args.clone()
+        }));
+    }
+
+    fn init(&mut self) -> Result<(), String> {
+        // Build local docker image
+        if !self.args.no_docker {
+            docker::build_image_if_missing(&self.paths.base_paths, "archive.Dockerfile", "vbackup-archive")?;
+        }
+
+        return Ok(());
+    }
+
+    fn backup(&self, timings: &Vec<ExecutionTiming>) -> Result<Option<RunStatistics>, String> {
+        let mut cmd = self.get_base_cmd(self.paths.module_data_dir.as_str());
+
+        // Relative path to backup (if docker is used)
+        let save_path = self.get_save_path()?;
+        let extension = self.get_extension();
+
+        // Temporary files for the archive and the listing of its content
+        let tmp_file_name = format!("vbackup-archive-backup.{}", extension);
+        let listing_file_name = "vbackup-archive-backup.list";
+        let tmp_backup_file_actual = format!("{}/{}", self.paths.module_data_dir, tmp_file_name);
+        let listing_file_actual = format!("{}/{}", self.paths.module_data_dir, listing_file_name);
+        let (tmp_backup_file, listing_file) = if self.args.no_docker {
+            (tmp_backup_file_actual.clone(), listing_file_actual.clone())
+        } else {
+            (format!("/savedir/{}", tmp_file_name), format!("/savedir/{}", listing_file_name))
+        };
+
+        // if the temporary files already exist (e.g. from a failed / interrupted run) delete them
+        for leftover in [tmp_backup_file_actual.as_str(), listing_file_actual.as_str()].iter() {
+            if file::checked_remove(leftover)? {
+                debug!("Deleted leftover temporary file '{}'", leftover);
+            }
+        }
+
+        let tar_exclude = self.config.exclude.as_ref().map(|exclude_list| {
+            exclude_list.iter()
+                .map(|exclude_part| format!("--exclude='{}'", exclude_part))
+                .collect::<Vec<String>>()
+                .join(" ")
+        });
+
+        // The archive might be encrypted, so the listing for the manifest is written while creating it
+        //  tar exits with 1 if files changed while reading them, which is not treated as failure
+        let mut stages = vec![format!("tar -cvv --index-file='{}' -f - -C '{}' {} . || [ $? -eq 1 ]", listing_file, save_path, tar_exclude.as_deref().unwrap_or(""))];
+        if let Some(compress_command) = self.config.compression.compress_command(self.config.level, self.config.threads) {
+            stages.push(compress_command);
+        }
+        if let Some(encryption) = self.config.encryption.as_ref() {
+            stages.push(encryption.encrypt_command(&|path| encryption.key_file_path(path, !self.args.no_docker)));
+        }
+        stages.push(format!("cat > '{}'", tmp_backup_file));
+
+        cmd.arg_string(checked_pipeline(&stages));
+
+        // Create a backup as temporary file
+        cmd.run_with_args(self.args.as_ref())?;
+
+        let archive_info = if !self.args.dry_run {
+            let listing = file::read(listing_file_actual.as_str())?;
+            let content: Vec<FileEntry> = listing.lines().filter_map(parse_tar_listing_line).collect();
+            Some(ArchiveInfo::from_content(tmp_backup_file_actual.as_str(), &content)?)
+        } else {
+            dry_run!("Collecting checksum, file count and size of the archive for the manifest");
+            None
+        };
+
+        // Archives in another format (e.g. before changing the compression) count for the timeframe as well
+        archive::store_for_timeframes(
+            tmp_backup_file_actual.as_str(),
+            extension.as_str(),
+            self.name.as_str(),
+            timings,
+            &self.paths,
+            &self.config.link_mode,
+            archive_info.as_ref(),
+            &|file_name| parse_archive_name(file_name).is_some(),
+            self.args.as_ref()
+        )?;
+
+        // Clear temporary files if still existing for some reason
+        for leftover in [tmp_backup_file_actual.as_str(), listing_file_actual.as_str()].iter() {
+            if let Err(err) = file::checked_remove(leftover) {
+                error!("Could not remove temporary file ({})", err);
+            }
+        }
+
+        return Ok(None);
+    }
+
+    fn verify(&self) -> Result<(), String> {
+        let archives = self.list_archives()?;
+
+        if archives.is_empty() {
+            return Err(String::from("There is no backup to verify"));
+        }
+
+        let mut checksum_failed = vec![];
+        let mut unverifiable = vec![];
+        let mut tests = vec![];
+        for archive in archives.iter() {
+            let has_manifest = match archive::check_manifest(self.paths.destination.as_str(), archive.id.as_str()) {
+                Ok(checked) => checked,
+                Err(err) => {
+                    error!("{}", err);
+                    checksum_failed.push(archive.id.as_str());
+                    continue;
+                }
+            };
+
+            // Encrypted archives can only be tested with the private key
+            let is_encrypted = parse_archive_name(archive.id.as_str()).map_or(false, |(_, encryption)| encryption.is_some());
+            if is_encrypted && self.args.identity.is_none() {
+                if has_manifest {
+                    debug!("Only checked the checksum of '{}', the content can only be tested with --identity", archive.id.as_str());
+                } else {
+                    unverifiable.push(archive.id.as_str());
+                }
+                continue;
+            }
+
+            let mut stages = self.get_read_stages(archive.id.as_str())?;
+            stages.push(String::from("tar -tf - > /dev/null"));
+            tests.push(format!("( {} ) 2> /dev/null || echo '{}'", checked_pipeline(&stages), archive.id.replace('\'', "'\\''")));
+        }
+
+        let failed = if tests.is_empty() {
+            String::new()
+        } else {
+            let mut cmd = self.get_base_cmd(self.paths.destination.as_str());
+            cmd.arg_string(tests.join("; "));
+
+            if self.args.dry_run {
+                dry_run!(cmd.to_string());
+                String::new()
+            } else {
+                info!("Testing {} backup archive(s)...", tests.len());
+                cmd.run_get_output()?
+            }
+        };
+
+        let mut problems = vec![];
+        if !checksum_failed.is_empty() {
+            problems.push(format!("Checksum mismatch for {} archive(s): {}", checksum_failed.len(), checksum_failed.join(", ")));
+        }
+        if !failed.trim().is_empty() {
+            let failed_list: Vec<&str> = failed.lines().collect();
+            problems.push(format!("Testing failed for {} archive(s): {}", failed_list.len(), failed_list.join(", ")));
+        }
+        if !unverifiable.is_empty() {
+            problems.push(format!("{} encrypted archive(s) without manifest can only be tested with --identity: {}", unverifiable.len(), unverifiable.join(", ")));
+        }
+
+        return if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("; "))
+        };
+    }
+
+    fn restore(&self, request: &RestoreRequest) -> Result<(), String> {
+        let archives = self.list_archives()?;
+
+        if archives.is_empty() {
+            let err = "There seems to be no backup to restore";
+            error!("{}", err);
+            return Err(String::from(err));
+        }
+
+        let selected_index = select_archive(
+            request,
+            Some("Found multiple backup files:"),
+            Some("Which backup file should be restored?"),
+            &archives,
+            &|archive: &ArchiveEntry| archive.id.as_str(),
+            &|archive: &ArchiveEntry| archive.time
+        )?;
+        let chosen_file = archives[selected_index].id.as_str();
+
+        confirm_abort(request, Some(&format!("Continue to restore '{}'?", chosen_file)))?;
+
+        // Make sure the archive is intact before anything is extracted
+        if archive::check_manifest(self.paths.destination.as_str(), chosen_file)? {
+            info!("Checksum of '{}' matches its manifest", chosen_file);
+        } else {
+            warn!("There is no checksum manifest for '{}', restoring without checking it", chosen_file);
+        }
+
+        // Archive members are stored relative to the source directory ('./...'), a directory includes its content
+        let member_option = request.path.as_ref()
+            .map(|path| format!(" '{}'", format!("./{}", path).replace('\'', "'\\''")))
+            .unwrap_or(String::new());
+
+        let mut stages = self.get_read_stages(chosen_file)?;
+        stages.push(format!("tar -xf - -C '{}'{}", self.get_save_path()?, member_option));
+
+        let mut cmd = self.get_base_cmd(self.paths.destination.as_str());
+        cmd.arg_string(checked_pipeline(&stages));
+
+        info!("Starting restore of '{}'...", chosen_file);
+        cmd.run_with_args(self.args.as_ref())?;
+        info!("Restore done.");
+
+        return Ok(());
+    }
+
+    fn list_archives(&self) -> Result<Vec<ArchiveEntry>, String> {
+        let mut archives: Vec<ArchiveEntry> = file::list_in_dir(self.paths.destination.as_str())?.iter()
+            .filter_map(|path| path.file_name().and_then(|name| name.to_str()).map(String::from))
+            .filter(|name| parse_archive_name(name.as_str()).is_some())
+            .map(|name| {
+                let time = parse_time_prefix(name.as_str(), 0, "%Y-%m-%d_%H-%M-%S", 19);
+                ArchiveEntry { id: name, time }
+            })
+            .collect();
+
+        // file names start with an ISO date and thus can be sorted by name
+        archives.sort_by(|a, b| a.id.cmp(&b.id));
+
+        return Ok(archives);
+    }
+
+    fn list_files(&self, archive: &ArchiveEntry) -> Result<Vec<FileEntry>, String> {
+        let mut stages = self.get_read_stages(archive.id.as_str())?;
+        stages.push(String::from("tar -tvf -"));
+
+        let mut cmd = self.get_base_cmd(self.paths.destination.as_str());
+        cmd.arg_string(checked_pipeline(&stages));
+
+        let output = cmd.run_get_output()?;
+        return Ok(output.lines().filter_map(parse_tar_listing_line).collect());
+    }
+
+    fn included_files(&self) -> Result<Option<Vec<String>>, String> {
+        return latest_archive_files(self.list_archives()?, &|archive| self.list_files(archive));
+    }
+
+    fn clear(&mut self) -> Result<(), String> {
+        return Ok(());
+    }
+}
+
+impl Archive {
+    fn get_extension(&self) -> String {
+        return if let Some(encryption) = self.config.encryption.as_ref() {
+            format!("{}.{}", self.config.compression.extension(), encryption.encryption_type.extension())
+        } else {
+            String::from(self.config.compression.extension())
+        };
+    }
+
+    // Commands to write the plain tar of an archive to stdout, the format is detected from the file name
+    fn get_read_stages(&self, archive_name: &str) -> Result<Vec<String>, String> {
+        let (compression, encryption) = if let Some(format) = parse_archive_name(archive_name) {
+            format
+        } else {
+            return Err(format!("Could not detect the format of '{}'", archive_name));
+        };
+
+        let contextual_backup_path = if self.args.no_docker {
+            format!("{}/{}", self.paths.destination.as_str(), archive_name)
+        } else {
+            format!("/savedir/{}", archive_name)
+        };
+
+        let mut stages = vec![format!("cat '{}'", contextual_backup_path.replace('\'', "'\\''"))];
+        if let Some(encryption_type) = encryption {
+            stages.push(encryption_type.decrypt_command(encryption::identity_path(self.args.identity.as_ref(), !self.args.no_docker).as_deref(), None)?);
+        }
+        if let Some(decompress_command) = compression.decompress_command() {
+            stages.push(decompress_command);
+        }
+
+        return Ok(stages);
+    }
+
+    fn get_base_cmd(&self, mount_path: &str) -> CommandWrapper {
+        return if self.args.no_docker {
+            let mut cmd = CommandWrapper::new("sh");
+            cmd.arg_str("-c");
+            cmd
+        } else {
+            let mut cmd = CommandWrapper::new("docker");
+
+            let mut source_overwrite = None;
+            if self.args.is_restore {
+                if let Some(restore_to) = self.args.restore_to.as_ref() {
+                    source_overwrite.replace(SourcePath::Single(restore_to.clone()));
+                }
+            }
+
+            let source_mount = source_overwrite.as_ref().unwrap_or(self.paths.source.borrow());
+
+            cmd.arg_str("run")
+                .arg_str("--rm")
+                .add_docker_volume_mapping(source_mount, "volume")
+                .arg_string(format!("--volume={}:/savedir", mount_path));
+
+            for option in encryption::key_volume_options(self.config.encryption.as_ref(), self.args.identity.as_ref()) {
+                cmd.arg_string(option);
+            }
+
+            cmd.arg_str("--name=vbackup-archive-tmp")
+                .arg_str("vbackup-archive")
+                .arg_str("sh")
+                .arg_str("-c");
+            cmd
+        };
+    }
+
+    fn get_save_path(&self) -> Result<&str, String> {
+        return if self.args.no_docker {
+            if self.args.is_restore && self.args.restore_to.is_some() {
+                Ok(self.args.restore_to.as_ref().unwrap())
+            } else if let SourcePath::Single(path) = &self.paths.source {
+                Ok(path.as_str())
+            } else {
+                Err(String::from("Multiple source paths are not supported in the archive module without docker"))
+            }
+        } else {
+            Ok("/volume")
+        }
+    }
+}
@@ -3,6 +3,9 @@ pub use crate::modules::shared::borg::Borg;
 use crate::modules::traits::Backup;
 use serde_json::Value;
 use crate::util::objects::paths::ModulePaths;
+use crate::util::objects::restore::{RestoreRequest, ArchiveEntry, FileEntry};
+use crate::util::objects::statistics::RunStatistics;
+use crate::util::objects::recovery::RecoveryInformation;
 use crate::Arguments;
 use crate::util::objects::time::ExecutionTiming;
 
@@ -13,18 +16,48 @@ impl Backup for Borg {
         Borg::new(name, config_json, paths, args, None)
     }
 
+    fn check_restore_to(args: &Arguments) -> Result<(), String> {
+        Borg::check_restore_to(args)
+    }
+
     fn init(&mut self) -> Result<(), String> {
         Borg::init(self)
     }
 
-    fn backup(&self, _time_frames: &Vec<ExecutionTiming>) -> Result<(), String> {
+    fn backup(&self, time_frames: &Vec<ExecutionTiming>) -> Result<Option<RunStatistics>, String> {
+        Borg::run_save(self, &time_frames.iter().collect::<Vec<&ExecutionTiming>>(), None)
+    }
+
+    fn verify(&self) -> Result<(), String> {
+        Borg::run_check(self)
+    }
+
+    fn maintain(&self) -> Result<(), String> {
+        Borg::run_maintenance(self)
+    }
+
+    fn check_maintenance() -> Result<(), String> {
+        Ok(())
+    }
+
+    fn restore(&self, request: &RestoreRequest) -> Result<(), String> {
+        Borg::run_restore(self, request)
+    }
+
+    fn list_archives(&self) -> Result<Vec<ArchiveEntry>, String> {
+        Borg::get_archives(self)
+    }
+
+    fn list_files(&self, archive: &ArchiveEntry) -> Result<Vec<FileEntry>, String> {
+        Borg::get_files(self, archive)
+    }
 
-        // ignore timeframes for now and just create a backup if necessary in any timeframe
-        Borg::run_save(self)
+    fn included_files(&self) -> Result<Option<Vec<String>>, String> {
+        Borg::get_latest_files(self)
     }
 
-    fn restore(&self) -> Result<(), String> {
-        Borg::run_restore(self)
+    fn recovery_information(&self) -> Result<Option<RecoveryInformation>, String> {
+        Borg::get_recovery_information(self)
     }
 
     fn clear(&mut self) -> Result<(), String> {
@@ -2,12 +2,17 @@ use std::rc::Rc;
 use crate::modules::traits::Backup;
 use crate::util::objects::time::{ExecutionTiming};
 use crate::util::objects::paths::{ModulePaths};
+use crate::util::objects::restore::{RestoreRequest, ArchiveEntry, FileEntry};
+use crate::util::objects::statistics::RunStatistics;
+use crate::util::objects::recovery::RecoveryInformation;
 use crate::Arguments;
 
 use serde_json::Value;
 
 mod tar7zip;
 mod borg;
+mod archive;
+mod native_archive;
 
 pub struct BackupModule {
     module: Box<dyn BackupWrapper>
@@ -22,6 +27,12 @@ impl BackupModule {
             <borg::Borg as Backup>::MODULE_NAME => {
                 <borg::Borg as Backup>::new(name, config_json, paths, args)?
             },
+            archive::Archive::MODULE_NAME => {
+                archive::Archive::new(name, config_json, paths, args)?
+            },
+            native_archive::NativeArchive::MODULE_NAME => {
+                native_archive::NativeArchive::new(name, config_json, paths, args)?
+            },
             unknown => {
                 let msg = format!("Unknown backup module: '{}'", unknown);
                 error!("{}", msg);
@@ -31,6 +42,32 @@ impl BackupModule {
 
         return Ok(BackupModule { module });
     }
+
+    /**
+     * Check whether the module can restore into another directory (--restore-to) without setting it up, e.g. for a drill
+     */
+    pub fn check_restore_to(backup_type: &str, args: &Arguments) -> Result<(), String> {
+        return match backup_type.to_lowercase().as_str() {
+            tar7zip::Tar7Zip::MODULE_NAME => tar7zip::Tar7Zip::check_restore_to(args),
+            <borg::Borg as Backup>::MODULE_NAME => <borg::Borg as Backup>::check_restore_to(args),
+            archive::Archive::MODULE_NAME => archive::Archive::check_restore_to(args),
+            native_archive::NativeArchive::MODULE_NAME => native_archive::NativeArchive::check_restore_to(args),
+            unknown => Err(format!("Unknown backup module: '{}'", unknown))
+        };
+    }
+
+    /**
+     * Check whether the module supports a maintenance schedule
+     */
+    pub fn check_maintenance(backup_type: &str) -> Result<(), String> {
+        return match backup_type.to_lowercase().as_str() {
+            tar7zip::Tar7Zip::MODULE_NAME => tar7zip::Tar7Zip::check_maintenance(),
+            <borg::Borg as Backup>::MODULE_NAME => <borg::Borg as Backup>::check_maintenance(),
+            archive::Archive::MODULE_NAME => archive::Archive::check_maintenance(),
+            native_archive::NativeArchive::MODULE_NAME => native_archive::NativeArchive::check_maintenance(),
+            unknown => Err(format!("Unknown backup module: '{}'", unknown))
+        };
+    }
 }
 
 impl BackupWrapper for BackupModule {
@@ -38,12 +75,36 @@ impl BackupWrapper for BackupModule {
         self.module.init()
     }
 
-    fn backup(&self, timings: &Vec<ExecutionTiming>) -> Result<(), String> {
+    fn backup(&self, timings: &Vec<ExecutionTiming>) -> Result<Option<RunStatistics>, String> {
         self.module.backup(timings)
     }
 
-    fn restore(&self) -> Result<(), String> {
-        self.module.restore()
+    fn verify(&self) -> Result<(), String> {
+        self.module.verify()
+    }
+
+    fn restore(&self, request: &RestoreRequest) -> Result<(), String> {
+        self.module.restore(request)
+    }
+
+    fn list_archives(&self) -> Result<Vec<ArchiveEntry>, String> {
+        self.module.list_archives()
+    }
+
+    fn list_files(&self, archive: &ArchiveEntry) -> Result<Vec<FileEntry>, String> {
+        self.module.list_files(archive)
+    }
+
+    fn included_files(&self) -> Result<Option<Vec<String>>, String> {
+        self.module.included_files()
+    }
+
+    fn maintain(&self) -> Result<(), String> {
+        self.module.maintain()
+    }
+
+    fn recovery_information(&self) -> Result<Option<RecoveryInformation>, String> {
+        self.module.recovery_information()
     }
 
     fn clear(&mut self) -> Result<(), String> {
@@ -57,8 +118,14 @@ impl BackupWrapper for BackupModule {
 
 pub trait BackupWrapper {
     fn init(&mut self) -> Result<(), String>;
-    fn backup(&self, time_frames: &Vec<ExecutionTiming>) -> Result<(), String>;
-    fn restore(&self) -> Result<(), String>;
+    fn backup(&self, time_frames: &Vec<ExecutionTiming>) -> Result<Option<RunStatistics>, String>;
+    fn verify(&self) -> Result<(), String>;
+    fn restore(&self, request: &RestoreRequest) -> Result<(), String>;
+    fn list_archives(&self) -> Result<Vec<ArchiveEntry>, String>;
+    fn list_files(&self, archive: &ArchiveEntry) -> Result<Vec<FileEntry>, String>;
+    fn included_files(&self) -> Result<Option<Vec<String>>, String>;
+    fn maintain(&self) -> Result<(), String>;
+    fn recovery_information(&self) -> Result<Option<RecoveryInformation>, String>;
     fn clear(&mut self) -> Result<(), String>;
     fn get_module_name(&self) -> &str;
 }
@@ -68,12 +135,36 @@ impl<T: Backup> BackupWrapper for T {
         Backup::init(self)
     }
 
-    fn backup(&self, time_frames: &Vec<ExecutionTiming>) -> Result<(), String> {
+    fn backup(&self, time_frames: &Vec<ExecutionTiming>) -> Result<Option<RunStatistics>, String> {
         Backup::backup(self, time_frames)
     }
 
-    fn restore(&self) -> Result<(), String> {
-        Backup::restore(self)
+    fn verify(&self) -> Result<(), String> {
+        Backup::verify(self)
+    }
+
+    fn restore(&self, request: &RestoreRequest) -> Result<(), String> {
+        Backup::restore(self, request)
+    }
+
+    fn list_archives(&self) -> Result<Vec<ArchiveEntry>, String> {
+        Backup::list_archives(self)
+    }
+
+    fn list_files(&self, archive: &ArchiveEntry) -> Result<Vec<FileEntry>, String> {
+        Backup::list_files(self, archive)
+    }
+
+    fn included_files(&self) -> Result<Option<Vec<String>>, String> {
+        Backup::included_files(self)
+    }
+
+    fn maintain(&self) -> Result<(), String> {
+        Backup::maintain(self)
+    }
+
+    fn recovery_information(&self) -> Result<Option<RecoveryInformation>, String> {
+        Backup::recovery_information(self)
     }
 
     fn clear(&mut self) -> Result<(), String> {
@@ -0,0 +1,947 @@
+use crate::modules::traits::Backup;
+use crate::modules::shared::archive;
+use crate::modules::shared::archive::{ArchiveInfo, LinkMode, default_link_mode};
+use crate::modules::shared::encryption::{EncryptionType, PublicKeyEncryption};
+use crate::util::command;
+use crate::util::docker;
+use crate::util::io::{json,file};
+use crate::util::objects::time::{ExecutionTiming};
+use crate::util::objects::paths::{ModulePaths,SourcePath};
+use crate::util::io::user::{confirm_abort, select_archive};
+use crate::util::objects::restore::{parse_time_prefix, RestoreRequest, ArchiveEntry, FileEntry, latest_archive_files};
+use crate::util::objects::statistics::{RunStatistics, RunOutcome};
+use crate::Arguments;
+
+use crate::{dry_run, try_result};
+
+use serde_json::Value;
+use serde::{Deserialize};
+use chrono::{Local, TimeZone};
+use glob::Pattern;
+use tar::{Builder, EntryType, Header, HeaderMode};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use std::str::FromStr;
+
+/**
+ * Backup to tar archives compressed with zstd and optionally encrypted with age, written without any external tools
+ *  The archives can be read with tar, zstd and age as well
+ */
+pub struct NativeArchive {
+    name: String,
+    config: Configuration,
+    exclude: Vec<Pattern>,
+    paths: ModulePaths,
+    args: Rc<Arguments>
+}
+
+#[derive(Deserialize)]
+struct Configuration {
+    #[serde(default="default_level")]
+    level: i32,
+    encryption: Option<PublicKeyEncryption>,
+    exclude: Option<Vec<String>>,
+    #[serde(default="default_link_mode")]
+    link_mode: LinkMode
+}
+
+fn default_level() -> i32 { 3 }
+
+const EXTENSION: &str = "tar.zst";
+
+// Archive being written, hard links refer to the first archived path of the same inode
+struct ArchiveState<W: Write> {
+    builder: Builder<W>,
+    hard_links: HashMap<(u64, u64), PathBuf>,
+    file_count: usize,
+    uncompressed_size: u64,
+    warnings: usize
+}
+
+// The output file, age encrypts the stream in chunks and needs to write the last one when finished
+enum OutputWriter {
+    Plain(File),
+    Encrypted(age::stream::StreamWriter<File>)
+}
+
+impl Write for OutputWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        return match self {
+            OutputWriter::Plain(writer) => writer.write(buf),
+            OutputWriter::Encrypted(writer) => writer.write(buf)
+        };
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return match self {
+            OutputWriter::Plain(writer) => writer.flush(),
+            OutputWriter::Encrypted(writer) => writer.flush()
+        };
+    }
+}
+
+impl OutputWriter {
+    fn finish(self) -> io::Result<File> {
+        return match self {
+            OutputWriter::Plain(writer) => Ok(writer),
+            OutputWriter::Encrypted(writer) => writer.finish()
+        };
+    }
+}
+
+/*
+ * Reads exactly the size in the header of a file, a file that shrank while reading it is padded with zeros
+ *  otherwise the size in the header would not match the data and the rest of the archive would be broken
+ */
+struct SizedReader<R: Read> {
+    inner: R,
+    remaining: u64,
+    padded: u64
+}
+
+impl<R: Read> Read for SizedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 {
+            return Ok(0);
+        }
+
+        let max = std::cmp::min(buf.len() as u64, self.remaining) as usize;
+        let mut read = self.inner.read(&mut buf[..max])?;
+        if read == 0 {
+            buf[..max].iter_mut().for_each(|byte| *byte = 0);
+            self.padded += max as u64;
+            read = max;
+        }
+
+        self.remaining -= read as u64;
+        return Ok(read);
+    }
+}
+
+impl Backup for NativeArchive {
+    const MODULE_NAME: &'static str = "native-archive";
+
+    fn new(name: &str, config_json: &Value, paths: ModulePaths, args: &Rc<Arguments>) -> Result<Box<Self>, String> {
+        let config = json::from_value::<Configuration>(config_json.clone())?; // TODO: - clone
+
+        if config.level < 1 || config.level > 19 {
+            return Err(String::from("The compression level for zstd has to be between 1 and 19"));
+        }
+
+        if let Some(encryption) = config.encryption.as_ref() {
+            encryption.validate()?;
+
+            if encryption.encryption_type != EncryptionType::Age {
+                return Err(String::from("The native-archive module only supports age encryption"));
+            }
+
+            // Fail early on invalid recipients instead of after reading the source
+            get_recipients(encryption)?;
+        }
+
+        // Without docker there are no volumes to look up
+        if args.no_docker {
+            let source_paths = match &paths.source {
+                SourcePath::Single(path) => vec![path.as_str()],
+                SourcePath::Multiple(mappings) => mappings.iter().map(|mapping| mapping.path.as_str()).collect()
+            };
+
+            if let Some(volume) = source_paths.iter().find(|path| !path.starts_with('/')) {
+                return Err(format!("The source path '{}' has to be an absolute path without docker, docker volumes are only supported with docker", volume));
+            }
+        }
+
+        let mut exclude = vec![];
+        for pattern in config.exclude.as_ref().unwrap_or(&vec![]) {
+            exclude.push(try_result!(Pattern::new(pattern.as_str()), format!("Invalid exclude pattern '{}'", pattern)));
+        }
+
+        if let Some(identity) = args.identity.as_ref() {
+            if !file::exists(identity) {
+                return Err(format!("The identity file '{}' does not exist", identity));
+            }
+        }
+
+        return Ok(Box::new(Self {
+            name: String::from(name),
+            config,
+            exclude,
+            paths,
+            args: args.clone()
+        }));
+    }
+
+    // Nothing to prepare, the archive is written without docker or external tools
+    fn init(&mut self) -> Result<(), String> {
+        return Ok(());
+    }
+
+    fn backup(&self, timings: &Vec<ExecutionTiming>) -> Result<Option<RunStatistics>, String> {
+        let extension = self.get_extension();
+        let tmp_backup_file = format!("{}/vbackup-native-archive-backup.{}", self.paths.module_data_dir, extension);
+
+        // if the temporary file already exists (e.g. from a failed / interrupted run) delete it
+        if file::checked_remove(tmp_backup_file.as_str())? {
+            debug!("Deleted leftover temporary file '{}'", tmp_backup_file);
+        }
+
+        let statistics = if !self.args.dry_run {
+            file::create_dir_if_missing(self.paths.module_data_dir.as_str(), true)?;
+
+            let result = self.write_archive(tmp_backup_file.as_str());
+            if result.is_err() {
+                if let Err(err) = file::checked_remove(tmp_backup_file.as_str()) {
+                    error!("Could not remove temporary file ({})", err);
+                }
+            }
+
+            Some(result?)
+        } else {
+            dry_run!(format!("Writing the archive of '{}' to '{}'", self.get_source_description(), tmp_backup_file));
+            None
+        };
+
+        let archive_info = if let Some((file_count, uncompressed_size, _)) = statistics.as_ref() {
+            Some(ArchiveInfo {
+                sha256: archive::get_checksum(tmp_backup_file.as_str())?,
+                file_count: *file_count,
+                uncompressed_size: *uncompressed_size
+            })
+        } else {
+            dry_run!("Collecting checksum, file count and size of the archive for the manifest");
+            None
+        };
+
+        let archive_size = if self.args.dry_run {
+            None
+        } else {
+            Some(try_result!(fs::metadata(tmp_backup_file.as_str()), "Could not read the size of the archive").len())
+        };
+
+        archive::store_for_timeframes(
+            tmp_backup_file.as_str(),
+            extension.as_str(),
+            self.name.as_str(),
+            timings,
+            &self.paths,
+            &self.config.link_mode,
+            archive_info.as_ref(),
+            &is_archive_name,
+            self.args.as_ref()
+        )?;
+
+        return Ok(statistics.map(|(file_count, uncompressed_size, warnings)| {
+            RunStatistics {
+                files_transferred: Some(file_count as u64),
+                bytes_sent: archive_size,
+                bytes_received: None,
+                speedup: archive_size.filter(|size| *size > 0).map(|size| uncompressed_size as f64 / size as f64),
+                outcome: if warnings > 0 { Some(RunOutcome::Warnings) } else { None }
+            }
+        }));
+    }
+
+    fn verify(&self) -> Result<(), String> {
+        let archives = self.list_archives()?;
+
+        if archives.is_empty() {
+            return Err(String::from("There is no backup to verify"));
+        }
+
+        let mut checksum_failed = vec![];
+        let mut failed = vec![];
+        let mut unverifiable = vec![];
+        let mut tested = 0;
+        for archive in archives.iter() {
+            let has_manifest = match archive::check_manifest(self.paths.destination.as_str(), archive.id.as_str()) {
+                Ok(checked) => checked,
+                Err(err) => {
+                    error!("{}", err);
+                    checksum_failed.push(archive.id.as_str());
+                    continue;
+                }
+            };
+
+            // Encrypted archives can only be tested with the private key
+            if is_encrypted(archive.id.as_str()) && self.args.identity.is_none() {
+                if has_manifest {
+                    debug!("Only checked the checksum of '{}', the content can only be tested with --identity", archive.id.as_str());
+                } else {
+                    unverifiable.push(archive.id.as_str());
+                }
+                continue;
+            }
+
+            if self.args.dry_run {
+                dry_run!(format!("Reading all entries of '{}'", archive.id.as_str()));
+                continue;
+            }
+
+            tested += 1;
+            if let Err(err) = self.test_archive(archive.id.as_str()) {
+                error!("Testing '{}' failed: {}", archive.id.as_str(), err);
+                failed.push(archive.id.as_str());
+            }
+        }
+
+        if tested > 0 {
+            info!("Tested {} backup archive(s)", tested);
+        }
+
+        let mut problems = vec![];
+        if !checksum_failed.is_empty() {
+            problems.push(format!("Checksum mismatch for {} archive(s): {}", checksum_failed.len(), checksum_failed.join(", ")));
+        }
+        if !failed.is_empty() {
+            problems.push(format!("Testing failed for {} archive(s): {}", failed.len(), failed.join(", ")));
+        }
+        if !unverifiable.is_empty() {
+            problems.push(format!("{} encrypted archive(s) without manifest can only be tested with --identity: {}", unverifiable.len(), unverifiable.join(", ")));
+        }
+
+        return if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("; "))
+        };
+    }
+
+    fn restore(&self, request: &RestoreRequest) -> Result<(), String> {
+        let archives = self.list_archives()?;
+
+        if archives.is_empty() {
+            let err = "There seems to be no backup to restore";
+            error!("{}", err);
+            return Err(String::from(err));
+        }
+
+        let selected_index = select_archive(
+            request,
+            Some("Found multiple backup files:"),
+            Some("Which backup file should be restored?"),
+            &archives,
+            &|archive: &ArchiveEntry| archive.id.as_str(),
+            &|archive: &ArchiveEntry| archive.time
+        )?;
+        let chosen_file = archives[selected_index].id.as_str();
+
+        confirm_abort(request, Some(&format!("Continue to restore '{}'?", chosen_file)))?;
+
+        // Make sure the archive is intact before anything is extracted
+        if archive::check_manifest(self.paths.destination.as_str(), chosen_file)? {
+            info!("Checksum of '{}' matches its manifest", chosen_file);
+        } else {
+            warn!("There is no checksum manifest for '{}', restoring without checking it", chosen_file);
+        }
+
+        if self.args.dry_run {
+            dry_run!(format!("Extracting '{}' to '{}'", chosen_file, self.get_restore_description()));
+            return Ok(());
+        }
+
+        info!("Starting restore of '{}'...", chosen_file);
+        self.extract_archive(chosen_file, request.path.as_ref().map(|path| path.trim_matches('/')))?;
+        info!("Restore done.");
+
+        return Ok(());
+    }
+
+    fn list_archives(&self) -> Result<Vec<ArchiveEntry>, String> {
+        let mut archives: Vec<ArchiveEntry> = file::list_in_dir(self.paths.destination.as_str())?.iter()
+            .filter_map(|path| path.file_name().and_then(|name| name.to_str()).map(String::from))
+            .filter(|name| is_archive_name(name))
+            .map(|name| {
+                let time = parse_time_prefix(name.as_str(), 0, "%Y-%m-%d_%H-%M-%S", 19);
+                ArchiveEntry { id: name, time }
+            })
+            .collect();
+
+        // file names start with an ISO date and thus can be sorted by name
+        archives.sort_by(|a, b| a.id.cmp(&b.id));
+
+        return Ok(archives);
+    }
+
+    fn list_files(&self, archive: &ArchiveEntry) -> Result<Vec<FileEntry>, String> {
+        let mut reader = self.open_archive(archive.id.as_str())?;
+        let mut result = vec![];
+
+        for entry in try_result!(reader.entries(), "Could not read the archive") {
+            let entry = try_result!(entry, "Could not read an entry of the archive");
+            let header = entry.header();
+            let path = try_result!(entry.path(), "Could not read the path of an entry").to_string_lossy().trim_start_matches("./").trim_end_matches('/').to_string();
+
+            if path.is_empty() || path == "." {
+                continue;
+            }
+
+            let is_dir = header.entry_type().is_dir();
+            result.push(FileEntry {
+                path,
+                size: if header.entry_type().is_file() { header.size().ok() } else { Some(0) },
+                mtime: header.mtime().ok().and_then(|mtime| Local.timestamp_opt(mtime as i64, 0).single()),
+                is_dir
+            });
+        }
+
+        return Ok(result);
+    }
+
+    fn included_files(&self) -> Result<Option<Vec<String>>, String> {
+        return latest_archive_files(self.list_archives()?, &|archive| self.list_files(archive));
+    }
+
+    fn clear(&mut self) -> Result<(), String> {
+        return Ok(());
+    }
+}
+
+impl NativeArchive {
+    fn get_extension(&self) -> String {
+        return if self.config.encryption.is_some() {
+            format!("{}.age", EXTENSION)
+        } else {
+            String::from(EXTENSION)
+        };
+    }
+
+    /*
+     * The source directories on the host and their path in the archive, multiple sources are stored in a directory with their name
+     *  docker volumes are read from their mountpoint
+     */
+    fn get_sources(&self) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+        return match &self.paths.source {
+            SourcePath::Single(path) => Ok(vec![(PathBuf::from(docker::host_path(path.as_str(), self.args.no_docker)?), PathBuf::from("."))]),
+            SourcePath::Multiple(mappings) => {
+                let mut sources = vec![];
+                for mapping in mappings {
+                    sources.push((PathBuf::from(docker::host_path(mapping.path.as_str(), self.args.no_docker)?), PathBuf::from(&mapping.name)));
+                }
+                Ok(sources)
+            }
+        };
+    }
+
+    fn get_source_description(&self) -> String {
+        return match &self.paths.source {
+            SourcePath::Single(path) => path.clone(),
+            SourcePath::Multiple(mappings) => mappings.iter()
+                .map(|mapping| mapping.path.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        };
+    }
+
+    fn get_restore_description(&self) -> String {
+        return if let Some(restore_to) = self.args.restore_to.as_ref() {
+            restore_to.clone()
+        } else {
+            self.get_source_description()
+        };
+    }
+
+    /*
+     * Write the source into the archive at the given path
+     *  Returns the number of files, their size and the number of warnings
+     */
+    fn write_archive(&self, archive_path: &str) -> Result<(usize, u64, usize), String> {
+        let output_file = try_result!(File::create(archive_path), format!("Could not create the archive '{}'", archive_path));
+        file::set_permission(archive_path, "600")?;
+
+        let output = if let Some(encryption) = self.config.encryption.as_ref() {
+            let recipients = get_recipients(encryption)?;
+            let encryptor = try_result!(age::Encryptor::with_recipients(recipients.iter().map(|recipient| recipient as &dyn age::Recipient)), "Could not set up the age encryption");
+            OutputWriter::Encrypted(try_result!(encryptor.wrap_output(output_file), "Could not start the age encryption"))
+        } else {
+            OutputWriter::Plain(output_file)
+        };
+
+        let encoder = try_result!(zstd::Encoder::new(output, self.config.level), "Could not start the zstd compression");
+        let mut state = ArchiveState {
+            builder: Builder::new(encoder),
+            hard_links: HashMap::new(),
+            file_count: 0,
+            uncompressed_size: 0,
+            warnings: 0
+        };
+
+        for (source, archive_prefix) in self.get_sources()? {
+            info!("Archiving '{}'...", source.to_string_lossy());
+            self.append_tree(&mut state, source.as_path(), archive_prefix.as_path())?;
+        }
+
+        let ArchiveState { builder, file_count, uncompressed_size, warnings, .. } = state;
+        let encoder = try_result!(builder.into_inner(), "Could not finish the tar archive");
+        let output = try_result!(encoder.finish(), "Could not finish the zstd compression");
+        let output_file = try_result!(output.finish(), "Could not finish the age encryption");
+        try_result!(output_file.sync_all(), "Could not write the archive to disk");
+
+        if warnings > 0 {
+            warn!("{} file(s) changed or vanished while creating the archive", warnings);
+        }
+
+        return Ok((file_count, uncompressed_size, warnings));
+    }
+
+    /*
+     * Walk the directory and append everything that is not excluded, directories before their content
+     */
+    fn append_tree<W: Write>(&self, state: &mut ArchiveState<W>, source: &Path, archive_prefix: &Path) -> Result<(), String> {
+        let metadata = try_result!(fs::symlink_metadata(source), format!("Could not read the source '{}'", source.to_string_lossy()));
+        if !metadata.is_dir() {
+            return Err(format!("The source '{}' is not a directory", source.to_string_lossy()));
+        }
+
+        let mut queue = vec![(source.to_path_buf(), archive_prefix.to_path_buf())];
+        while let Some((path, archive_path)) = queue.pop() {
+            if command::is_cancelled() {
+                return Err(String::from("Creating the archive was cancelled"));
+            }
+
+            if !self.append_entry(state, path.as_path(), archive_path.as_path())? {
+                continue;
+            }
+
+            if !try_result!(fs::symlink_metadata(path.as_path()), format!("Could not read '{}'", path.to_string_lossy())).is_dir() {
+                continue;
+            }
+
+            let mut children = vec![];
+            let read_dir = match fs::read_dir(path.as_path()) {
+                Ok(read_dir) => read_dir,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    warn!("The directory '{}' vanished while creating the archive", path.to_string_lossy());
+                    state.warnings += 1;
+                    continue;
+                },
+                Err(err) => return Err(format!("Could not read the directory '{}': {}", path.to_string_lossy(), err))
+            };
+
+            for child in read_dir {
+                let child = try_result!(child, format!("Could not read the directory '{}'", path.to_string_lossy()));
+                let child_archive_path = archive_path.join(child.file_name());
+
+                if self.is_excluded(child_archive_path.as_path()) {
+                    trace!("Excluding '{}'", child.path().to_string_lossy());
+                    continue;
+                }
+
+                children.push((child.path(), child_archive_path));
+            }
+
+            // The queue is a stack, sort in reverse to append the entries in order
+            children.sort_by(|a, b| b.1.cmp(&a.1));
+            queue.append(&mut children);
+        }
+
+        return Ok(());
+    }
+
+    // Patterns match the path in the archive or only the file name
+    fn is_excluded(&self, archive_path: &Path) -> bool {
+        let relative = strip_current_dir(archive_path);
+        let relative_str = relative.to_string_lossy();
+        let file_name = archive_path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+
+        return self.exclude.iter().any(|pattern| pattern.matches(relative_str.as_ref()) || pattern.matches(file_name.as_ref()));
+    }
+
+    /*
+     * Append a single file system entry without its content, returns false if it was skipped
+     */
+    fn append_entry<W: Write>(&self, state: &mut ArchiveState<W>, path: &Path, archive_path: &Path) -> Result<bool, String> {
+        let path_str = path.to_string_lossy();
+        let metadata = match fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                warn!("'{}' vanished while creating the archive", path_str);
+                state.warnings += 1;
+                return Ok(false);
+            },
+            Err(err) => return Err(format!("Could not read '{}': {}", path_str, err))
+        };
+
+        let file_type = metadata.file_type();
+        if file_type.is_socket() {
+            info!("Skipping the socket '{}', sockets can not be archived", path_str);
+            return Ok(false);
+        }
+
+        let mut header = Header::new_gnu();
+        header.set_metadata_in_mode(&metadata, HeaderMode::Complete);
+
+        // Further paths of a hard linked file only refer to the first one
+        let inode = (metadata.dev(), metadata.ino());
+        if !metadata.is_dir() && metadata.nlink() > 1 {
+            if let Some(target) = state.hard_links.get(&inode) {
+                header.set_entry_type(EntryType::Link);
+                header.set_size(0);
+                try_result!(state.builder.append_link(&mut header, archive_path, target), format!("Could not add the hard link '{}' to the archive", path_str));
+                return Ok(true);
+            }
+        }
+
+        // The extended attributes are stored in a pax header, which applies to the next entry
+        append_xattrs(state, path, archive_path)?;
+
+        if file_type.is_file() {
+            let input = match File::open(path) {
+                Ok(input) => input,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    warn!("'{}' vanished while creating the archive", path_str);
+                    state.warnings += 1;
+                    return Ok(false);
+                },
+                Err(err) => return Err(format!("Could not open '{}': {}", path_str, err))
+            };
+
+            let mut reader = SizedReader { inner: input, remaining: metadata.len(), padded: 0 };
+            try_result!(state.builder.append_data(&mut header, archive_path, &mut reader), format!("Could not add '{}' to the archive", path_str));
+
+            let changed = fs::symlink_metadata(path)
+                .map(|current| current.len() != metadata.len() || current.mtime() != metadata.mtime() || current.mtime_nsec() != metadata.mtime_nsec())
+                .unwrap_or(true);
+            if reader.padded > 0 || changed {
+                warn!("'{}' changed while creating the archive", path_str);
+                state.warnings += 1;
+            }
+
+            state.file_count += 1;
+            state.uncompressed_size += metadata.len();
+        } else if file_type.is_symlink() {
+            let target = try_result!(fs::read_link(path), format!("Could not read the target of the link '{}'", path_str));
+            header.set_size(0);
+            try_result!(state.builder.append_link(&mut header, archive_path, target), format!("Could not add the link '{}' to the archive", path_str));
+        } else {
+            // directories, fifos and devices only consist of the header
+            if file_type.is_char_device() || file_type.is_block_device() {
+                let (major, minor) = split_device_id(metadata.rdev());
+                try_result!(header.set_device_major(major), format!("Could not set the device number of '{}'", path_str));
+                try_result!(header.set_device_minor(minor), format!("Could not set the device number of '{}'", path_str));
+            }
+
+            header.set_size(0);
+            try_result!(state.builder.append_data(&mut header, archive_path, io::empty()), format!("Could not add '{}' to the archive", path_str));
+        }
+
+        if !metadata.is_dir() && metadata.nlink() > 1 {
+            state.hard_links.insert(inode, strip_current_dir(archive_path));
+        }
+
+        return Ok(true);
+    }
+
+    // Plain tar stream of an archive, the format is detected from the file name
+    fn open_archive(&self, archive_name: &str) -> Result<tar::Archive<Box<dyn Read>>, String> {
+        let path = format!("{}/{}", self.paths.destination, archive_name);
+        let input = BufReader::new(try_result!(File::open(path.as_str()), format!("Could not open the archive '{}'", path)));
+
+        let decrypted: Box<dyn Read> = if is_encrypted(archive_name) {
+            let identity = if let Some(identity) = self.args.identity.as_ref() {
+                identity
+            } else {
+                return Err(String::from("An identity file is required to decrypt age encrypted files (--identity)"));
+            };
+
+            let identity_file = try_result!(age::IdentityFile::from_file(identity.clone()), format!("Could not read the identity file '{}'", identity));
+            let identities = try_result!(identity_file.into_identities(), format!("Could not read the identities in '{}'", identity));
+            let decryptor = try_result!(age::Decryptor::new_buffered(input), format!("Could not read the age header of '{}'", archive_name));
+            Box::new(try_result!(decryptor.decrypt(identities.iter().map(|identity| identity.as_ref() as &dyn age::Identity)), format!("Could not decrypt '{}'", archive_name)))
+        } else {
+            Box::new(input)
+        };
+
+        let decoder: Box<dyn Read> = Box::new(try_result!(zstd::Decoder::new(decrypted), "Could not start the zstd decompression"));
+        return Ok(tar::Archive::new(decoder));
+    }
+
+    // Read all entries with their content, this fails on any corruption
+    fn test_archive(&self, archive_name: &str) -> Result<(), String> {
+        let mut reader = self.open_archive(archive_name)?;
+        for entry in try_result!(reader.entries(), "Could not read the archive") {
+            let mut entry = try_result!(entry, "Could not read an entry of the archive");
+            try_result!(io::copy(&mut entry, &mut io::sink()), "Could not read the content of an entry");
+        }
+
+        return Ok(());
+    }
+
+    /*
+     * Extract the archive to the source paths (or the restore-to path), optionally only the given path in the archive
+     *  Directories are finished last, such that their permissions do not prevent extracting their content
+     */
+    fn extract_archive(&self, archive_name: &str, only_path: Option<&str>) -> Result<(), String> {
+        let mut reader = self.open_archive(archive_name)?;
+        reader.set_preserve_permissions(true);
+        reader.set_preserve_ownerships(is_root());
+        reader.set_preserve_mtime(true);
+        reader.set_unpack_xattrs(true);
+        reader.set_overwrite(true);
+
+        // The sources are not needed when restoring to another directory
+        let sources = if self.args.restore_to.is_some() {
+            vec![]
+        } else {
+            self.get_sources()?
+        };
+
+        let mut directories = vec![];
+        let mut restored = HashSet::new();
+        let mut count = 0;
+        for entry in try_result!(reader.entries(), "Could not read the archive") {
+            let mut entry = try_result!(entry, "Could not read an entry of the archive");
+            let member = strip_current_dir(try_result!(entry.path(), "Could not read the path of an entry").as_ref());
+
+            if let Some(only_path) = only_path {
+                if !(member == Path::new(only_path) || member.starts_with(only_path)) {
+                    continue;
+                }
+            }
+
+            let target = if let Some(target) = self.get_restore_target(&sources, member.as_path())? {
+                target
+            } else {
+                trace!("Skipping '{}', it does not belong to any source path", member.to_string_lossy());
+                continue;
+            };
+
+            if let Some(parent) = target.parent() {
+                try_result!(fs::create_dir_all(parent), format!("Could not create the directory '{}'", parent.to_string_lossy()));
+            }
+
+            let entry_type = entry.header().entry_type();
+            if entry_type.is_dir() {
+                try_result!(fs::create_dir_all(target.as_path()), format!("Could not create the directory '{}'", target.to_string_lossy()));
+                directories.push((entry, target));
+                continue;
+            }
+
+            remove_existing(target.as_path())?;
+
+            if entry_type.is_hard_link() {
+                // The link refers to a path in the archive, which is restored to a different location
+                let link_name = try_result!(entry.link_name(), "Could not read the target of a hard link")
+                    .ok_or(format!("The hard link '{}' has no target", member.to_string_lossy()))?;
+                let link_member = strip_current_dir(link_name.as_ref());
+                let link_target = match self.get_restore_target(&sources, link_member.as_path())? {
+                    Some(link_target) if restored.contains(&link_member) => link_target,
+                    _ => {
+                        // e.g. only a path was restored, which does not contain the first path of the file
+                        warn!("Skipping the hard link '{}', its target '{}' was not restored", member.to_string_lossy(), link_member.to_string_lossy());
+                        continue;
+                    }
+                };
+                try_result!(fs::hard_link(link_target.as_path(), target.as_path()), format!("Could not create the hard link '{}'", target.to_string_lossy()));
+            } else if entry_type.is_fifo() || entry_type.is_character_special() || entry_type.is_block_special() {
+                // tar would extract these as regular files
+                create_special_file(&entry, target.as_path())?;
+                apply_xattrs(&mut entry, target.as_path())?;
+            } else {
+                try_result!(entry.unpack(target.as_path()), format!("Could not extract '{}'", target.to_string_lossy()));
+            }
+
+            restored.insert(member);
+            count += 1;
+        }
+
+        for (mut entry, target) in directories.into_iter().rev() {
+            try_result!(entry.unpack(target.as_path()), format!("Could not restore the directory '{}'", target.to_string_lossy()));
+            apply_xattrs(&mut entry, target.as_path())?;
+            count += 1;
+        }
+
+        if count == 0 {
+            return Err(String::from("Nothing was restored, the path might not exist in the archive"));
+        }
+
+        debug!("Restored {} entries", count);
+        return Ok(());
+    }
+
+    // Where a path of the archive is restored to, paths outside of the archive are rejected
+    fn get_restore_target(&self, sources: &[(PathBuf, PathBuf)], member: &Path) -> Result<Option<PathBuf>, String> {
+        if member.components().any(|component| !matches!(component, Component::Normal(_))) {
+            return Err(format!("The archive contains the invalid path '{}'", member.to_string_lossy()));
+        }
+
+        if let Some(restore_to) = self.args.restore_to.as_ref() {
+            return Ok(Some(Path::new(restore_to).join(member)));
+        }
+
+        // A single source is stored as the root of the archive
+        return Ok(sources.iter().find_map(|(source, archive_prefix)| {
+            if archive_prefix == Path::new(".") {
+                Some(source.join(member))
+            } else {
+                member.strip_prefix(archive_prefix).ok().map(|rest| source.join(rest))
+            }
+        }));
+    }
+}
+
+fn is_encrypted(archive_name: &str) -> bool {
+    return EncryptionType::from_file_name(archive_name) == Some(EncryptionType::Age);
+}
+
+/*
+ * Recipients are age public keys, or files containing one public key per line
+ */
+fn get_recipients(encryption: &PublicKeyEncryption) -> Result<Vec<age::x25519::Recipient>, String> {
+    let mut keys = vec![];
+    for recipient in encryption.recipients.iter() {
+        if recipient.starts_with('/') {
+            let content = file::read(recipient.as_str())?;
+            keys.extend(content.lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from));
+        } else {
+            keys.push(recipient.clone());
+        }
+    }
+
+    let mut recipients = vec![];
+    for key in keys {
+        recipients.push(age::x25519::Recipient::from_str(key.as_str())
+            .map_err(|err| format!("Only age public keys (age1...) are supported as recipients, could not parse '{}': {}", key, err))?);
+    }
+
+    return Ok(recipients);
+}
+
+fn strip_current_dir(path: &Path) -> PathBuf {
+    return path.components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .collect();
+}
+
+// Same encoding of the device id as glibc
+fn split_device_id(device_id: u64) -> (u32, u32) {
+    let major = ((device_id >> 32) & 0xffff_f000) | ((device_id >> 8) & 0x0000_0fff);
+    let minor = ((device_id >> 12) & 0xffff_ff00) | (device_id & 0x0000_00ff);
+    return (major as u32, minor as u32);
+}
+
+fn is_root() -> bool {
+    return unsafe { libc::geteuid() } == 0;
+}
+
+/*
+ * Store the extended attributes of a file in a pax header ('SCHILY.xattr.<name>=<value>' as GNU tar does)
+ */
+fn append_xattrs<W: Write>(state: &mut ArchiveState<W>, path: &Path, archive_path: &Path) -> Result<(), String> {
+    let names = match xattr::list(path) {
+        Ok(names) => names,
+        Err(err) => {
+            // e.g. not supported by the file system
+            trace!("Could not list the extended attributes of '{}': {}", path.to_string_lossy(), err);
+            return Ok(());
+        }
+    };
+
+    let mut records = vec![];
+    for name in names {
+        let key = format!("SCHILY.xattr.{}", name.to_string_lossy());
+        match xattr::get(path, name.as_os_str()) {
+            Ok(Some(value)) => append_pax_record(&mut records, key.as_str(), value.as_slice()),
+            Ok(None) => continue,
+            Err(err) => warn!("Could not read the extended attribute '{}' of '{}': {}", name.to_string_lossy(), path.to_string_lossy(), err)
+        }
+    }
+
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    // The name of the pax header is not used, but has to fit into the header without a long name entry
+    let file_name: String = archive_path.file_name().map(|name| name.to_string_lossy().chars().take(80).collect()).unwrap_or_default();
+    let mut header = Header::new_ustar();
+    header.set_entry_type(EntryType::XHeader);
+    header.set_mode(0o644);
+    header.set_size(records.len() as u64);
+
+    try_result!(state.builder.append_data(&mut header, format!("PaxHeaders/{}", file_name), records.as_slice()), format!("Could not add the extended attributes of '{}' to the archive", path.to_string_lossy()));
+    return Ok(());
+}
+
+// A pax record is '<length> <key>=<value>\n', where the length includes its own digits
+fn append_pax_record(records: &mut Vec<u8>, key: &str, value: &[u8]) {
+    let content_length = key.len() + value.len() + 3; // space, equals sign and newline
+    let mut length = content_length + 1;
+    while length != content_length + length.to_string().len() {
+        length = content_length + length.to_string().len();
+    }
+
+    records.extend_from_slice(format!("{} {}=", length, key).as_bytes());
+    records.extend_from_slice(value);
+    records.push(b'\n');
+}
+
+fn apply_xattrs<R: Read>(entry: &mut tar::Entry<R>, target: &Path) -> Result<(), String> {
+    let extensions = if let Some(extensions) = try_result!(entry.pax_extensions(), "Could not read the pax header of an entry") {
+        extensions
+    } else {
+        return Ok(());
+    };
+
+    for extension in extensions {
+        let extension = try_result!(extension, "Could not read the pax header of an entry");
+        if let Some(name) = extension.key_bytes().strip_prefix(b"SCHILY.xattr.") {
+            let name = String::from_utf8_lossy(name).to_string();
+            if let Err(err) = xattr::set(target, name.as_str(), extension.value_bytes()) {
+                warn!("Could not restore the extended attribute '{}' of '{}': {}", name, target.to_string_lossy(), err);
+            }
+        }
+    }
+
+    return Ok(());
+}
+
+fn remove_existing(target: &Path) -> Result<(), String> {
+    return match fs::symlink_metadata(target) {
+        Ok(metadata) if metadata.is_dir() => Err(format!("Can not restore '{}', there is a directory in its place", target.to_string_lossy())),
+        Ok(_) => {
+            try_result!(fs::remove_file(target), format!("Could not replace '{}'", target.to_string_lossy()));
+            Ok(())
+        },
+        Err(_) => Ok(())
+    };
+}
+
+fn create_special_file<R: Read>(entry: &tar::Entry<R>, target: &Path) -> Result<(), String> {
+    let header = entry.header();
+    let entry_type = header.entry_type();
+    let mode = header.mode().unwrap_or(0o644) & 0o7777;
+
+    let (file_type, device) = if entry_type.is_fifo() {
+        (libc::S_IFIFO, 0)
+    } else {
+        let major = try_result!(header.device_major(), "Could not read the device number").unwrap_or(0);
+        let minor = try_result!(header.device_minor(), "Could not read the device number").unwrap_or(0);
+        (if entry_type.is_character_special() { libc::S_IFCHR } else { libc::S_IFBLK }, libc::makedev(major, minor))
+    };
+
+    let target_c = try_result!(std::ffi::CString::new(target.as_os_str().to_string_lossy().as_bytes()), format!("Invalid path '{}'", target.to_string_lossy()));
+    if unsafe { libc::mknod(target_c.as_ptr(), file_type | mode as libc::mode_t, device) } != 0 {
+        return Err(format!("Could not create '{}': {}", target.to_string_lossy(), io::Error::last_os_error()));
+    }
+
+    if is_root() {
+        if let (Ok(uid), Ok(gid)) = (header.uid(), header.gid()) {
+            if unsafe { libc::lchown(target_c.as_ptr(), uid as libc::uid_t, gid as libc::gid_t) } != 0 {
+                warn!("Could not restore the owner of '{}': {}", target.to_string_lossy(), io::Error::last_os_error());
+            }
+        }
+    }
+
+    return Ok(());
+}
+
+// Encrypted and plain archives are both kept in the destination, independent of the current encryption setting
+fn is_archive_name(name: &str) -> bool {
+    return name.ends_with(format!(".{}", EXTENSION).as_str()) || name.ends_with(format!(".{}.age", EXTENSION).as_str());
+}
@@ -1,5 +1,7 @@
 use crate::modules::traits::Backup;
-use crate::util::io::{json,savefile,file};
+use crate::modules::shared::archive;
+use crate::modules::shared::archive::{ArchiveInfo, LinkMode, default_link_mode};
+use crate::util::io::{json,file};
 use crate::util::command::CommandWrapper;
 use crate::util::docker;
 use crate::util::objects::time::{ExecutionTiming};
@@ -10,13 +12,14 @@ use crate::{dry_run};
 
 use serde_json::Value;
 use serde::{Deserialize};
-use std::fs::{copy, remove_file};
 use core::borrow::{Borrow};
 use std::borrow::BorrowMut;
-use std::ops::Sub;
 use std::path::PathBuf;
 use std::rc::Rc;
-use crate::util::io::user::{ask_user_abort, ask_user_option_list};
+use crate::util::io::user::{confirm_abort, select_archive};
+use crate::util::objects::restore::{parse_time_prefix, parse_tar_listing_line, RestoreRequest, ArchiveEntry, FileEntry, latest_archive_files};
+use crate::util::objects::statistics::RunStatistics;
+use crate::util::objects::recovery::RecoveryInformation;
 
 pub struct Tar7Zip {
     name: String,
@@ -30,7 +33,9 @@ struct Configuration {
     encryption_key: Option<String>,
     #[serde(default="default_7z_executable")]
     executable: String,
-    exclude: Option<Vec<String>>
+    exclude: Option<Vec<String>>,
+    #[serde(default="default_link_mode")]
+    link_mode: LinkMode
 }
 
 fn default_7z_executable() -> String { String::from("7z") }
@@ -59,27 +64,30 @@ impl Backup for Tar7Zip {
         return Ok(());
     }
 
-    fn backup(&self, timings: &Vec<ExecutionTiming>) -> Result<(), String> {
+    fn backup(&self, timings: &Vec<ExecutionTiming>) -> Result<Option<RunStatistics>, String> {
         let mut cmd = self.get_base_cmd(self.paths.module_data_dir.as_str());
 
         // Relative path to backup (if docker is used)
         let save_path = self.get_save_path()?;
 
-        // File name for the temporary backup file
+        // File name for the temporary backup file and the listing of its content
         let tmp_file_name = "vbackup-tar7zip-backup.tar.7z";
-        // Path to the temporary backup file on the disk
+        let listing_file_name = "vbackup-tar7zip-backup.list";
+        // Path to the temporary files on the disk
         let tmp_backup_file_actual = format!("{}/{}", self.paths.module_data_dir, tmp_file_name);
-        // Relative path to the temporary backup file (if docker is used)
-        let tmp_backup_file = if self.args.no_docker {
-            tmp_backup_file_actual.clone()
+        let listing_file_actual = format!("{}/{}", self.paths.module_data_dir, listing_file_name);
+        // Relative path to the temporary files (if docker is used)
+        let (tmp_backup_file, listing_file) = if self.args.no_docker {
+            (tmp_backup_file_actual.clone(), listing_file_actual.clone())
         } else {
-            format!("/savedir/{}", tmp_file_name)
+            (format!("/savedir/{}", tmp_file_name), format!("/savedir/{}", listing_file_name))
         };
 
-        // if the temporary file already exists (e.g. from a failed / interrupted run) delete it
-        if file::exists(tmp_backup_file_actual.as_str()) {
-            file::remove(tmp_backup_file_actual.as_str())?;
-            debug!("Deleted leftover temporary archive");
+        // if the temporary files already exist (e.g. from a failed / interrupted run) delete them
+        for leftover in [tmp_backup_file_actual.as_str(), listing_file_actual.as_str()].iter() {
+            if file::checked_remove(leftover)? {
+                debug!("Deleted leftover temporary file '{}'", leftover);
+            }
         }
 
         // Store the password option for 7zip, if there is no password set it to an empty String
@@ -93,7 +101,8 @@ impl Backup for Tar7Zip {
                 .join(" ")
         });
 
-        let tar_command = format!("tar -cf - -C '{}' {} .", save_path, tar_exclude.as_deref().unwrap_or(""));
+        // The archive is encrypted, so the listing for the manifest is written while creating it
+        let tar_command = format!("tar -cvv --index-file='{}' -f - -C '{}' {} .", listing_file, save_path, tar_exclude.as_deref().unwrap_or(""));
 
         // Build the command for 7zip
         //  Use full path to 7z executable to avoid additional forking without the password being replaced in the process overview
@@ -106,57 +115,110 @@ impl Backup for Tar7Zip {
         // Create a backup as temporary file
         cmd.run_with_args(self.args.as_ref())?;
 
-        // Create directory for backups
-        file::create_dir_if_missing(self.paths.destination.as_str(), true)?;
-
-        {
-            let mut from: Option<String> = None;
-            for timing in timings {
-                let file_name = savefile::format_filename(&timing.execution_time, &timing.time_frame_reference, self.name.as_str(), None, Some("tar.7z"));
-                let backup_file = format!("{}/{}", self.paths.destination.as_str(), file_name);
-
-                // TODO: (?) Change permission on persisted files (currently readable by group and other due to default)?
-                if from.is_none() {
-                    if !self.args.dry_run {
-                        file::move_file(tmp_backup_file_actual.as_str(), backup_file.as_str())?;
-                    } else {
-                        dry_run!(format!("Moving file '{}' to '{}'", &tmp_backup_file_actual, &backup_file));
-                    }
-                    from = Some(backup_file);
-                } else {
-                    if !self.args.dry_run {
-                        if copy(from.as_ref().unwrap(), backup_file).is_err() {
-                            error!("Could not copy temporary backup to persistent file");
-                            continue;
-                        }
-                    } else {
-                        dry_run!(format!("Copying file '{}' to '{}'", from.as_ref().unwrap(), &backup_file));
-                    }
-                }
+        // Collect the information for the manifests once, all timeframes get the same archive
+        let archive_info = if !self.args.dry_run {
+            let listing = file::read(listing_file_actual.as_str())?;
+            let content: Vec<FileEntry> = listing.lines().filter_map(parse_tar_listing_line).collect();
+            Some(ArchiveInfo::from_content(tmp_backup_file_actual.as_str(), &content)?)
+        } else {
+            dry_run!("Collecting checksum, file count and size of the archive for the manifest");
+            None
+        };
 
-                if !self.args.dry_run {
-                    if !savefile::prune(self.paths.destination.as_str(), &timing.time_frame_reference.frame, &timing.time_frame_reference.amount)? {
-                        trace!("Amount of backups is below threshold, not removing anything");
-                    }
-                } else {
-                    dry_run!("Removing oldest file from backup in timeframe");
-                }
+        archive::store_for_timeframes(
+            tmp_backup_file_actual.as_str(),
+            "tar.7z",
+            self.name.as_str(),
+            timings,
+            &self.paths,
+            &self.config.link_mode,
+            archive_info.as_ref(),
+            &|file_name| file_name.ends_with(".tar.7z"),
+            self.args.as_ref()
+        )?;
+
+        // Clear temporary files if still existing for some reason
+        for leftover in [tmp_backup_file_actual.as_str(), listing_file_actual.as_str()].iter() {
+            if let Err(err) = file::checked_remove(leftover) {
+                error!("Could not remove temporary file ({})", err);
             }
         }
 
-        // Clear temporary file if still exists for some reason
-        if file::exists(tmp_backup_file_actual.as_str()) {
-            if let Err(err) = remove_file(tmp_backup_file_actual) {
-                error!("Could not remove temporary backup file ({})", err);
+        return Ok(None);
+    }
+
+    fn verify(&self) -> Result<(), String> {
+        let archives = self.list_archives()?;
+
+        if archives.is_empty() {
+            return Err(String::from("There is no backup to verify"));
+        }
+
+        // Check the archives against their manifests first, this does not need to decrypt anything
+        let mut checksum_failed = vec![];
+        let mut without_manifest = 0;
+        for archive in archives.iter() {
+            match archive::check_manifest(self.paths.destination.as_str(), archive.id.as_str()) {
+                Ok(true) => {},
+                Ok(false) => without_manifest += 1,
+                Err(err) => {
+                    error!("{}", err);
+                    checksum_failed.push(archive.id.as_str());
+                }
             }
         }
 
-        return Ok(());
+        if without_manifest > 0 {
+            info!("There is no checksum manifest for {} archive(s), only testing them", without_manifest);
+        }
+
+        let mut cmd = self.get_base_cmd(self.paths.destination.as_str());
+        let password_option = self.get_password_option(cmd.borrow_mut());
+
+        let archive_list = archives.iter()
+            .map(|archive| {
+                let path = if self.args.no_docker {
+                    format!("{}/{}", self.paths.destination.as_str(), archive.id)
+                } else {
+                    format!("/savedir/{}", archive.id)
+                };
+                format!("'{}'", path.replace('\'', "'\\''"))
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        // Test every archive and print the ones that failed, such that the result is known for all of them
+        cmd.arg_string(format!("for archive in {}; do 7z t {}\"$archive\" > /dev/null 2>&1 || echo \"$archive\"; done", archive_list, password_option));
+
+        let failed = if self.args.dry_run {
+            dry_run!(cmd.to_string());
+            String::new()
+        } else {
+            info!("Testing {} backup archive(s)...", archives.len());
+            cmd.run_get_output()?
+        };
+
+        let mut problems = vec![];
+        if !checksum_failed.is_empty() {
+            problems.push(format!("Checksum mismatch for {} archive(s): {}", checksum_failed.len(), checksum_failed.join(", ")));
+        }
+        if !failed.trim().is_empty() {
+            let failed_list: Vec<&str> = failed.lines().map(|line| line.rsplit('/').next().unwrap_or(line)).collect();
+            problems.push(format!("Testing failed for {} archive(s): {}", failed_list.len(), failed_list.join(", ")));
+        }
+
+        return if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("; "))
+        };
     }
 
-    fn restore(&self) -> Result<(), String> {
+    fn restore(&self, request: &RestoreRequest) -> Result<(), String> {
         // find and select backup to restore
         let mut paths = file::list_in_dir(self.paths.destination.as_str())?;
+        // there are manifests next to the archives
+        paths.retain(|path| path.to_str().map_or(false, |path| path.ends_with(".tar.7z")));
 
         if paths.is_empty() {
             let err = "There seems to be no backup to restore";
@@ -167,17 +229,29 @@ impl Backup for Tar7Zip {
         // file names start with an ISO date and thus can be sorted by name
         paths.sort();
 
-        let selected_path = ask_user_option_list(
+        let selected_index = select_archive(
+            request,
             Some("Found multiple backup files:"),
             Some("Which backup file should be restored?"),
             paths.as_ref(),
             &|path: &PathBuf| {path.to_str().unwrap_or("<can't parse path>")},
-            paths.len().sub(1)
+            &|path: &PathBuf| {
+                // file names start with the date of the backup
+                path.file_name().and_then(|name| name.to_str()).and_then(|name| parse_time_prefix(name, 0, "%Y-%m-%d_%H-%M-%S", 19))
+            }
         )?;
+        let selected_path = &paths[selected_index];
 
         let chosen_file = try_option!(selected_path.file_name().map(|name| name.to_str()).flatten(), "Could not read filename for latest file");
 
-        ask_user_abort(Some(&format!("Continue to restore '{}'?", chosen_file)))?;
+        confirm_abort(request, Some(&format!("Continue to restore '{}'?", chosen_file)))?;
+
+        // Make sure the archive is intact before anything is extracted
+        if archive::check_manifest(self.paths.destination.as_str(), chosen_file)? {
+            info!("Checksum of '{}' matches its manifest", chosen_file);
+        } else {
+            warn!("There is no checksum manifest for '{}', restoring without checking it", chosen_file);
+        }
 
         // Relative path to restore (if docker is used)
         let contextual_restore_path = self.get_save_path()?;
@@ -194,7 +268,11 @@ impl Backup for Tar7Zip {
 
         //let command_actual = format!("7z x -so {}'{}' | tar xf - -C '{}', password_option, backup_file, save_path);
         let unzip_command = format!("7z x -so {}'{}'", password_option, contextual_backup_path);
-        let untar_command = format!("tar xf - -C '{}'", contextual_restore_path);
+        // Archive members are stored relative to the source directory ('./...'), a directory includes its content
+        let member_option = request.path.as_ref()
+            .map(|path| format!(" '{}'", format!("./{}", path).replace('\'', "'\\''")))
+            .unwrap_or(String::new());
+        let untar_command = format!("tar xf - -C '{}'{}", contextual_restore_path, member_option);
         let command_actual = format!("{} | {}", unzip_command, untar_command);
 
         cmd.arg_string(command_actual);
@@ -206,12 +284,61 @@ impl Backup for Tar7Zip {
         Ok(())
     }
 
+    fn list_archives(&self) -> Result<Vec<ArchiveEntry>, String> {
+        let mut archives: Vec<ArchiveEntry> = file::list_in_dir(self.paths.destination.as_str())?.iter()
+            .filter_map(|path| path.file_name().and_then(|name| name.to_str()).map(String::from))
+            .filter(|name| name.ends_with(".tar.7z"))
+            .map(|name| {
+                let time = parse_time_prefix(name.as_str(), 0, "%Y-%m-%d_%H-%M-%S", 19);
+                ArchiveEntry { id: name, time }
+            })
+            .collect();
+
+        // file names start with an ISO date and thus can be sorted by name
+        archives.sort_by(|a, b| a.id.cmp(&b.id));
+
+        return Ok(archives);
+    }
+
+    fn list_files(&self, archive: &ArchiveEntry) -> Result<Vec<FileEntry>, String> {
+        let contextual_backup_path = if self.args.no_docker {
+            format!("{}/{}", self.paths.destination.as_str(), archive.id)
+        } else {
+            format!("/savedir/{}", archive.id)
+        };
+
+        return self.list_archive_content(self.paths.destination.as_str(), contextual_backup_path.as_str());
+    }
+
+    fn included_files(&self) -> Result<Option<Vec<String>>, String> {
+        return latest_archive_files(self.list_archives()?, &|archive| self.list_files(archive));
+    }
+
+    fn recovery_information(&self) -> Result<Option<RecoveryInformation>, String> {
+        return Ok(Some(RecoveryInformation {
+            repository: self.paths.destination.clone(),
+            key_files: vec![],
+            passphrase_option: self.config.encryption_key.as_ref().map(|_| String::from("encryption_key"))
+        }));
+    }
+
     fn clear(&mut self) -> Result<(), String> {
         return Ok(());
     }
 }
 
 impl Tar7Zip {
+    fn list_archive_content(&self, mount_path: &str, contextual_backup_path: &str) -> Result<Vec<FileEntry>, String> {
+        let mut cmd = self.get_base_cmd(mount_path);
+        let password_option = self.get_password_option(cmd.borrow_mut());
+
+        // The archive only contains the tar file, so the content has to be listed by tar
+        cmd.arg_string(format!("7z x -so {}'{}' | tar tvf -", password_option, contextual_backup_path.replace('\'', "'\\''")));
+
+        let output = cmd.run_get_output()?;
+        return Ok(output.lines().filter_map(parse_tar_listing_line).collect());
+    }
+
     fn get_base_cmd(&self, mount_path: &str) -> CommandWrapper {
         return if self.args.no_docker {
             let mut cmd = CommandWrapper::new("sh");
@@ -265,4 +392,4 @@ impl Tar7Zip {
             String::new()
         };
     }
-}
\ No newline at end of file
+}
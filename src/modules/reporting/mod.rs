@@ -1,6 +1,7 @@
 use crate::modules::traits::Reporting;
 use crate::util::objects::paths::{Paths};
 use crate::util::objects::reporting::*;
+use crate::util::objects::statistics::RunStatistics;
 use crate::Arguments;
 use crate::{log_error};
 
@@ -73,10 +74,15 @@ impl ReportingModule {
     }
 
     pub fn report_status(&mut self, run_type: RunType, name: Option<String>, status: Status) {
+        self.report_status_with_statistics(run_type, name, status, None);
+    }
+
+    pub fn report_status_with_statistics(&mut self, run_type: RunType, name: Option<String>, status: Status, statistics: Option<RunStatistics>) {
         let result = self.report(ReportEvent::Status(StatusReport {
             module: name.map(|input| String::from(input)),
             status,
-            run_type
+            run_type,
+            statistics
         }));
 
         log_error!(result);
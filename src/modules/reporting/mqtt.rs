@@ -4,30 +4,41 @@ use crate::util::io::{auth_data,json};
 use crate::util::objects::paths::{Paths};
 use crate::modules::shared::mqtt::MqttConfiguration;
 use crate::Arguments;
-use crate::{try_result, try_result_debug};
+use crate::{try_result, try_result_debug, try_option};
 use crate::modules::shared::mqtt::{get_client,qos_from_u8};
 
-use serde_json::Value;
+use serde_json::{json, Value};
 use serde::{Deserialize};
+use std::collections::HashSet;
 use std::ops::AddAssign;
 use std::rc::Rc;
 use std::thread::JoinHandle;
-use rumqttc::{Client};
+use chrono::Local;
+use rumqttc::{Client, QoS};
 
 pub struct Reporter {
     config: Configuration,
     mqtt_config: MqttConfiguration,
     client: Option<Client>,
-    join_handle: Option<JoinHandle<()>>
+    join_handle: Option<JoinHandle<()>>,
+    announced: HashSet<String>
 }
 
 #[derive(Deserialize)]
 struct Configuration {
     auth_reference: Option<String>,
     base_topic: Option<String>,
-    auth: Option<Value>
+    auth: Option<Value>,
+
+    #[serde(default="default_false")]
+    homeassistant_discovery: bool,
+    #[serde(default="default_discovery_prefix")]
+    discovery_prefix: String
 }
 
+fn default_false() -> bool { false }
+fn default_discovery_prefix() -> String { String::from("homeassistant") }
+
 impl Reporting for Reporter {
     const MODULE_NAME: &'static str = "mqtt";
 
@@ -39,7 +50,8 @@ impl Reporting for Reporter {
             config,
             mqtt_config,
             client: None,
-            join_handle: None
+            join_handle: None,
+            announced: HashSet::new()
         }));
     }
 
@@ -61,6 +73,7 @@ impl Reporting for Reporter {
 
         let qos = qos_from_u8(self.mqtt_config.qos)?;
         let mut topic = get_base_topic(&self.config, &self.mqtt_config);
+        let mut statistics = None;
 
         let message = match event {
             ReportEvent::Version(version) => {
@@ -68,6 +81,12 @@ impl Reporting for Reporter {
                 version
             },
             ReportEvent::Status(report) => {
+                if self.config.homeassistant_discovery {
+                    if let Some(name) = report.module.as_ref() {
+                        self.publish_discovery_state(name, &report, qos)?;
+                    }
+                }
+
                 if let Some(name) = report.module {
                     topic.push('/');
                     topic.add_assign(name.as_str());
@@ -75,18 +94,16 @@ impl Reporting for Reporter {
                     topic.add_assign(match report.run_type {
                         RunType::RUN => "run",
                         RunType::BACKUP => "backup",
-                        RunType::SYNC => "sync"
+                        RunType::SYNC => "sync",
+                        RunType::VERIFY => "verify",
+                        RunType::MAINTENANCE => "maintenance",
+                        RunType::DRILL => "drill"
                     });
                 }
 
-                String::from(match report.status {
-                    Status::START => "starting",
-                    Status::DONE => "done",
-                    Status::ERROR => "failed",
-                    Status::SKIP => "skipped",
-                    Status::DISABLED => "disabled",
-                    Status::MANUAL => "disabled (manual)"
-                })
+                statistics = report.statistics;
+
+                String::from(get_status_string(&report.status))
             },
             ReportEvent::Operation(operation) => {
                 match operation {
@@ -99,10 +116,22 @@ impl Reporting for Reporter {
         // TODO: Trace or debug?
         trace!("Reporting on '{}': '{}'", topic.as_str(), message);
 
+        // Transfer statistics are published next to the status they belong to
+        let statistics_topic = format!("{}/statistics", topic);
+
         if let Err(err) = self.client.as_mut().unwrap().publish(topic, qos, false, message) {
             return Err(format!("Could not send report: {}", err));
         }
 
+        if let Some(statistics) = statistics {
+            let payload = try_result!(serde_json::to_string(&statistics), "Could not serialize statistics");
+            trace!("Reporting on '{}': '{}'", statistics_topic.as_str(), payload);
+
+            if let Err(err) = self.client.as_mut().unwrap().publish(statistics_topic, qos, false, payload) {
+                return Err(format!("Could not send statistics report: {}", err));
+            }
+        }
+
         // Return whether device is available or not
         return Ok(());
     }
@@ -120,6 +149,168 @@ impl Reporting for Reporter {
     }
 }
 
+impl Reporter {
+    fn publish_discovery_state(&mut self, name: &str, report: &StatusReport, qos: QoS) -> Result<(), String> {
+        // Announce the entities for a configuration once, the first time it shows up in a report
+        if !self.announced.contains(name) {
+            self.publish_discovery_config(name, qos)?;
+            self.announced.insert(String::from(name));
+        }
+
+        let base_topic = get_base_topic(&self.config, &self.mqtt_config);
+        let now = Local::now().to_rfc3339();
+
+        let state = json!({
+            "status": get_status_string(&report.status),
+            "run_type": report.run_type.to_string(),
+            "timestamp": now
+        });
+        self.publish_retained(format!("{}/{}/state", base_topic, name), qos, state)?;
+
+        let run_type = match report.run_type {
+            RunType::BACKUP => Some("backup"),
+            RunType::SYNC => Some("sync"),
+            RunType::VERIFY => Some("verify"),
+            RunType::MAINTENANCE => Some("maintenance"),
+            RunType::DRILL => Some("drill"),
+            RunType::RUN => None
+        };
+
+        // The problem is kept per run type and only finished runs change it, a started or skipped run says nothing about the last result
+        if let (Some(run_type), Status::DONE | Status::UNCHANGED | Status::WARNING | Status::PARTIAL | Status::ERROR) = (run_type, &report.status) {
+            let problem = json!({
+                "problem": matches!(report.status, Status::ERROR | Status::PARTIAL),
+                "status": get_status_string(&report.status),
+                "timestamp": now
+            });
+            self.publish_retained(format!("{}/{}/problem_{}", base_topic, name, run_type), qos, problem)?;
+        }
+
+        // Only successful runs update the timestamp of the last backup or sync
+        if let Status::DONE | Status::UNCHANGED | Status::WARNING = report.status {
+            if let Some(run_type) = run_type {
+                let topic = format!("{}/{}/last_{}", base_topic, name, run_type);
+                let mut payload = json!({ "timestamp": now });
+                if let (Some(statistics), Some(target)) = (report.statistics.as_ref(), payload.as_object_mut()) {
+                    let value = try_result!(serde_json::to_value(statistics), "Could not serialize statistics");
+                    target.insert(String::from("statistics"), value);
+                }
+
+                self.publish_retained(topic, qos, payload)?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    fn publish_discovery_config(&mut self, name: &str, qos: QoS) -> Result<(), String> {
+        let base_topic = get_base_topic(&self.config, &self.mqtt_config);
+        let device_id = format!("vbackup_{}", sanitize_id(self.mqtt_config.user.as_str()));
+        let object_id = format!("{}_{}", device_id, sanitize_id(name));
+
+        let device = json!({
+            "identifiers": [device_id],
+            "name": format!("vbackup ({})", self.mqtt_config.user),
+            "manufacturer": "vbackup",
+            "sw_version": env!("CARGO_PKG_VERSION")
+        });
+
+        let problem = json!({
+            "value_template": "{{ 'ON' if value_json.problem else 'OFF' }}",
+            "device_class": "problem"
+        });
+
+        // (component, entity suffix, display name, state topic suffix, additional fields)
+        let entities = vec![
+            ("sensor", "status", "status", "state", json!({
+                "value_template": "{{ value_json.status }}",
+                "json_attributes_topic": format!("{}/{}/state", base_topic, name),
+                "icon": "mdi:backup-restore"
+            })),
+            ("sensor", "last_backup", "last backup", "last_backup", json!({
+                "value_template": "{{ value_json.timestamp }}",
+                "device_class": "timestamp"
+            })),
+            ("sensor", "last_sync", "last sync", "last_sync", json!({
+                "value_template": "{{ value_json.timestamp }}",
+                "device_class": "timestamp"
+            })),
+            ("sensor", "last_verify", "last verify", "last_verify", json!({
+                "value_template": "{{ value_json.timestamp }}",
+                "device_class": "timestamp"
+            })),
+            ("sensor", "last_maintenance", "last maintenance", "last_maintenance", json!({
+                "value_template": "{{ value_json.timestamp }}",
+                "device_class": "timestamp"
+            })),
+            ("sensor", "last_drill", "last drill", "last_drill", json!({
+                "value_template": "{{ value_json.timestamp }}",
+                "device_class": "timestamp"
+            })),
+            ("binary_sensor", "problem_backup", "backup problem", "problem_backup", problem.clone()),
+            ("binary_sensor", "problem_sync", "sync problem", "problem_sync", problem.clone()),
+            ("binary_sensor", "problem_verify", "verify problem", "problem_verify", problem.clone()),
+            ("binary_sensor", "problem_maintenance", "maintenance problem", "problem_maintenance", problem.clone()),
+            ("binary_sensor", "problem_drill", "drill problem", "problem_drill", problem)
+        ];
+
+        for (component, suffix, display_name, state_suffix, additional) in entities {
+            let unique_id = format!("{}_{}", object_id, suffix);
+
+            let mut payload = json!({
+                "name": format!("{} {}", name, display_name),
+                "unique_id": unique_id,
+                "object_id": unique_id,
+                "state_topic": format!("{}/{}/{}", base_topic, name, state_suffix),
+                "device": device.clone()
+            });
+
+            if let (Some(target), Some(source)) = (payload.as_object_mut(), additional.as_object()) {
+                source.iter().for_each(|(key, value)| {
+                    target.insert(key.clone(), value.clone());
+                });
+            }
+
+            let topic = format!("{}/{}/{}/{}/config", self.config.discovery_prefix, component, object_id, suffix);
+            trace!("Publishing Home Assistant discovery config on '{}'", topic.as_str());
+            self.publish_retained(topic, qos, payload)?;
+        }
+
+        return Ok(());
+    }
+
+    fn publish_retained(&mut self, topic: String, qos: QoS, payload: Value) -> Result<(), String> {
+        let client = try_option!(self.client.as_mut(), "MQTT reporter is not connected for reporting");
+
+        if let Err(err) = client.publish(topic, qos, true, payload.to_string()) {
+            return Err(format!("Could not send discovery message: {}", err));
+        }
+
+        return Ok(());
+    }
+}
+
+fn get_status_string(status: &Status) -> &'static str {
+    match status {
+        Status::START => "starting",
+        Status::DONE => "done",
+        Status::UNCHANGED => "done (no changes)",
+        Status::WARNING => "done (with warnings)",
+        Status::PARTIAL => "partial",
+        Status::ERROR => "failed",
+        Status::SKIP => "skipped",
+        Status::DISABLED => "disabled",
+        Status::MANUAL => "disabled (manual)"
+    }
+}
+
+// Home Assistant only accepts [a-zA-Z0-9_-] in object ids
+fn sanitize_id(input: &str) -> String {
+    input.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect()
+}
+
 fn get_base_topic(config: &Configuration, mqtt_config: &MqttConfiguration) -> String {
     config.base_topic.clone().unwrap_or(format!("device/{}/vbackup", mqtt_config.user))
 }
\ No newline at end of file
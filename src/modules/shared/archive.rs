@@ -0,0 +1,212 @@
+use crate::util::io::{json, file, savefile};
+use crate::util::objects::time::ExecutionTiming;
+use crate::util::objects::paths::{ModulePaths, SourcePath};
+use crate::util::objects::restore::FileEntry;
+use crate::Arguments;
+use crate::{dry_run, try_option, try_result};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/**
+ * Helpers shared by the backup modules that store archive files per timeframe
+ */
+
+// How the archive is put into the files of additional timeframes
+#[derive(Deserialize)]
+#[serde(rename_all="lowercase")]
+pub enum LinkMode {
+    Copy,
+    Hardlink,
+    Reflink
+}
+
+pub fn default_link_mode() -> LinkMode { LinkMode::Copy }
+
+impl std::fmt::Display for LinkMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LinkMode::Copy => write!(f, "copy"),
+            LinkMode::Hardlink => write!(f, "hardlink"),
+            LinkMode::Reflink => write!(f, "reflink")
+        }
+    }
+}
+
+// Metadata written next to every archive as '<archive>.json', the checksum is also written as '<archive>.sha256'
+#[derive(Serialize)]
+struct Manifest {
+    archive: String,
+    sha256: String,
+    source_paths: Vec<String>,
+    file_count: usize,
+    uncompressed_size: u64,
+    vbackup_version: String,
+    timeframe: String,
+    created: String
+}
+
+// Checksum, file count and uncompressed size of a created archive
+pub struct ArchiveInfo {
+    pub sha256: String,
+    pub file_count: usize,
+    pub uncompressed_size: u64
+}
+
+impl ArchiveInfo {
+    pub fn from_content(archive_path: &str, content: &[FileEntry]) -> Result<ArchiveInfo, String> {
+        let files: Vec<&FileEntry> = content.iter().filter(|entry| !entry.is_dir).collect();
+
+        return Ok(ArchiveInfo {
+            sha256: get_checksum(archive_path)?,
+            file_count: files.len(),
+            uncompressed_size: files.iter().map(|entry| entry.size.unwrap_or(0)).sum()
+        });
+    }
+}
+
+// Each timeframe has its own file, so pruning a timeframe only removes its own link to the data
+pub fn link_or_copy(link_mode: &LinkMode, from: &str, to: &str) -> Result<(), String> {
+    let result = match link_mode {
+        LinkMode::Copy => return file::copy_file(from, to),
+        LinkMode::Hardlink => file::hard_link(from, to),
+        LinkMode::Reflink => file::reflink(from, to)
+    };
+
+    if let Err(err) = result {
+        warn!("Falling back to copy: {}", err);
+        return file::copy_file(from, to);
+    }
+
+    return Ok(());
+}
+
+/**
+ * Store the temporary archive for every timeframe, write its manifest and prune the timeframe
+ *  The first timeframe gets the archive moved, the others a link or copy of it
+ *  is_archive tells which files of the destination count for the amount of a timeframe
+ */
+pub fn store_for_timeframes(
+    tmp_backup_file: &str,
+    extension: &str,
+    name: &str,
+    timings: &[ExecutionTiming],
+    paths: &ModulePaths,
+    link_mode: &LinkMode,
+    archive_info: Option<&ArchiveInfo>,
+    is_archive: &dyn Fn(&str) -> bool,
+    args: &Arguments
+) -> Result<(), String> {
+    // Create directory for backups
+    file::create_dir_if_missing(paths.destination.as_str(), true)?;
+
+    let mut from: Option<String> = None;
+    for timing in timings {
+        let file_name = savefile::format_filename(&timing.execution_time, &timing.time_frame_reference, name, None, Some(extension));
+        let backup_file = format!("{}/{}", paths.destination.as_str(), file_name);
+
+        // TODO: (?) Change permission on persisted files (currently readable by group and other due to default)?
+        if from.is_none() {
+            if !args.dry_run {
+                file::move_file(tmp_backup_file, backup_file.as_str())?;
+            } else {
+                dry_run!(format!("Moving file '{}' to '{}'", tmp_backup_file, &backup_file));
+            }
+            from = Some(backup_file.clone());
+        } else {
+            if !args.dry_run {
+                if let Err(err) = link_or_copy(link_mode, from.as_ref().unwrap(), backup_file.as_str()) {
+                    error!("Could not copy temporary backup to persistent file ({})", err);
+                    continue;
+                }
+            } else {
+                dry_run!(format!("Creating file '{}' from '{}' ({})", &backup_file, from.as_ref().unwrap(), link_mode));
+            }
+        }
+
+        if let Some(info) = archive_info {
+            write_manifest(backup_file.as_str(), file_name.as_str(), &paths.source, timing, info)?;
+        } else if args.dry_run {
+            dry_run!(format!("Writing manifest for '{}'", &backup_file));
+        } else {
+            debug!("No archive information for '{}', no manifest is written", &backup_file);
+        }
+
+        if !args.dry_run {
+            if !savefile::prune(paths.destination.as_str(), &timing.time_frame_reference.frame, &timing.time_frame_reference.amount, is_archive)? {
+                trace!("Amount of backups is below threshold, not removing anything");
+            }
+        } else {
+            dry_run!("Removing oldest file from backup in timeframe");
+        }
+    }
+
+    return Ok(());
+}
+
+pub fn write_manifest(backup_file: &str, file_name: &str, source: &SourcePath, timing: &ExecutionTiming, info: &ArchiveInfo) -> Result<(), String> {
+    let source_paths = match source {
+        SourcePath::Single(path) => vec![path.clone()],
+        SourcePath::Multiple(mappings) => mappings.iter().map(|mapping| mapping.path.clone()).collect()
+    };
+
+    let manifest = Manifest {
+        archive: String::from(file_name),
+        sha256: info.sha256.clone(),
+        source_paths,
+        file_count: info.file_count,
+        uncompressed_size: info.uncompressed_size,
+        vbackup_version: String::from(env!("CARGO_PKG_VERSION")),
+        timeframe: timing.time_frame_reference.frame.clone(),
+        created: timing.execution_time.to_rfc3339()
+    };
+
+    // Same format as the output of sha256sum, such that 'sha256sum -c' works in the backup directory
+    file::write(format!("{}.sha256", backup_file).as_str(), format!("{}  {}\n", info.sha256, file_name).as_str(), true)?;
+    json::to_file(Path::new(format!("{}.json", backup_file).as_str()), &manifest)?;
+
+    return Ok(());
+}
+
+// Returns false if there is no manifest, e.g. for archives created by older versions
+pub fn check_manifest(directory: &str, archive_name: &str) -> Result<bool, String> {
+    let archive_path = format!("{}/{}", directory, archive_name);
+    let checksum_path = format!("{}.sha256", archive_path);
+
+    if !file::exists(checksum_path.as_str()) {
+        return Ok(false);
+    }
+
+    let content = file::read(checksum_path.as_str())?;
+    let expected = try_option!(content.split_whitespace().next(), format!("Checksum manifest of '{}' is empty", archive_name));
+
+    if get_checksum(archive_path.as_str())? == expected {
+        return Ok(true);
+    } else {
+        return Err(format!("Checksum of '{}' does not match its manifest", archive_name));
+    }
+}
+
+pub fn get_checksum(path: &str) -> Result<String, String> {
+    let mut input = try_result!(File::open(path), format!("Could not open '{}' to get its checksum", path));
+    let mut hasher = Sha256::new();
+    try_result!(io::copy(&mut input, &mut hasher), format!("Could not read '{}' to get its checksum", path));
+
+    return Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect());
+}
+
+/**
+ * Join the commands to a shell pipeline that fails if any of the commands fails
+ *  Without pipefail (not available in every sh) only the exit code of the last command would count
+ */
+pub fn checked_pipeline(stages: &[String]) -> String {
+    let pipeline = stages.iter()
+        .map(|stage| format!("{{ {} || echo $? >> \"$failed\"; }}", stage))
+        .collect::<Vec<String>>()
+        .join(" | ");
+
+    return format!("failed=$(mktemp) && {}; result=$(cat \"$failed\"); rm -f \"$failed\"; [ -z \"$result\" ]", pipeline);
+}
@@ -5,12 +5,19 @@ use crate::util::objects::paths::{ModulePaths, SourcePath};
 use crate::Arguments;
 use crate::util::command::CommandWrapper;
 use std::borrow::Borrow;
-use std::ops::Sub;
 use std::rc::Rc;
+use chrono::Local;
 use crate::modules::sync::borg::BorgSyncConfig;
 use crate::util::docker;
 use crate::modules::shared::ssh::{write_known_hosts, write_identity_file};
-use crate::util::io::user::{ask_user_abort, ask_user_option_list_index};
+use crate::util::io::user::{confirm_abort, select_archive};
+use crate::util::objects::restore::{parse_time_prefix, parse_time_formats, RestoreRequest, ArchiveEntry, FileEntry, latest_archive_files};
+use crate::util::objects::bandwidth::to_kibibytes;
+use crate::util::objects::statistics::RunStatistics;
+use crate::util::objects::time::ExecutionTiming;
+use crate::util::objects::recovery::RecoveryInformation;
+use crate::util::io::savefile::time_format;
+use crate::{dry_run, try_result};
 
 #[derive(Deserialize)]
 struct BorgKeepConfig {
@@ -24,6 +31,20 @@ struct BorgKeepConfig {
     yearly: Option<u8>
 }
 
+#[derive(Deserialize)]
+struct BorgMaintenanceConfig {
+    #[serde(default="default_true")]
+    compact: bool,
+    #[serde(default="default_true")]
+    check: bool,
+    #[serde(default="default_false")]
+    verify_data: bool,
+    #[serde(default="default_false")]
+    repair: bool,
+    #[serde(default="default_false")]
+    lift_append_only: bool
+}
+
 #[derive(Deserialize)]
 struct BorgConfig {
     encryption_key: Option<String>,
@@ -42,11 +63,17 @@ struct BorgConfig {
     exclude: Option<Vec<String>>,
     additional_options: Option<Vec<String>>,
 
-    keep: BorgKeepConfig,
+    keep: Option<BorgKeepConfig>, // default: keep the amount of archives of each timeframe
     #[serde(default="default_false")]
     disable_prune: bool,
     #[serde(default="default_false")]
     relocate_ok: bool,
+    #[serde(default="default_false")]
+    verify_data: bool,
+    #[serde(default="default_false")]
+    recovery_kit: bool,
+    #[serde(default="default_maintenance")]
+    maintenance: BorgMaintenanceConfig, // runs on the maintenance schedule of the backup or sync configuration
 
     #[serde(default="default_umask")]
     umask: String
@@ -55,8 +82,36 @@ struct BorgConfig {
 fn default_true() -> bool { true }
 fn default_false() -> bool { false }
 fn default_umask() -> String { String::from("0007") }
+fn default_maintenance() -> BorgMaintenanceConfig {
+    BorgMaintenanceConfig {
+        compact: true,
+        check: true,
+        verify_data: false,
+        repair: false,
+        lift_append_only: false
+    }
+}
+
+// Only the statistics of the archive are of interest in the output of 'borg create --json'
+#[derive(Deserialize)]
+struct BorgCreateOutput {
+    archive: BorgCreateArchive
+}
+
+#[derive(Deserialize)]
+struct BorgCreateArchive {
+    stats: BorgArchiveStats
+}
+
+#[derive(Deserialize)]
+struct BorgArchiveStats {
+    nfiles: u64,
+    original_size: u64,
+    deduplicated_size: u64
+}
 
 pub struct Borg {
+    name: String,
     config: BorgConfig,
     sync_config: Option<BorgSyncConfig>,
     paths: ModulePaths,
@@ -65,14 +120,28 @@ pub struct Borg {
 }
 
 impl Borg {
-    pub fn new(_name: &str, config_json: &Value, paths: ModulePaths, args: &Rc<Arguments>, sync_config: Option<BorgSyncConfig>) -> Result<Box<Self>, String> {
+    pub fn check_restore_to(args: &Arguments) -> Result<(), String> {
+        if args.no_docker {
+            return Err(String::from("The restore-to option is not supported for borg without docker"));
+        }
+
+        return Ok(());
+    }
+
+    pub fn new(name: &str, config_json: &Value, paths: ModulePaths, args: &Rc<Arguments>, sync_config: Option<BorgSyncConfig>) -> Result<Box<Self>, String> {
         let config = json::from_value::<BorgConfig>(config_json.clone())?; // TODO: - clone
 
-        if args.no_docker && args.is_restore && args.restore_to.is_some() {
-            return Err(String::from("The restore-to option is not supported for borg without docker"));
+        if args.is_restore && args.restore_to.is_some() {
+            Borg::check_restore_to(args)?;
+        }
+
+        // borg config only works on local repositories
+        if config.maintenance.lift_append_only && sync_config.is_some() {
+            return Err(String::from("Lifting the append-only mode for maintenance is only supported for local repositories"));
         }
 
         return Ok(Box::new(Self {
+            name: String::from(name),
             config,
             sync_config,
             paths,
@@ -118,22 +187,7 @@ impl Borg {
         let mut command = self.get_base_cmd("init")?;
 
         command.arg_str("--encryption");
-
-        if self.config.encryption_key.is_some() {
-            if self.config.blake2 {
-                command.arg_str("repokey-blake2");
-            } else {
-                command.arg_str("repokey");
-            }
-        } else if self.config.authentication_key.is_some() {
-            if self.config.blake2 {
-                command.arg_str("authenticated-blake2");
-            } else {
-                command.arg_str("authenticated");
-            }
-        } else {
-            command.arg_str("none");
-        }
+        command.arg_str(self.get_encryption_mode());
 
         if let Some(quota) = self.config.quota.as_ref() {
             command.arg_string(format!("--storage-quota={}", quota));
@@ -149,59 +203,187 @@ impl Borg {
         return command.run_with_args(self.args.as_ref());
     }
 
-    fn run_prune(&self) -> Result<(), String> {
-        let mut command = self.get_base_cmd("prune")?;
+    fn get_encryption_mode(&self) -> &'static str {
+        return if self.config.encryption_key.is_some() {
+            if self.config.blake2 { "repokey-blake2" } else { "repokey" }
+        } else if self.config.authentication_key.is_some() {
+            if self.config.blake2 { "authenticated-blake2" } else { "authenticated" }
+        } else {
+            "none"
+        };
+    }
 
-        /*
-         * limit to proper prefix
-         */
-        if let Some(prefix) = self.config.prefix.as_ref() {
-            command.arg_string(format!("--glob-archives=vbackup_{}_*", prefix));
+    // The key of a repository is only stored in the repository itself, unless it is exported
+    fn uses_key(&self) -> bool {
+        return self.config.encryption_key.is_some() || self.config.authentication_key.is_some();
+    }
+
+    fn get_keys_dir(&self) -> String {
+        return format!("{}/keys", self.paths.module_data_dir);
+    }
+
+    /*
+     * Export the key of the repository to the module data directory, as file and as paper key for printing
+     *  without the key a damaged repository header means that all backups are lost
+     */
+    fn export_key(&self) -> Result<(), String> {
+        let keys_dir = self.get_keys_dir();
+        let keys_dir_in_cmd = if self.args.no_docker {
+            keys_dir.clone()
         } else {
-            command.arg_str("--glob-archives=vbackup_*");
+            String::from("/module/keys")
+        };
+
+        if !self.args.dry_run {
+            file::create_dir_if_missing(keys_dir.as_str(), true)?;
         }
 
-        /*
-         * add options
-         */
+        for (file_name, paper) in [("repokey", false), ("repokey.txt", true)].iter() {
+            let mut command = self.get_base_cmd("key")?;
+            command.arg_str("export");
+            if *paper {
+                command.arg_str("--paper");
+            }
+            command.arg_string(self.get_repo_path());
+            command.arg_string(format!("{}/{}", keys_dir_in_cmd, file_name));
+
+            command.run_with_args(self.args.as_ref())?;
+
+            if !self.args.dry_run {
+                file::set_permission(format!("{}/{}", keys_dir, file_name).as_str(), "600")?;
+            }
+        }
+
+        if self.config.recovery_kit {
+            self.write_recovery_kit()?;
+        }
+
+        info!("Exported the key of the borg repository to '{}'", keys_dir);
+        return Ok(());
+    }
+
+    /*
+     * Everything needed to access the repository from a new host in one text file, except for the passphrase
+     */
+    fn write_recovery_kit(&self) -> Result<(), String> {
+        let keys_dir = self.get_keys_dir();
+        let kit_file = format!("{}/recovery-kit.txt", keys_dir);
+
         if self.args.dry_run {
-            command.arg_str("--dry-run");
+            dry_run!(format!("Writing the recovery kit to '{}'", kit_file));
+            return Ok(());
         }
 
-        if self.args.verbose {
-            command.arg_str("--stats");
-            command.arg_str("--list");
+        let key = file::read(format!("{}/repokey", keys_dir).as_str())?;
+        let paper_key = file::read(format!("{}/repokey.txt", keys_dir).as_str())?;
+        let location = self.get_repo_location();
+
+        let kit = format!("vbackup recovery kit for '{}' (created {})\n\n\
+            Repository: {}\n\
+            Encryption: {}\n\
+            Passphrase: stored as '{}' in the configuration of '{}'\n\n\
+            Import the key into a new installation with 'borg key import {} <file with the key below>'\n\
+            or type in the paper key with 'borg key import --paper {}'.\n\n\
+            Key:\n{}\n\n\
+            Paper key:\n{}\n",
+            self.name, time_format(&Local::now()),
+            location,
+            self.get_encryption_mode(),
+            self.get_passphrase_option().unwrap_or("-"), self.name,
+            location,
+            location,
+            key.trim_end(),
+            paper_key.trim_end());
+
+        file::write_with_perm(kit_file.as_str(), "600", kit.as_str(), true)?;
+        return Ok(());
+    }
+
+    fn get_passphrase_option(&self) -> Option<&'static str> {
+        return if self.config.encryption_key.is_some() {
+            Some("encryption_key")
+        } else if self.config.authentication_key.is_some() {
+            Some("authentication_key")
+        } else {
+            None
+        };
+    }
+
+    pub fn get_recovery_information(&self) -> Result<Option<RecoveryInformation>, String> {
+        let mut key_files = vec![];
+
+        if self.uses_key() {
+            let keys_dir = self.get_keys_dir();
+            if !file::exists(format!("{}/repokey", keys_dir).as_str()) {
+                self.export_key()?;
+            }
+
+            for file_name in ["repokey", "repokey.txt", "recovery-kit.txt"].iter() {
+                let path = format!("{}/{}", keys_dir, file_name);
+                if file::exists(path.as_str()) {
+                    key_files.push(path);
+                }
+            }
         }
 
-        if let Some(within) = self.config.keep.within.as_ref() {
+        return Ok(Some(RecoveryInformation {
+            repository: self.get_repo_location(),
+            key_files,
+            passphrase_option: self.get_passphrase_option().map(String::from)
+        }));
+    }
+
+    fn run_prune(&self, timings: &[&ExecutionTiming]) -> Result<(), String> {
+        let keep = if let Some(keep) = self.config.keep.as_ref() {
+            keep
+        } else {
+            // without explicit rules the archives of each timeframe are pruned on their own, keeping the amount of the timeframe
+            for timing in timings {
+                let mut command = self.get_prune_cmd(format!("{}_{}_*", self.get_archive_prefix(), timing.time_frame_reference.frame).as_str())?;
+                command.arg_string(format!("--keep-last={}", timing.time_frame_reference.amount));
+                command.arg_string(self.get_repo_path());
+
+                command.run_with_args(self.args.as_ref())?;
+            }
+
+            /*
+             * archives created before the timeframe was part of the name (<prefix>_<date>) are left alone,
+             *  only the timeframes due in this run are known here, which could delete archives a longer timeframe still needs
+             */
+            return Ok(());
+        };
+
+        let mut command = self.get_prune_cmd(format!("{}_*", self.get_archive_prefix()).as_str())?;
+
+        if let Some(within) = keep.within.as_ref() {
             command.arg_string(format!("--keep-within={}", within));
         }
 
-        if let Some(secondly) = self.config.keep.secondly {
+        if let Some(secondly) = keep.secondly {
             command.arg_string(format!("--keep-secondly={}", secondly));
         }
 
-        if let Some(minutely) = self.config.keep.minutely {
+        if let Some(minutely) = keep.minutely {
             command.arg_string(format!("--keep-minutely={}", minutely));
         }
 
-        if let Some(hourly) = self.config.keep.hourly {
+        if let Some(hourly) = keep.hourly {
             command.arg_string(format!("--keep-hourly={}", hourly));
         }
 
-        if let Some(daily) = self.config.keep.daily {
+        if let Some(daily) = keep.daily {
             command.arg_string(format!("--keep-daily={}", daily));
         }
 
-        if let Some(weekly) = self.config.keep.weekly {
+        if let Some(weekly) = keep.weekly {
             command.arg_string(format!("--keep-weekly={}", weekly));
         }
 
-        if let Some(monthly) = self.config.keep.monthly {
+        if let Some(monthly) = keep.monthly {
             command.arg_string(format!("--keep-monthly={}", monthly));
         }
 
-        if let Some(yearly) = self.config.keep.yearly {
+        if let Some(yearly) = keep.yearly {
             command.arg_string(format!("--keep-yearly={}", yearly));
         }
 
@@ -210,7 +392,34 @@ impl Borg {
         return command.run_with_args(self.args.as_ref());
     }
 
-    pub fn run_save(&self) -> Result<(), String> {
+    fn get_prune_cmd(&self, archive_glob: &str) -> Result<CommandWrapper, String> {
+        let mut command = self.get_base_cmd("prune")?;
+
+        /*
+         * limit to proper prefix
+         */
+        command.arg_string(format!("--glob-archives={}", archive_glob));
+
+        /*
+         * add options
+         */
+        if self.args.dry_run {
+            command.arg_str("--dry-run");
+        }
+
+        if self.args.verbose {
+            command.arg_str("--stats");
+            command.arg_str("--list");
+        }
+
+        return Ok(command);
+    }
+
+    /*
+     * Create an archive for every timeframe, borg only stores the data once
+     *  the statistics are the ones of the first archive, as the following archives do not add any data
+     */
+    pub fn run_save(&self, timings: &[&ExecutionTiming], bandwidth_limit: Option<u64>) -> Result<Option<RunStatistics>, String> {
         /*
          * Init repository if necessary
          */
@@ -222,6 +431,35 @@ impl Borg {
             }
         }
 
+        // Export the key right after init, or for repositories that were initialized before
+        if self.uses_key() && !file::exists(format!("{}/repokey", self.get_keys_dir()).as_str()) {
+            if let Err(err) = self.export_key() {
+                error!("Could not export the key of the borg repository: {}", err);
+            }
+        }
+
+        /*
+         * borg can not copy an archive, so every timeframe gets its own create
+         *  unchanged files are not read again thanks to the files cache, but the source is scanned each time
+         */
+        let mut statistics = None;
+        for timing in timings {
+            let archive_statistics = self.run_create(timing, bandwidth_limit)?;
+            if statistics.is_none() {
+                statistics = archive_statistics;
+            }
+        }
+
+        if !self.config.disable_prune {
+            self.run_prune(timings)?;
+        } else {
+            debug!("Pruning the borg repository is disabled");
+        }
+
+        Ok(statistics)
+    }
+
+    fn run_create(&self, timing: &ExecutionTiming, bandwidth_limit: Option<u64>) -> Result<Option<RunStatistics>, String> {
         /*
          * Start backup command
          */
@@ -235,16 +473,23 @@ impl Borg {
         }
 
         if self.args.verbose {
-            command.arg_str("--stats");
             command.arg_str("--list");
         }
 
+        // the statistics are printed as json to stdout
+        command.arg_str("--stats");
+        command.arg_str("--json");
+
         if let Some(excludes) = self.config.exclude.as_ref() {
             for exclude in excludes {
                 command.arg_string(format!("--exclude={}", exclude));
             }
         }
 
+        if let Some(limit) = bandwidth_limit {
+            command.arg_string(format!("--remote-ratelimit={}", to_kibibytes(limit)));
+        }
+
         if let Some(additional_options) = self.config.additional_options.as_ref() {
             for arg in additional_options {
                 command.arg_str(arg);
@@ -252,18 +497,13 @@ impl Borg {
         }
 
         /*
-         * append repo location
+         * append repo location, the archive name contains the timeframe and ends with the date
          */
-        let prefix = if let Some(prefix) = self.config.prefix.as_ref() {
-            format!("vbackup_{}", prefix)
-        } else {
-            String::from("vbackup")
-        };
-        command.arg_string(format!("{}::{}_{}",
-                                   self.get_repo_path(),
-                                   prefix,
-                                   "{now:%Y-%m-%dT%H:%M:%S}"
-        ));
+        let archive = format!("{}_{}_{}",
+                              self.get_archive_prefix(),
+                              timing.time_frame_reference.frame,
+                              timing.execution_time.format("%Y-%m-%dT%H:%M:%S"));
+        command.arg_string(format!("{}::{}", self.get_repo_path(), archive));
 
         /*
          * append paths to save
@@ -287,18 +527,32 @@ impl Borg {
             }
         }
 
-        command.run_with_args(self.args.as_ref())?;
+        let output = match command.run_get_output_with_args(self.args.as_ref())? {
+            Some(output) => output,
+            None => return Ok(None)
+        };
 
-        if !self.config.disable_prune {
-            self.run_prune()?;
-        } else {
-            debug!("Pruning the borg repository is disabled");
-        }
+        return match parse_statistics(output.as_str()) {
+            Ok(statistics) => {
+                info!("Statistics of '{}': {}", archive, statistics);
+                Ok(Some(statistics))
+            },
+            Err(err) => {
+                warn!("Could not read the statistics of '{}': {}", archive, err);
+                Ok(None)
+            }
+        };
+    }
 
-        Ok(())
+    fn get_archive_prefix(&self) -> String {
+        return if let Some(prefix) = self.config.prefix.as_ref() {
+            format!("vbackup_{}", prefix)
+        } else {
+            String::from("vbackup")
+        };
     }
 
-    pub fn run_restore(&self) -> Result<(), String> {
+    pub fn run_restore(&self, request: &RestoreRequest) -> Result<(), String> {
         // if the repo is not initialized during a restore operation assume it was recovered from some other location and does not need to be initialized
         if self.requires_init && !self.args.dry_run {
             file::write(format!("{}/init-marker", self.paths.module_data_dir).as_str(), "1", true)?;
@@ -306,9 +560,9 @@ impl Borg {
 
         // TODO: borg writes into the current directory ("."), so better make sure to use "cd /"
 
-        let selected_archive = self.user_select_archive()?;
+        let selected_archive = self.user_select_archive(request)?;
 
-        ask_user_abort(Some(&format!("Continue to restore '{}'?", selected_archive)))?;
+        confirm_abort(request, Some(&format!("Continue to restore '{}'?", selected_archive)))?;
 
         let mut command = self.get_base_cmd("extract")?;
 
@@ -326,6 +580,11 @@ impl Borg {
         // archive path is constructed from repo and archive
         command.arg_string(format!("{}::{}", self.get_repo_path(), selected_archive));
 
+        // borg stores paths without the leading slash, extracting a directory includes its content
+        if let Some(path) = request.path.as_ref() {
+            command.arg_string(self.get_archive_path(path)?);
+        }
+
         info!("Starting restore of '{}'...", selected_archive);
         command.run_with_args(self.args.as_ref())?;
         info!("Restore done.");
@@ -333,25 +592,117 @@ impl Borg {
         Ok(())
     }
 
-    fn user_select_archive(&self) -> Result<String, String> {
-        let mut archive_list = self.get_list_of_archives(Some("{archive}{NEWLINE}"))?; // would also work with '--short' option
+    fn user_select_archive(&self, request: &RestoreRequest) -> Result<String, String> {
+        let mut archive_list = self.get_archives()?;
 
         // the list is sorted by timestamp (borg default) so suggest the last entry
-        let selected_index = ask_user_option_list_index(
+        let selected_index = select_archive(
+            request,
             Some("Found multiple backup archives in the borg repository:"),
             Some("Which archive should be restored?"),
             archive_list.as_ref(),
-            &|archive| {archive.as_str()},
-            archive_list.len().sub(1)
+            &|archive| {archive.id.as_str()},
+            &|archive| {archive.time}
         )?;
 
         return if selected_index < archive_list.len() {
-            Ok(archive_list.swap_remove(selected_index))
+            Ok(archive_list.swap_remove(selected_index).id)
         } else {
             Err(String::from("Selected index is out of bounds"))
         }
     }
 
+    pub fn get_archives(&self) -> Result<Vec<ArchiveEntry>, String> {
+        let archive_list = self.get_list_of_archives(Some("{archive}{NEWLINE}"))?; // would also work with '--short' option
+
+        return Ok(archive_list.into_iter()
+            .filter(|archive| !archive.is_empty())
+            .map(|archive| {
+                // archive names end with the date of the backup
+                let time = archive.len().checked_sub(19).and_then(|start| parse_time_prefix(archive.as_str(), start, "%Y-%m-%dT%H:%M:%S", 19));
+                ArchiveEntry { id: archive, time }
+            })
+            .collect());
+    }
+
+    pub fn get_files(&self, archive: &ArchiveEntry) -> Result<Vec<FileEntry>, String> {
+        let mut command = self.get_base_cmd("list")?;
+
+        command.arg_str("--format={type}{TAB}{size}{TAB}{isomtime}{TAB}{path}{NEWLINE}");
+        command.arg_string(format!("{}::{}", self.get_repo_path(), archive.id));
+
+        let output = command.run_get_output()?;
+
+        return Ok(output.split('\n').filter_map(|line| {
+            let mut fields = line.splitn(4, '\t');
+            let file_type = fields.next()?;
+            let size = fields.next()?;
+            let mtime = fields.next()?;
+            let path = self.get_relative_path(fields.next()?)?;
+
+            Some(FileEntry {
+                path,
+                size: size.parse::<u64>().ok(),
+                // the format of isomtime differs between borg versions
+                mtime: parse_time_formats(mtime, &["%Y-%m-%dT%H:%M:%S%.f", "%a, %Y-%m-%d %H:%M:%S"]),
+                is_dir: file_type == "d"
+            })
+        }).collect());
+    }
+
+    pub fn get_latest_files(&self) -> Result<Option<Vec<String>>, String> {
+        return latest_archive_files(self.get_archives()?, &|archive| self.get_files(archive));
+    }
+
+    fn get_archive_path(&self, path: &str) -> Result<String, String> {
+        if !self.args.no_docker {
+            return Ok(format!("volume/{}", path));
+        }
+
+        return match self.paths.source.borrow() {
+            SourcePath::Single(source) => {
+                Ok(format!("{}/{}", source.trim_start_matches('/').trim_end_matches('/'), path))
+            },
+            SourcePath::Multiple(sources) => {
+                // with multiple sources the first part of the path is the name of the source
+                let (name, rest) = path.split_once('/').unwrap_or((path, ""));
+                let source = sources.iter()
+                    .find(|source| source.name == name)
+                    .ok_or(format!("There is no source path named '{}'", name))?;
+
+                let source_path = source.path.trim_start_matches('/').trim_end_matches('/');
+                Ok(if rest.is_empty() { String::from(source_path) } else { format!("{}/{}", source_path, rest) })
+            }
+        };
+    }
+
+    // Reverse of get_archive_path, paths outside of the sources are skipped
+    fn get_relative_path(&self, archive_path: &str) -> Option<String> {
+        let relative = if !self.args.no_docker {
+            String::from(archive_path.strip_prefix("volume/")?)
+        } else {
+            match self.paths.source.borrow() {
+                SourcePath::Single(source) => {
+                    let source_path = source.trim_start_matches('/').trim_end_matches('/');
+                    String::from(archive_path.strip_prefix(source_path)?.trim_start_matches('/'))
+                },
+                SourcePath::Multiple(sources) => {
+                    sources.iter().find_map(|source| {
+                        let source_path = source.path.trim_start_matches('/').trim_end_matches('/');
+                        let rest = archive_path.strip_prefix(source_path)?;
+                        if rest.is_empty() || rest.starts_with('/') {
+                            Some(format!("{}{}", source.name, rest))
+                        } else {
+                            None
+                        }
+                    })?
+                }
+            }
+        };
+
+        return if relative.is_empty() { None } else { Some(relative) };
+    }
+
     /*
      * format according to borg list's --format option
      * entries should be newline-separated if passing a custom format
@@ -363,6 +714,8 @@ impl Borg {
             command.arg_string(format!("--format={}", format));
         }
 
+        // the repository can be shared by several configurations, which use different prefixes
+        command.arg_string(format!("--glob-archives={}_*", self.get_archive_prefix()));
         command.arg_string(self.get_repo_path());
 
         return command.run_get_output().map(|result| {
@@ -370,8 +723,103 @@ impl Borg {
         });
     }
 
-    fn _run_check_backup(&self) -> Result<(), String> {
-        todo!("check the consistency of the backup?")
+    pub fn run_check(&self) -> Result<(), String> {
+        info!("Checking the borg repository...");
+        return self.get_check_cmd(self.config.verify_data, false)?.run_with_args(self.args.as_ref());
+    }
+
+    fn get_check_cmd(&self, verify_data: bool, repair: bool) -> Result<CommandWrapper, String> {
+        let mut command = self.get_base_cmd("check")?;
+
+        // reading all the data takes a lot longer than only checking the repository and archive metadata
+        if verify_data {
+            command.arg_str("--verify-data");
+        }
+
+        // borg asks for confirmation before repairing otherwise
+        if repair {
+            command.arg_str("--repair");
+            command.env("BORG_CHECK_I_KNOW_WHAT_I_AM_DOING", "YES");
+        }
+
+        command.arg_string(self.get_repo_path());
+
+        return Ok(command);
+    }
+
+    /*
+     * Maintenance runs on its own schedule: free the space of pruned archives and check the repository
+     */
+    pub fn run_maintenance(&self) -> Result<(), String> {
+        let maintenance = &self.config.maintenance;
+
+        if maintenance.compact {
+            self.run_compact()?;
+        }
+
+        if maintenance.check {
+            info!("Checking the borg repository...");
+            let check_result = self.get_check_cmd(maintenance.verify_data, false)?.run_with_args(self.args.as_ref());
+
+            if let Err(err) = check_result {
+                if !maintenance.repair {
+                    return Err(err);
+                }
+
+                warn!("Checking the borg repository failed, trying to repair it: {}", err);
+                self.get_check_cmd(maintenance.verify_data, true)?.run_with_args(self.args.as_ref())?;
+                warn!("The borg repository was repaired, some data might have been lost");
+            }
+        }
+
+        if !maintenance.compact && !maintenance.check {
+            warn!("All borg maintenance operations are disabled");
+        }
+
+        return Ok(());
+    }
+
+    /*
+     * Compacting requires borg 1.2 or newer, before that the space was freed when pruning
+     */
+    fn run_compact(&self) -> Result<(), String> {
+        // Deleted data of append-only repositories is only freed once the mode is lifted
+        let lift_append_only = self.config.append_only && self.config.maintenance.lift_append_only;
+        if self.config.append_only && !lift_append_only {
+            warn!("The borg repository is append-only, compacting does not free the space of deleted archives");
+        }
+
+        if lift_append_only {
+            self.set_append_only(false)?;
+        }
+
+        let mut command = self.get_base_cmd("compact")?;
+        command.arg_string(self.get_repo_path());
+
+        info!("Compacting the borg repository...");
+        let result = command.run_with_args(self.args.as_ref());
+
+        // Always restore the append-only mode, even if compacting failed
+        if lift_append_only {
+            let restore_result = self.set_append_only(true);
+            if result.is_ok() {
+                restore_result?;
+            } else if let Err(err) = restore_result {
+                error!("Could not restore the append-only mode of the borg repository: {}", err);
+            }
+        }
+
+        return result;
+    }
+
+    fn set_append_only(&self, enabled: bool) -> Result<(), String> {
+        let mut command = self.get_base_cmd("config")?;
+        command.arg_string(self.get_repo_path());
+        command.arg_str("append_only");
+        command.arg_str(if enabled { "1" } else { "0" });
+
+        debug!("Setting the append-only mode of the borg repository to {}", enabled);
+        return command.run_with_args(self.args.as_ref());
     }
 
     fn get_base_cmd(&self, operation: &str) -> Result<CommandWrapper,String> {
@@ -385,6 +833,7 @@ impl Borg {
             let mut options = vec![
                 "--env=BORG_BASE_DIR",
                 "--env=BORG_PASSPHRASE",
+                "--env=BORG_CHECK_I_KNOW_WHAT_I_AM_DOING",
                 "--env=SSHPASS"
             ];
 
@@ -442,6 +891,15 @@ impl Borg {
         return Ok(command);
     }
 
+    // The location of the repository as seen from the host
+    fn get_repo_location(&self) -> String {
+        return if self.sync_config.is_some() || self.args.no_docker {
+            self.get_repo_path()
+        } else {
+            self.paths.destination.clone()
+        };
+    }
+
     fn get_repo_path(&self) -> String {
         if let Some(borg_sync) = self.sync_config.as_ref() {
             let path = if borg_sync.directory.starts_with("/") {
@@ -461,4 +919,24 @@ impl Borg {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/*
+ * The statistics of the created archive in the json output of borg create
+ *  {"archive": {"name": "...", "stats": {"nfiles": 12, "original_size": 2048, "compressed_size": 1024, "deduplicated_size": 512, ...}}, ...}
+ */
+fn parse_statistics(output: &str) -> Result<RunStatistics, String> {
+    let parsed = try_result!(serde_json::from_str::<BorgCreateOutput>(output), "Could not parse the output of borg create");
+    let stats = parsed.archive.stats;
+
+    return Ok(RunStatistics {
+        files_transferred: Some(stats.nfiles),
+        bytes_sent: Some(stats.deduplicated_size),
+        speedup: if stats.deduplicated_size > 0 {
+            Some(stats.original_size as f64 / stats.deduplicated_size as f64)
+        } else {
+            None
+        },
+        ..RunStatistics::default()
+    });
+}
@@ -0,0 +1,156 @@
+use crate::util::command::quote;
+use serde::{Deserialize};
+
+/**
+ * Public key encryption with age or gpg, the host creating the backup only needs the public keys of the recipients
+ */
+#[derive(Deserialize,Clone,Copy,PartialEq)]
+#[serde(rename_all="lowercase")]
+pub enum EncryptionType {
+    Age,
+    Gpg
+}
+
+#[derive(Deserialize,Clone)]
+pub struct PublicKeyEncryption {
+    #[serde(rename(deserialize = "type"))]
+    pub encryption_type: EncryptionType,
+    pub recipients: Vec<String> // Public keys (age) or key ids (gpg), absolute paths refer to files containing the keys
+}
+
+impl EncryptionType {
+    pub fn extension(&self) -> &'static str {
+        return match self {
+            EncryptionType::Age => "age",
+            EncryptionType::Gpg => "gpg"
+        };
+    }
+
+    pub fn from_file_name(file_name: &str) -> Option<EncryptionType> {
+        return [EncryptionType::Age, EncryptionType::Gpg].iter()
+            .find(|encryption_type| file_name.ends_with(format!(".{}", encryption_type.extension()).as_str()))
+            .copied();
+    }
+
+    /**
+     * Command that decrypts stdin to stdout, the private key is only needed here
+     *  age requires an identity file, gpg falls back to the keyring of the user
+     *  gpg also decrypts symmetrically encrypted input if a passphrase file is given
+     */
+    pub fn decrypt_command(&self, identity: Option<&str>, passphrase_file: Option<&str>) -> Result<String, String> {
+        return match self {
+            EncryptionType::Age => {
+                if let Some(identity) = identity {
+                    Ok(format!("age --decrypt --identity {}", quote(identity)))
+                } else {
+                    Err(String::from("An identity file is required to decrypt age encrypted files (--identity)"))
+                }
+            },
+            EncryptionType::Gpg => {
+                let passphrase_option = passphrase_file
+                    .map(|passphrase_file| format!(" --passphrase-file {}", quote(passphrase_file)))
+                    .unwrap_or(String::new());
+
+                if let Some(identity) = identity {
+                    // Import the secret key into a temporary keyring that is removed afterwards
+                    Ok(format!("(home=$(mktemp -d) && gpg --homedir \"$home\" --batch --quiet --import {} && gpg --homedir \"$home\" --batch --quiet{} --decrypt; result=$?; gpgconf --homedir \"$home\" --kill gpg-agent 2> /dev/null; rm -rf \"$home\"; exit $result)", quote(identity), passphrase_option))
+                } else {
+                    Ok(format!("gpg --batch --quiet{} --decrypt", passphrase_option))
+                }
+            }
+        };
+    }
+}
+
+impl PublicKeyEncryption {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.recipients.is_empty() {
+            return Err(format!("At least one recipient is required for {} encryption", self.encryption_type.extension()));
+        }
+
+        return Ok(());
+    }
+
+    // The keyring of the host is not available in docker, gpg keys have to be given as files
+    pub fn validate_docker(&self) -> Result<(), String> {
+        if self.encryption_type == EncryptionType::Gpg {
+            if let Some(key_id) = self.recipients.iter().find(|recipient| !is_key_file(recipient.as_str())) {
+                return Err(format!("The gpg recipient '{}' has to be a key file when using docker, key ids are only supported with --no-docker", key_id));
+            }
+        }
+
+        return Ok(());
+    }
+
+    // Recipients given as files, these need to be available where the encryption runs
+    pub fn key_files(&self) -> Vec<&str> {
+        return self.recipients.iter()
+            .filter(|recipient| is_key_file(recipient.as_str()))
+            .map(|recipient| recipient.as_str())
+            .collect();
+    }
+
+    // Key files are mounted to /keys in docker
+    pub fn key_file_path(&self, path: &str, use_docker: bool) -> String {
+        if !use_docker {
+            return String::from(path);
+        }
+
+        let index = self.key_files().iter().position(|key_file| *key_file == path).unwrap_or(0);
+        return format!("/keys/{}", index);
+    }
+
+    /**
+     * Command that encrypts stdin to stdout for all recipients
+     *  key_file_path maps the path of a key file to the path where the command runs (e.g. in docker)
+     */
+    pub fn encrypt_command(&self, key_file_path: &dyn Fn(&str) -> String) -> String {
+        let (mut command, key_option, file_option) = match self.encryption_type {
+            EncryptionType::Age => (String::from("age --encrypt"), "--recipient", "--recipients-file"),
+            EncryptionType::Gpg => (String::from("gpg --batch --yes --quiet --trust-model always --encrypt"), "--recipient", "--recipient-file")
+        };
+
+        for recipient in self.recipients.iter() {
+            if is_key_file(recipient.as_str()) {
+                command.push_str(format!(" {} {}", file_option, quote(key_file_path(recipient.as_str()).as_str())).as_str());
+            } else {
+                command.push_str(format!(" {} {}", key_option, quote(recipient.as_str())).as_str());
+            }
+        }
+
+        return command;
+    }
+}
+
+// The identity (private key) is mounted to /keys/identity in docker
+pub fn identity_path(identity: Option<&String>, use_docker: bool) -> Option<String> {
+    return identity.map(|identity| {
+        if use_docker {
+            String::from("/keys/identity")
+        } else {
+            identity.clone()
+        }
+    });
+}
+
+// Docker options mounting the key files of the recipients and the identity read-only
+pub fn key_volume_options(encryption: Option<&PublicKeyEncryption>, identity: Option<&String>) -> Vec<String> {
+    let mut options = vec![];
+
+    if let Some(encryption) = encryption {
+        for (index, key_file) in encryption.key_files().iter().enumerate() {
+            options.push(format!("--volume={}:/keys/{}:ro", key_file, index));
+        }
+    }
+
+    if let Some(identity) = identity {
+        options.push(format!("--volume={}:/keys/identity:ro", identity));
+    }
+
+    return options;
+}
+
+fn is_key_file(recipient: &str) -> bool {
+    return recipient.starts_with('/');
+}
+
@@ -1,3 +1,5 @@
 pub mod mqtt;
 pub mod ssh;
-pub mod borg;
\ No newline at end of file
+pub mod borg;
+pub mod archive;
+pub mod encryption;
@@ -3,6 +3,11 @@ pub use crate::modules::shared::borg::Borg;
 use crate::modules::traits::{Sync};
 use serde_json::Value;
 use crate::util::objects::paths::ModulePaths;
+use crate::util::objects::restore::{RestoreRequest, ArchiveEntry, FileEntry};
+use crate::util::objects::statistics::RunStatistics;
+use crate::util::objects::recovery::RecoveryInformation;
+use crate::util::objects::bandwidth::{BandwidthConfiguration, current_limit};
+use crate::util::objects::time::ExecutionTiming;
 use crate::Arguments;
 use crate::modules::shared::ssh::SshConfig;
 use crate::util::io::{auth_data, json};
@@ -35,16 +40,48 @@ impl Sync for Borg {
         }))
     }
 
+    fn check_restore_to(args: &Arguments) -> Result<(), String> {
+        Borg::check_restore_to(args)
+    }
+
     fn init(&mut self) -> Result<(), String> {
         Borg::init(self)
     }
 
-    fn sync(&self) -> Result<(), String> {
-        Borg::run_save(self)
+    fn sync(&self, timing: &ExecutionTiming, bandwidth: Option<&BandwidthConfiguration>) -> Result<Option<RunStatistics>, String> {
+        Borg::run_save(self, &[timing], current_limit(bandwidth)?)
+    }
+
+    fn verify(&self) -> Result<(), String> {
+        Borg::run_check(self)
+    }
+
+    fn maintain(&self) -> Result<(), String> {
+        Borg::run_maintenance(self)
+    }
+
+    fn check_maintenance() -> Result<(), String> {
+        Ok(())
+    }
+
+    fn restore(&self, request: &RestoreRequest) -> Result<(), String> {
+        Borg::run_restore(self, request)
+    }
+
+    fn list_archives(&self) -> Result<Vec<ArchiveEntry>, String> {
+        Borg::get_archives(self)
+    }
+
+    fn list_files(&self, archive: &ArchiveEntry) -> Result<Vec<FileEntry>, String> {
+        Borg::get_files(self, archive)
+    }
+
+    fn included_files(&self) -> Result<Option<Vec<String>>, String> {
+        Borg::get_latest_files(self)
     }
 
-    fn restore(&self) -> Result<(), String> {
-        Borg::run_restore(self)
+    fn recovery_information(&self) -> Result<Option<RecoveryInformation>, String> {
+        Borg::get_recovery_information(self)
     }
 
     fn clear(&mut self) -> Result<(), String> {
@@ -3,15 +3,23 @@ use crate::modules::traits::Sync;
 use crate::util::command::CommandWrapper;
 use crate::util::io::{file,json,auth_data};
 use crate::util::objects::paths::{ModulePaths,SourcePath};
-use crate::Arguments;
+use crate::util::objects::restore::{ArchiveSelector, RestoreRequest, ArchiveEntry, FileEntry, parse_time_formats, latest_archive_files};
+use crate::util::objects::statistics::{RunStatistics, RunOutcome};
+use crate::util::objects::recovery::RecoveryInformation;
+use crate::util::objects::time::ExecutionTiming;
+use crate::util::objects::bandwidth::{BandwidthConfiguration, current_limit, to_kibibytes};
+use crate::util::io::user::confirm_abort;
+use crate::{Arguments, try_option, dry_run};
 
 use serde_json::Value;
 use serde::{Deserialize};
+use std::process::ExitStatus;
 
 pub struct Duplicati {
     name: String,
     config: Configuration,
     auth: Authentication,
+    export_auth: Option<Authentication>,
     paths: ModulePaths,
     args: Rc<Arguments>
 }
@@ -33,13 +41,28 @@ struct Configuration {
     #[serde(default="default_retention_policy")]
     retention_policy: String,
 
+    #[serde(default="default_verify_samples")]
+    verify_samples: String, // number of samples or 'all'
+
     block_size: Option<String>, // default by duplicati: 100kb
-    file_size: Option<String> // default by duplicati: 50mb
+    file_size: Option<String>, // default by duplicati: 50mb
+
+    database_export: Option<DatabaseExport>
+}
+
+#[derive(Deserialize)]
+struct DatabaseExport {
+    path: Option<String>, // local directory for the exported database, default: $module_data_dir/db-export
+    directory_prefix: Option<String>,
+    directory: Option<String>, // the export is backed up to this directory on the server if set
+    auth_reference: Option<String>, // default is the authentication of the sync
+    auth: Option<Value>
 }
 
 fn default_versions() -> i32 { 1 }
 fn default_smart_retention() -> bool { false }
 fn default_retention_policy() -> String { "1W:1D,4W:1W,12M:1M".to_string() }
+fn default_verify_samples() -> String { "1".to_string() }
 
 #[derive(Deserialize)]
 struct Authentication {
@@ -58,39 +81,62 @@ impl Sync for Duplicati {
         let config = json::from_value::<Configuration>(config_json.clone())?; // TODO: Remove clone
         let auth = auth_data::resolve::<Authentication>(&config.auth_reference, &config.auth, paths.base_paths.as_ref())?;
 
+        // The second destination for the database uses the same server unless configured otherwise
+        let export_auth = match config.database_export.as_ref() {
+            Some(export) if export.directory.is_some() => Some(if export.auth_reference.is_some() || export.auth.is_some() {
+                auth_data::resolve::<Authentication>(&export.auth_reference, &export.auth, paths.base_paths.as_ref())?
+            } else {
+                auth_data::resolve::<Authentication>(&config.auth_reference, &config.auth, paths.base_paths.as_ref())?
+            }),
+            _ => None
+        };
+
         warn!("Duplicati sync is deprecated! Better use borg.");
 
         if args.is_restore && args.restore_to.is_some() {
-            return Err(String::from("The restore-to option is not supported for duplicati"));
+            Self::check_restore_to(args)?;
         }
 
         return Ok(Box::new(Self {
             name: String::from(name),
             config,
             auth,
+            export_auth,
             paths,
             args: args.clone()
         }));
     }
 
+    fn check_restore_to(_args: &Arguments) -> Result<(), String> {
+        return Err(String::from("The restore-to option is not supported for duplicati"));
+    }
+
     fn init(&mut self) -> Result<(), String> {
         return Ok(());
     }
 
-    fn sync(&self) -> Result<(), String> {
+    fn sync(&self, _timing: &ExecutionTiming, bandwidth: Option<&BandwidthConfiguration>) -> Result<Option<RunStatistics>, String> {
         debug!("Starting duplicati sync for {}", self.name);
 
+        self.check_source_layout()?;
+
         // Base command
-        let mut command = get_base_cmd(self.args.no_docker, &self.paths);
+        let mut command = get_base_cmd(self.args.no_docker, &self.paths, &self.paths.source);
 
         // Add source and destination
         command.arg_str("backup");
-        command.arg_string(format!("{}", get_connection_uri(&self.config, &self.auth)));
+        command.arg_string(get_connection_uri(&self.config.directory_prefix, self.config.directory.as_str(), &self.auth));
         if self.args.no_docker {
-            if let SourcePath::Single(path) = &self.paths.source {
-                command.arg_string(path.clone());
-            } else {
-                return Err(String::from("Multiple source paths are not supported in duplicati module without docker"));
+            match &self.paths.source {
+                SourcePath::Single(path) => {
+                    command.arg_string(path.clone());
+                },
+                SourcePath::Multiple(paths) => {
+                    // duplicati stores the absolute paths, each source path is passed on its own
+                    for mapping in paths {
+                        command.arg_string(mapping.path.clone());
+                    }
+                }
             }
         } else {
             command.arg_str("/volume");
@@ -110,35 +156,82 @@ impl Sync for Duplicati {
         command.arg_str("--compression-module=zip");
         command.arg_str("--encryption-module=aes");
 
+        if let Some(limit) = current_limit(bandwidth)? {
+            command.arg_string(format!("--throttle-upload={}KB", to_kibibytes(limit)));
+        }
+
         let status = command.run_get_status_with_args(self.args.as_ref())?;
-        if let Some(code) = status.code() {
-            trace!("Exit code is '{}'", code);
-            if code == 50 {
-                let msg = format!("Backup uploaded some files, but did not finish");
-                error!("{}", msg);
-                return Err(msg);
-            } else if code == 2 {
-                warn!("Duplicati exited with warnings");
-            } else if code != 0 && code != 1 {
-                let msg = format!("Exit code indicates failure of duplicati backup");
-                error!("{}", msg);
-                return Err(msg);
+        let mut outcome = get_outcome(&status, "backup")?;
+        match outcome {
+            Some(RunOutcome::Partial) => warn!("Backup uploaded some files, but did not finish"),
+            Some(RunOutcome::Warnings) => warn!("Duplicati exited with warnings"),
+            Some(RunOutcome::Unchanged) => info!("Duplicati did not find any changes"),
+            None => {}
+        }
+
+        // The database of an unfinished backup is not worth keeping
+        if outcome != Some(RunOutcome::Partial) {
+            if let Some(export) = self.config.database_export.as_ref() {
+                if let Err(err) = self.export_database(export) {
+                    error!("Exporting the duplicati database failed: {}", err);
+                    outcome = Some(RunOutcome::Warnings);
+                }
             }
         }
 
         debug!("Duplicati sync for {} is done", self.name);
+        Ok(outcome.map(|outcome| RunStatistics {
+            outcome: Some(outcome),
+            ..RunStatistics::default()
+        }))
+    }
+
+    fn verify(&self) -> Result<(), String> {
+        debug!("Starting duplicati test for {}", self.name);
+
+        let mut command = get_base_cmd(self.args.no_docker, &self.paths, &self.paths.source);
+
+        // Downloads the given amount of sample volumes and checks them against the local database
+        command.arg_str("test");
+        command.arg_string(get_connection_uri(&self.config.directory_prefix, self.config.directory.as_str(), &self.auth));
+        command.arg_string(self.config.verify_samples.clone());
+
+        add_default_options(&mut command, &self.name, &self.config, &self.auth, &self.paths, self.args.no_docker)?;
+
+        command.run_with_args(self.args.as_ref())?;
+
+        debug!("Duplicati test for {} is done", self.name);
         Ok(())
     }
 
-    fn restore(&self) -> Result<(), String> {
+    fn restore(&self, request: &RestoreRequest) -> Result<(), String> {
         debug!("Starting duplicati restore for {}", self.name);
 
+        // Duplicati selects the version itself, it can be given by its number (0 is the latest) or a point in time
+        let version_option = match request.archive.as_ref().map(|archive| ArchiveSelector::parse(archive.as_str())).transpose()? {
+            None | Some(ArchiveSelector::Latest) => None,
+            Some(ArchiveSelector::Before(date)) => Some(format!("--time={}", date.format("%Y-%m-%dT%H:%M:%S"))),
+            Some(ArchiveSelector::Id(id)) => {
+                if id.parse::<u32>().is_err() {
+                    return Err(format!("Duplicati archives are selected by their version number, got '{}'", id));
+                }
+                Some(format!("--version={}", id))
+            }
+        };
+
+        confirm_abort(request, Some(&format!("Continue to restore '{}'?", version_option.as_ref().map(|option| option.as_str()).unwrap_or("latest version"))))?;
+
+        // Use the exported database if the local one is missing, rebuilding it with repair takes long
+        if let Some(export) = self.config.database_export.as_ref() {
+            self.import_database(export)?;
+        }
+
         // Restore / repair the local database
         {
-            let mut command = get_base_cmd(self.args.no_docker, &self.paths);
+            let mut command = get_base_cmd(self.args.no_docker, &self.paths, &self.paths.source);
 
             command.arg_str("repair");
-            command.arg_string(format!("{}", get_connection_uri(&self.config, &self.auth)));
+            command.arg_string(get_connection_uri(&self.config.directory_prefix, self.config.directory.as_str(), &self.auth));
 
             add_default_options(&mut command, &self.name, &self.config, &self.auth, &self.paths, self.args.no_docker)?;
 
@@ -147,20 +240,30 @@ impl Sync for Duplicati {
 
         // Restore the data
         {
-            let mut command = get_base_cmd(self.args.no_docker, &self.paths);
+            let mut command = get_base_cmd(self.args.no_docker, &self.paths, &self.paths.source);
 
             command.arg_str("restore");
-            command.arg_string(format!("{}", get_connection_uri(&self.config, &self.auth)));
+            command.arg_string(get_connection_uri(&self.config.directory_prefix, self.config.directory.as_str(), &self.auth));
             command.arg_str("*");
 
             add_default_options(&mut command, &self.name, &self.config, &self.auth, &self.paths, self.args.no_docker)?;
 
             command.arg_str("--restore-permission=true");
+            if let Some(version) = version_option.as_ref() {
+                command.arg_string(version.clone());
+            }
+
+            // Paths in the backup are absolute, include the file itself or everything below the directory
+            if let Some(path) = request.path.as_ref() {
+                let backup_path = self.get_backup_path(path.as_str())?;
+
+                command.arg_string(format!("--include={}", backup_path));
+                command.arg_string(format!("--include={}/*", backup_path));
+            }
             if self.args.no_docker {
+                // multiple source paths are restored to their original location
                 if let SourcePath::Single(path) = &self.paths.source {
                     command.arg_string(format!("--restore-path={}", path));
-                } else {
-                    return Err(String::from("Multiple source paths are not supported in duplicati module without docker"));
                 }
             } else {
                 command.arg_str("--restore-path=/volume");
@@ -173,12 +276,320 @@ impl Sync for Duplicati {
         Ok(())
     }
 
+    fn list_archives(&self) -> Result<Vec<ArchiveEntry>, String> {
+        // Without a file filter duplicati lists the available versions
+        //  0	: 10/16/2024 3:00:04 AM (12 files, 1.10 MB)
+        let output = self.run_find(None)?;
+
+        let mut archives: Vec<ArchiveEntry> = output.lines().filter_map(|line| {
+            let (version, rest) = line.split_once(':')?;
+            let version = version.trim();
+            version.parse::<u32>().ok()?;
+
+            let time = rest.rsplit_once('(').map(|(time, _)| time).unwrap_or(rest).trim();
+            Some(ArchiveEntry {
+                id: String::from(version),
+                time: parse_duplicati_time(time)
+            })
+        }).collect();
+
+        // version 0 is the latest, but archives are expected from oldest to newest
+        archives.reverse();
+
+        return Ok(archives);
+    }
+
+    fn list_files(&self, archive: &ArchiveEntry) -> Result<Vec<FileEntry>, String> {
+        //  /volume/some/file.txt (1.10 KB)
+        //  /volume/some/directory/
+        let output = self.run_find(Some(archive.id.as_str()))?;
+
+        return Ok(output.lines().filter_map(|line| {
+            let line = line.trim();
+            let (path, size) = match line.rsplit_once(" (") {
+                Some((path, size)) if size.ends_with(')') => (path, parse_size(size.trim_end_matches(')'))),
+                _ => (line, None)
+            };
+
+            let is_dir = path.ends_with('/');
+            let relative = self.get_relative_path(path.trim_end_matches('/'))?;
+
+            Some(FileEntry {
+                path: relative,
+                size,
+                mtime: None, // not part of the output of duplicati
+                is_dir
+            })
+        }).collect());
+    }
+
+    fn included_files(&self) -> Result<Option<Vec<String>>, String> {
+        return latest_archive_files(self.list_archives()?, &|archive| self.list_files(archive));
+    }
+
+    fn recovery_information(&self) -> Result<Option<RecoveryInformation>, String> {
+        return Ok(Some(RecoveryInformation {
+            repository: get_connection_uri(&self.config.directory_prefix, self.config.directory.as_str(), &self.auth),
+            key_files: vec![],
+            passphrase_option: self.config.encryption_key.as_ref().map(|_| String::from("encryption_key"))
+        }));
+    }
+
     fn clear(&mut self) -> Result<(), String> {
         return Ok(());
     }
 }
 
-fn get_base_cmd(no_docker: bool, paths: &ModulePaths) -> CommandWrapper {
+impl Duplicati {
+    fn get_database_file(&self) -> String {
+        return format!("{}/db/{}.sqlite", &self.paths.module_data_dir, self.name);
+    }
+
+    fn get_export_dir(&self, export: &DatabaseExport) -> String {
+        return export.path.clone().unwrap_or(format!("{}/db-export", &self.paths.module_data_dir));
+    }
+
+    // The exported database is backed up to the second destination with its own name and database
+    fn get_export_name(&self) -> String {
+        return format!("{}-database", self.name);
+    }
+
+    /*
+     * Copy the local database of duplicati to the export directory and back it up to the second destination if configured
+     */
+    fn export_database(&self, export: &DatabaseExport) -> Result<(), String> {
+        let export_dir = self.get_export_dir(export);
+        let export_file = format!("{}/{}.sqlite", export_dir, self.name);
+
+        if self.args.dry_run {
+            dry_run!(format!("Exporting the duplicati database to '{}'", export_file));
+        } else {
+            debug!("Exporting the duplicati database to '{}'", export_file);
+            file::create_dir_if_missing(export_dir.as_str(), true)?;
+
+            // Copy to a temporary file first to never leave a broken export behind
+            let tmp_file = format!("{}.tmp", export_file);
+            file::copy_file(self.get_database_file().as_str(), tmp_file.as_str())?;
+            file::move_file(tmp_file.as_str(), export_file.as_str())?;
+        }
+
+        if let (Some(directory), Some(auth)) = (export.directory.as_ref(), self.export_auth.as_ref()) {
+            debug!("Backing up the exported duplicati database to the second destination");
+
+            let mut command = get_base_cmd(self.args.no_docker, &self.paths, &SourcePath::Single(export_dir));
+            command.arg_str("backup");
+            command.arg_string(get_connection_uri(&export.directory_prefix, directory.as_str(), auth));
+            command.arg_string(if self.args.no_docker {
+                export_file
+            } else {
+                format!("/volume/{}.sqlite", self.name)
+            });
+
+            add_default_options(&mut command, self.get_export_name().as_str(), &self.config, auth, &self.paths, self.args.no_docker)?;
+            command.arg_string(format!("--keep-versions={}", self.config.keep_versions));
+            command.arg_str("--compression-module=zip");
+            command.arg_str("--encryption-module=aes");
+
+            let status = command.run_get_status_with_args(self.args.as_ref())?;
+            if get_outcome(&status, "database backup")? == Some(RunOutcome::Partial) {
+                return Err(String::from("Backup of the exported database did not finish"));
+            }
+        }
+
+        return Ok(());
+    }
+
+    /*
+     * Copy the exported database back if the local database is missing, it is fetched from the second destination first if there is no local export
+     */
+    fn import_database(&self, export: &DatabaseExport) -> Result<(), String> {
+        let database_file = self.get_database_file();
+        if file::exists(database_file.as_str()) {
+            return Ok(());
+        }
+
+        let export_dir = self.get_export_dir(export);
+        let export_file = format!("{}/{}.sqlite", export_dir, self.name);
+
+        if !file::exists(export_file.as_str()) {
+            let (directory, auth) = match (export.directory.as_ref(), self.export_auth.as_ref()) {
+                (Some(directory), Some(auth)) => (directory, auth),
+                _ => {
+                    warn!("There is no exported duplicati database, it has to be rebuilt from the remote");
+                    return Ok(());
+                }
+            };
+
+            info!("Restoring the exported duplicati database from the second destination");
+            if !self.args.dry_run {
+                file::create_dir_if_missing(export_dir.as_str(), true)?;
+            }
+
+            let mut command = get_base_cmd(self.args.no_docker, &self.paths, &SourcePath::Single(export_dir.clone()));
+            command.arg_str("restore");
+            command.arg_string(get_connection_uri(&export.directory_prefix, directory.as_str(), auth));
+            command.arg_str("*");
+
+            add_default_options(&mut command, self.get_export_name().as_str(), &self.config, auth, &self.paths, self.args.no_docker)?;
+
+            // The database of the export backup is most likely missing as well, it is small enough to do without
+            command.arg_str("--no-local-db=true");
+            command.arg_str("--overwrite=true");
+            command.arg_string(if self.args.no_docker {
+                format!("--restore-path={}", export_dir)
+            } else {
+                String::from("--restore-path=/volume")
+            });
+
+            command.run_with_args(self.args.as_ref())?;
+        }
+
+        if self.args.dry_run {
+            dry_run!(format!("Importing the exported duplicati database '{}'", export_file));
+        } else {
+            info!("Importing the exported duplicati database '{}'", export_file);
+            file::create_dir_if_missing(format!("{}/db", &self.paths.module_data_dir).as_str(), true)?;
+            file::copy_file(export_file.as_str(), database_file.as_str())?;
+        }
+
+        return Ok(());
+    }
+
+    /*
+     * The paths in the backup differ with and without docker (/volume[/<name>] or the absolute paths on the host)
+     *  Switching would back up every file again under another path and restore would miss the old ones, so it is refused
+     */
+    fn check_source_layout(&self) -> Result<(), String> {
+        let layout_file = format!("{}/source-layout", self.paths.module_data_dir);
+        let layout = if self.args.no_docker { "host" } else { "docker" };
+
+        if file::exists(layout_file.as_str()) {
+            let previous = file::read(layout_file.as_str())?;
+            if previous.trim() != layout {
+                let (previous_mode, mode) = if self.args.no_docker { ("with", "without") } else { ("without", "with") };
+                return Err(format!("The backup of '{}' was created {} docker, syncing it {} docker would store the source paths differently", self.name, previous_mode, mode));
+            }
+        } else if self.args.dry_run {
+            dry_run!(format!("Writing the source layout '{}' to '{}'", layout, layout_file));
+        } else {
+            file::write(layout_file.as_str(), layout, true)?;
+        }
+
+        return Ok(());
+    }
+
+    // The base paths of the sources in the backup, with the name of the source path if there are multiple without docker
+    fn get_base_paths(&self) -> Vec<(String, Option<&str>)> {
+        if !self.args.no_docker {
+            return vec![(String::from("/volume"), None)];
+        }
+
+        return match &self.paths.source {
+            SourcePath::Single(source) => vec![(source.trim_end_matches('/').to_string(), None)],
+            SourcePath::Multiple(paths) => paths.iter()
+                .map(|mapping| (mapping.path.trim_end_matches('/').to_string(), Some(mapping.name.as_str())))
+                .collect()
+        };
+    }
+
+    // The absolute path in the backup, the first part of the relative path is the name of the source path if there are multiple without docker
+    fn get_backup_path(&self, relative: &str) -> Result<String, String> {
+        let base_paths = self.get_base_paths();
+
+        if let [(base_path, None)] = base_paths.as_slice() {
+            return Ok(format!("{}/{}", base_path, relative));
+        }
+
+        let (name, rest) = relative.split_once('/').unwrap_or((relative, ""));
+        let (base_path, _) = try_option!(base_paths.iter().find(|(_, base_name)| *base_name == Some(name)), format!("There is no source path with the name '{}'", name));
+
+        return if rest.is_empty() {
+            Ok(base_path.clone())
+        } else {
+            Ok(format!("{}/{}", base_path, rest))
+        };
+    }
+
+    // The path relative to the source, None for the source itself and paths outside of it
+    fn get_relative_path(&self, backup_path: &str) -> Option<String> {
+        for (base_path, name) in self.get_base_paths() {
+            let rest = if backup_path == base_path {
+                ""
+            } else if let Some(rest) = backup_path.strip_prefix(format!("{}/", base_path).as_str()) {
+                rest
+            } else {
+                continue;
+            };
+
+            return match (name, rest.is_empty()) {
+                (None, true) => None,
+                (None, false) => Some(String::from(rest)),
+                (Some(name), true) => Some(String::from(name)),
+                (Some(name), false) => Some(format!("{}/{}", name, rest))
+            };
+        }
+
+        return None;
+    }
+
+    fn run_find(&self, version: Option<&str>) -> Result<String, String> {
+        let mut command = get_base_cmd(self.args.no_docker, &self.paths, &self.paths.source);
+
+        command.arg_str("find");
+        command.arg_string(get_connection_uri(&self.config.directory_prefix, self.config.directory.as_str(), &self.auth));
+
+        if let Some(version) = version {
+            command.arg_str("*");
+            command.arg_string(format!("--version={}", version));
+        }
+
+        add_default_options(&mut command, &self.name, &self.config, &self.auth, &self.paths, self.args.no_docker)?;
+
+        return command.run_get_output();
+    }
+}
+
+// The time is printed in the format of the locale of the system
+fn parse_duplicati_time(time: &str) -> Option<chrono::DateTime<chrono::Local>> {
+    return parse_time_formats(time, &["%m/%d/%Y %I:%M:%S %p", "%d.%m.%Y %H:%M:%S", "%d/%m/%Y %H:%M:%S", "%Y-%m-%d %H:%M:%S"]);
+}
+
+// Sizes are printed human readable, e.g. '1.10 KB'
+fn parse_size(size: &str) -> Option<u64> {
+    let (number, unit) = size.trim().split_once(' ')?;
+    let number = number.parse::<f64>().ok()?;
+    let factor: u64 = match unit {
+        "bytes" | "byte" | "B" => 1,
+        "KB" => 1 << 10,
+        "MB" => 1 << 20,
+        "GB" => 1 << 30,
+        "TB" => 1 << 40,
+        _ => return None
+    };
+
+    return Some((number * factor as f64) as u64);
+}
+
+/*
+ * Duplicati exit codes: 0 success, 1 no changes, 2 warnings, 50 partially uploaded, everything else is a failure
+ */
+fn get_outcome(status: &ExitStatus, action: &str) -> Result<Option<RunOutcome>, String> {
+    trace!("Exit code is '{:?}'", status.code());
+
+    return match status.code() {
+        Some(0) => Ok(None),
+        Some(1) => Ok(Some(RunOutcome::Unchanged)),
+        Some(2) => Ok(Some(RunOutcome::Warnings)),
+        Some(50) => Ok(Some(RunOutcome::Partial)),
+        _ => {
+            let msg = format!("Exit code indicates failure of duplicati {}", action);
+            error!("{}", msg);
+            Err(msg)
+        }
+    };
+}
+
+fn get_base_cmd(no_docker: bool, paths: &ModulePaths, source: &SourcePath) -> CommandWrapper {
     if no_docker {
         return CommandWrapper::new("duplicati-cli");
     } else {
@@ -186,7 +597,7 @@ fn get_base_cmd(no_docker: bool, paths: &ModulePaths) -> CommandWrapper {
         command.arg_str("run")
             .arg_str("--rm")
             .arg_str("--name=vbackup-duplicati-tmp")
-            .add_docker_volume_mapping(&paths.source, "volume")
+            .add_docker_volume_mapping(source, "volume")
             .arg_string(format!("--volume={}:/module", &paths.module_data_dir))
             .arg_str("--env=AUTH_USERNAME")
             .arg_str("--env=AUTH_PASSWORD")
@@ -264,8 +675,8 @@ fn add_default_options(command: &mut CommandWrapper, name: &str, config: &Config
     Ok(())
 }
 
-fn get_connection_uri(config: &Configuration, auth: &Authentication) -> String {
-    let (directory_prefix, separator) = if let Some(prefix) = config.directory_prefix.as_ref() {
+fn get_connection_uri(directory_prefix: &Option<String>, directory: &str, auth: &Authentication) -> String {
+    let (directory_prefix, separator) = if let Some(prefix) = directory_prefix.as_ref() {
         let separator = if prefix.eq("") || prefix.eq("/") {
             // Putting a separating slash after an empty string or slash only makes no sense
             ""
@@ -280,5 +691,5 @@ fn get_connection_uri(config: &Configuration, auth: &Authentication) -> String {
 
     // TODO: Check if this is handled correctly with the changed (relative prefix)
     //  There would be a second slash in the prefix if the path is not relative
-    format!("ssh://{}:{}/{}{}{}", auth.hostname, auth.port, directory_prefix, separator, config.directory)
+    format!("ssh://{}:{}/{}{}{}", auth.hostname, auth.port, directory_prefix, separator, directory)
 }
\ No newline at end of file
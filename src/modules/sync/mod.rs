@@ -1,6 +1,11 @@
 use std::rc::Rc;
 use crate::modules::traits::Sync;
 use crate::util::objects::paths::{ModulePaths};
+use crate::util::objects::restore::{RestoreRequest, ArchiveEntry, FileEntry};
+use crate::util::objects::statistics::RunStatistics;
+use crate::util::objects::recovery::RecoveryInformation;
+use crate::util::objects::bandwidth::BandwidthConfiguration;
+use crate::util::objects::time::ExecutionTiming;
 use crate::Arguments;
 
 use serde_json::Value;
@@ -38,6 +43,32 @@ impl SyncModule {
 
         return Ok(SyncModule { module } );
     }
+
+    /**
+     * Check whether the module can restore into another directory (--restore-to) without setting it up, e.g. for a drill
+     */
+    pub fn check_restore_to(sync_type: &str, args: &Arguments) -> Result<(), String> {
+        return match sync_type.to_lowercase().as_str() {
+            duplicati::Duplicati::MODULE_NAME => duplicati::Duplicati::check_restore_to(args),
+            rsync::Rsync::MODULE_NAME => rsync::Rsync::check_restore_to(args),
+            ssh_gpg::SshGpg::MODULE_NAME => ssh_gpg::SshGpg::check_restore_to(args),
+            <borg::Borg as Sync>::MODULE_NAME => <borg::Borg as Sync>::check_restore_to(args),
+            unknown => Err(format!("Unknown sync module: '{}'", unknown))
+        };
+    }
+
+    /**
+     * Check whether the module supports a maintenance schedule
+     */
+    pub fn check_maintenance(sync_type: &str) -> Result<(), String> {
+        return match sync_type.to_lowercase().as_str() {
+            duplicati::Duplicati::MODULE_NAME => duplicati::Duplicati::check_maintenance(),
+            rsync::Rsync::MODULE_NAME => rsync::Rsync::check_maintenance(),
+            ssh_gpg::SshGpg::MODULE_NAME => ssh_gpg::SshGpg::check_maintenance(),
+            <borg::Borg as Sync>::MODULE_NAME => <borg::Borg as Sync>::check_maintenance(),
+            unknown => Err(format!("Unknown sync module: '{}'", unknown))
+        };
+    }
 }
 
 impl SyncWrapper for SyncModule {
@@ -45,12 +76,36 @@ impl SyncWrapper for SyncModule {
         self.module.init()
     }
 
-    fn sync(&self) -> Result<(), String> {
-        self.module.sync()
+    fn sync(&self, timing: &ExecutionTiming, bandwidth: Option<&BandwidthConfiguration>) -> Result<Option<RunStatistics>, String> {
+        self.module.sync(timing, bandwidth)
+    }
+
+    fn verify(&self) -> Result<(), String> {
+        self.module.verify()
+    }
+
+    fn restore(&self, request: &RestoreRequest) -> Result<(), String> {
+        self.module.restore(request)
+    }
+
+    fn list_archives(&self) -> Result<Vec<ArchiveEntry>, String> {
+        self.module.list_archives()
+    }
+
+    fn list_files(&self, archive: &ArchiveEntry) -> Result<Vec<FileEntry>, String> {
+        self.module.list_files(archive)
+    }
+
+    fn included_files(&self) -> Result<Option<Vec<String>>, String> {
+        self.module.included_files()
+    }
+
+    fn maintain(&self) -> Result<(), String> {
+        self.module.maintain()
     }
 
-    fn restore(&self) -> Result<(), String> {
-        self.module.restore()
+    fn recovery_information(&self) -> Result<Option<RecoveryInformation>, String> {
+        self.module.recovery_information()
     }
 
     fn clear(&mut self) -> Result<(), String> {
@@ -64,8 +119,14 @@ impl SyncWrapper for SyncModule {
 
 pub trait SyncWrapper {
     fn init(&mut self) -> Result<(), String>;
-    fn sync(&self) -> Result<(), String>;
-    fn restore(&self) -> Result<(), String>;
+    fn sync(&self, timing: &ExecutionTiming, bandwidth: Option<&BandwidthConfiguration>) -> Result<Option<RunStatistics>, String>;
+    fn verify(&self) -> Result<(), String>;
+    fn restore(&self, request: &RestoreRequest) -> Result<(), String>;
+    fn list_archives(&self) -> Result<Vec<ArchiveEntry>, String>;
+    fn list_files(&self, archive: &ArchiveEntry) -> Result<Vec<FileEntry>, String>;
+    fn included_files(&self) -> Result<Option<Vec<String>>, String>;
+    fn maintain(&self) -> Result<(), String>;
+    fn recovery_information(&self) -> Result<Option<RecoveryInformation>, String>;
     fn clear(&mut self) -> Result<(), String>;
     fn get_module_name(&self) -> &str;
 }
@@ -75,12 +136,36 @@ impl<T: Sync> SyncWrapper for T {
         Sync::init(self)
     }
 
-    fn sync(&self) -> Result<(), String> {
-        Sync::sync(self)
+    fn sync(&self, timing: &ExecutionTiming, bandwidth: Option<&BandwidthConfiguration>) -> Result<Option<RunStatistics>, String> {
+        Sync::sync(self, timing, bandwidth)
+    }
+
+    fn verify(&self) -> Result<(), String> {
+        Sync::verify(self)
+    }
+
+    fn restore(&self, request: &RestoreRequest) -> Result<(), String> {
+        Sync::restore(self, request)
+    }
+
+    fn list_archives(&self) -> Result<Vec<ArchiveEntry>, String> {
+        Sync::list_archives(self)
+    }
+
+    fn list_files(&self, archive: &ArchiveEntry) -> Result<Vec<FileEntry>, String> {
+        Sync::list_files(self, archive)
+    }
+
+    fn included_files(&self) -> Result<Option<Vec<String>>, String> {
+        Sync::included_files(self)
+    }
+
+    fn maintain(&self) -> Result<(), String> {
+        Sync::maintain(self)
     }
 
-    fn restore(&self) -> Result<(), String> {
-        Sync::restore(self)
+    fn recovery_information(&self) -> Result<Option<RecoveryInformation>, String> {
+        Sync::recovery_information(self)
     }
 
     fn clear(&mut self) -> Result<(), String> {
@@ -1,29 +1,42 @@
 use crate::modules::traits::Sync;
-use crate::util::command::CommandWrapper;
-use crate::util::io::{file,json,auth_data};
+use crate::util::command::{CommandWrapper, quote};
+use crate::util::io::{file,json,auth_data,savefile};
 use crate::util::docker;
-use crate::util::objects::paths::{ModulePaths,SourcePath};
+use crate::util::objects::paths::{ModulePaths,SourcePath,PathMapping};
 use crate::modules::shared::ssh::{SshConfig, write_identity_file, write_known_hosts};
-use crate::Arguments;
+use crate::util::objects::restore::{require_latest, parse_time_prefix, ArchiveSelector, ArchiveEntry, RestoreRequest};
+use crate::util::objects::statistics::RunStatistics;
+use crate::util::objects::time::ExecutionTiming;
+use crate::util::objects::bandwidth::{BandwidthConfiguration, current_limit, to_kibibytes};
+use crate::{Arguments, dry_run, try_option};
 
 use serde_json::Value;
 use serde::{Deserialize};
+use std::fs;
 use std::path::Path;
 use std::rc::Rc;
 
 pub struct Rsync {
-    _name: String,
+    name: String,
     config: Configuration,
     ssh_config: SshConfig,
     module_paths: ModulePaths,
     sync_paths: DockerPaths,
+    snapshot_paths: Option<SnapshotPaths>,
     args: Rc<Arguments>
 }
 
 struct DockerPaths {
     volume: Option<SourcePath>,
     from: String,
-    to: String
+    to: String,
+    mappings: Option<Vec<PathMapping>> // multiple source paths without docker
+}
+
+struct SnapshotPaths {
+    local: String, // the directory whose content is synced
+    remote: String, // the directory containing the snapshots, including the login for rsync
+    remote_dir: String // the directory containing the snapshots, for commands run on the remote
 }
 
 #[derive(Deserialize)]
@@ -65,7 +78,19 @@ struct Configuration {
     #[serde(default="default_false")]
     detect_renamed_lax: bool,
     #[serde(default="default_false")]
-    detect_moved: bool
+    detect_moved: bool,
+
+    // Sync into a new dated directory every time, unchanged files are hard linked to the previous snapshot
+    #[serde(default="default_false")]
+    snapshots: bool,
+
+    // Guards against wiping the copy with --delete, e.g. when the source is not mounted
+    max_deletions: Option<usize>,
+    max_deletions_percent: Option<f64>,
+    #[serde(default="default_false")]
+    allow_empty_source: bool,
+    #[serde(default="default_false")]
+    require_mount_point: bool
 }
 
 fn default_true() -> bool { true }
@@ -80,8 +105,8 @@ impl Sync for Rsync {
         let mut config = json::from_value::<Configuration>(config_json.clone())?; // TODO: - clone
         let ssh_config = auth_data::resolve::<SshConfig>(&config.host_reference, &config.host, module_paths.base_paths.as_ref())?;
 
-        if args.no_docker && args.is_restore && args.restore_to.is_some() {
-            return Err(format!("The restore-to option is not supported for {} without docker", Rsync::MODULE_NAME));
+        if args.is_restore && args.restore_to.is_some() {
+            Self::check_restore_to(args)?;
         }
 
         let remote_path = format!("{}@{}:{}",
@@ -100,53 +125,120 @@ impl Sync for Rsync {
             ""
         };
 
+        if let Some(percent) = config.max_deletions_percent {
+            if !(0f64..=100f64).contains(&percent) {
+                return Err(format!("The maximum percentage of deletions has to be between 0 and 100, not {}", percent));
+            }
+        }
+
+        if args.no_docker && !config.to_remote {
+            if let SourcePath::Multiple(_) = module_paths.source {
+                return Err(format!("Syncing from the remote into multiple source paths is not supported by {} without docker", Rsync::MODULE_NAME));
+            }
+        }
+
+        if config.snapshots && !config.to_remote {
+            return Err(format!("Snapshots are only supported by {} when syncing to the remote", Rsync::MODULE_NAME));
+        }
+
         // reverse the direction in case of a restore
         config.to_remote ^= args.is_restore; // exclusive or assignment
 
         let sync_paths= if args.no_docker {
-            if let SourcePath::Single(source_path) = module_paths.source.clone() {
-                if config.to_remote {
-                    DockerPaths {
-                        volume: None,
-                        from: source_path,
-                        to: remote_path,
+            match module_paths.source.clone() {
+                SourcePath::Single(source_path) => {
+                    if config.to_remote {
+                        DockerPaths {
+                            volume: None,
+                            from: source_path,
+                            to: remote_path,
+                            mappings: None
+                        }
+                    } else {
+                        DockerPaths {
+                            volume: None,
+                            from: format!("{}{}{}", remote_path, separator, config.dirname),
+                            to: source_path,
+                            mappings: None
+                        }
                     }
-                } else {
-                    DockerPaths {
-                        volume: None,
-                        from: format!("{}{}{}", remote_path, separator, config.dirname),
-                        to: source_path,
+                },
+                // The source paths are linked into a directory under their names, to get the same layout as the volumes in docker
+                SourcePath::Multiple(mappings) => {
+                    if config.to_remote {
+                        DockerPaths {
+                            volume: None,
+                            from: get_links_dir(&module_paths, config.dirname.as_str()),
+                            to: remote_path,
+                            mappings: Some(mappings)
+                        }
+                    } else {
+                        DockerPaths {
+                            volume: None,
+                            from: format!("{}{}{}", remote_path, separator, config.dirname),
+                            to: String::new(), // every source path is restored on its own
+                            mappings: Some(mappings)
+                        }
                     }
                 }
-            } else {
-                return Err(String::from("Multiple source paths are not supported in rsync module without docker"));
             }
         } else {
             if config.to_remote {
                 DockerPaths {
                     volume: Some(module_paths.source.clone()),
                     from: format!("/{}", config.dirname),
-                    to: remote_path
+                    to: remote_path,
+                    mappings: None
                 }
             } else {
                 DockerPaths {
                     volume: Some(module_paths.source.clone()),
                     from: format!("{}{}{}", remote_path, separator, config.dirname),
-                    to: String::from("/")
+                    to: String::from("/"),
+                    mappings: None
                 }
             }
         };
 
+        // Snapshots are always stored in the directory given by dirname, the content of the source is synced into them
+        let snapshot_paths = if config.snapshots {
+            let remote_dir = format!("{}{}{}", config.path_prefix.as_ref().map_or("", |prefix| prefix.as_str()), separator, config.dirname);
+            let local = if sync_paths.volume.is_some() {
+                format!("/{}", config.dirname)
+            } else if let SourcePath::Single(source_path) = module_paths.source.clone() {
+                source_path
+            } else {
+                get_links_dir(&module_paths, config.dirname.as_str())
+            };
+
+            Some(SnapshotPaths {
+                local,
+                remote: format!("{}@{}:{}", ssh_config.user, ssh_config.hostname, remote_dir),
+                remote_dir
+            })
+        } else {
+            None
+        };
+
         return Ok(Box::new(Self {
-            _name: String::from(name),
+            name: String::from(name),
             config,
             ssh_config,
             module_paths,
             sync_paths,
+            snapshot_paths,
             args: args.clone()
         }));
     }
 
+    fn check_restore_to(args: &Arguments) -> Result<(), String> {
+        if args.no_docker {
+            return Err(format!("The restore-to option is not supported for {} without docker", Rsync::MODULE_NAME));
+        }
+
+        return Ok(());
+    }
+
     fn init(&mut self) -> Result<(), String> {
         // Build local docker image if missing
         if !self.args.no_docker {
@@ -159,13 +251,24 @@ impl Sync for Rsync {
 
         file::create_path_dir_if_missing(Path::new(&self.module_paths.module_data_dir), true)?;
 
+        if let Some(mappings) = self.sync_paths.mappings.as_ref() {
+            if self.config.to_remote {
+                self.link_sources(mappings)?;
+            }
+        }
+
         write_known_hosts(&self.ssh_config, &self.module_paths, self.args.dry_run)?;
         write_identity_file(&self.ssh_config, &self.module_paths, self.args.dry_run)?;
 
         return Ok(());
     }
 
-    fn sync(&self) -> Result<(), String> {
+    fn sync(&self, timing: &ExecutionTiming, bandwidth: Option<&BandwidthConfiguration>) -> Result<Option<RunStatistics>, String> {
+        // The local source is only checked when syncing to the remote
+        if self.config.to_remote {
+            self.check_source()?;
+        }
+
         let mut command = self.get_base_cmd()?;
 
         if self.config.detect_renamed {
@@ -180,27 +283,500 @@ impl Sync for Rsync {
             command.arg_str("--detect-moved");
         }
 
-        command.arg_string(format!("{}", &self.sync_paths.from))
-            .arg_string(format!("{}", &self.sync_paths.to));
+        if let Some(limit) = current_limit(bandwidth)? {
+            command.arg_string(format!("--bwlimit={}", to_kibibytes(limit)));
+        }
 
-        return command.run_with_args(self.args.as_ref());
+        // Print a summary at the end, which is parsed for the statistics of the run
+        command.arg_str("--stats");
+
+        // A new snapshot is always empty, so deletions are counted against the latest one
+        if let Some(snapshot_paths) = self.snapshot_paths.as_ref() {
+            self.check_deletions(snapshot_paths.local.as_str(), true, format!("{}/latest/", snapshot_paths.remote))?;
+            return self.sync_snapshot(command, snapshot_paths, timing);
+        }
+
+        self.check_deletions(self.sync_paths.from.as_str(), false, self.sync_paths.to.clone())?;
+
+        self.append_from(&mut command, self.sync_paths.from.as_str(), false);
+        command.arg_string(format!("{}", &self.sync_paths.to));
+
+        return self.run_sync(command);
+    }
+
+    fn verify(&self) -> Result<(), String> {
+        let mut command = self.get_base_cmd()?;
+
+        // With snapshots, the source is compared to the latest one
+        let (from, content_only, to) = if let Some(snapshot_paths) = self.snapshot_paths.as_ref() {
+            (snapshot_paths.local.as_str(), true, format!("{}/latest/", snapshot_paths.remote))
+        } else {
+            (self.sync_paths.from.as_str(), false, self.sync_paths.to.clone())
+        };
+
+        // Compare the content of all files instead of size and time, without changing anything
+        command.arg_str("--checksum")
+            .arg_str("--itemize-changes");
+
+        if !self.args.dry_run {
+            command.arg_str("--dry-run");
+        }
+
+        self.append_from(&mut command, from, content_only);
+        command.arg_string(to);
+
+        if self.args.dry_run {
+            dry_run!(command.to_string());
+            return Ok(());
+        }
+
+        info!("Comparing checksums of local and remote files...");
+        let output = command.run_get_output()?;
+
+        // Itemized changes start with the update type, files that would be transferred or deleted differ
+        let differences: Vec<&str> = output.lines()
+            .filter(|line| line.starts_with('<') || line.starts_with('>') || line.starts_with("*deleting"))
+            .collect();
+
+        if differences.is_empty() {
+            return Ok(());
+        }
+
+        for difference in differences.iter() {
+            debug!("Difference: {}", difference);
+        }
+
+        return Err(format!("{} file(s) differ between the local and the remote copy", differences.len()));
     }
 
-    fn restore(&self) -> Result<(), String> {
+    fn restore(&self, request: &RestoreRequest) -> Result<(), String> {
+        if let Some(snapshot_paths) = self.snapshot_paths.as_ref() {
+            return self.restore_snapshot(snapshot_paths, request);
+        }
+
+        require_latest(&request.archive, Rsync::MODULE_NAME)?;
+
+        if let Some(mappings) = self.sync_paths.mappings.as_ref() {
+            return self.restore_mappings(mappings, self.sync_paths.from.as_str(), request);
+        }
+
         let mut command = self.get_base_cmd()?;
 
-        command.arg_string(format!("{}", &self.sync_paths.from))
-            .arg_string(format!("{}", &self.sync_paths.to));
+        if let Some(path) = request.path.as_ref() {
+            // Pull only the subtree: the '/./' marks the part of the path that is recreated at the destination
+            let prefix = self.sync_paths.from.strip_suffix(self.config.dirname.as_str());
+            if self.config.to_remote || prefix.is_none() {
+                return Err(format!("Restoring a single path is only supported for {} if the data is synced to the remote", Rsync::MODULE_NAME));
+            }
+
+            command.arg_str("--relative")
+                .arg_string(format!("{}./{}/{}", prefix.unwrap(), self.config.dirname, path))
+                .arg_string(format!("{}", &self.sync_paths.to));
+        } else {
+            command.arg_string(format!("{}", &self.sync_paths.from))
+                .arg_string(format!("{}", &self.sync_paths.to));
+        }
 
         return command.run_with_args(self.args.as_ref());
     }
 
+    fn list_archives(&self) -> Result<Vec<ArchiveEntry>, String> {
+        let snapshot_paths = try_option!(self.snapshot_paths.as_ref(), format!("Listing archives is only supported by the {} sync module with snapshots", Rsync::MODULE_NAME));
+        let (snapshots, _) = self.list_snapshots(snapshot_paths)?;
+
+        return Ok(snapshots.into_iter()
+            .map(|name| {
+                let time = parse_time_prefix(name.as_str(), 0, "%Y-%m-%d_%H-%M-%S", 19);
+                ArchiveEntry { id: name, time }
+            })
+            .collect());
+    }
+
+    /*
+     * List the local source with the same filters as the sync, only syncs to the remote filter the local files
+     *  rsync --recursive --list-only <filters> <local>
+     */
+    fn included_files(&self) -> Result<Option<Vec<String>>, String> {
+        // the direction is reversed for restores
+        if self.config.to_remote == self.args.is_restore {
+            return Ok(None);
+        }
+
+        // Always the actual source, even when restoring to another directory
+        let mut command = if let Some(volume) = self.sync_paths.volume.as_ref() {
+            CommandWrapper::new_docker(
+                "rsync-vbackup-tmp",
+                self.get_image_name(),
+                Some(self.config.local_rsync.as_str()),
+                None,
+                &self.module_paths,
+                (volume, &self.config.dirname),
+                None
+            )
+        } else {
+            CommandWrapper::new(self.config.local_rsync.as_str())
+        };
+
+        command.arg_str("--recursive").arg_str("--list-only");
+        self.append_filters(&mut command);
+
+        // The directory itself is synced without snapshots, so the listing starts with its name
+        let content_only = self.snapshot_paths.is_some();
+        let local = if let Some(snapshot_paths) = self.snapshot_paths.as_ref() {
+            snapshot_paths.local.clone()
+        } else if self.sync_paths.volume.is_some() {
+            format!("/{}", self.config.dirname)
+        } else if let SourcePath::Single(source_path) = &self.module_paths.source {
+            source_path.clone()
+        } else {
+            get_links_dir(&self.module_paths, self.config.dirname.as_str())
+        };
+
+        if let Some(mappings) = self.sync_paths.mappings.as_ref() {
+            self.link_sources(mappings)?;
+        }
+        self.append_from(&mut command, local.as_str(), content_only);
+
+        let output = command.run_get_output()?;
+        return Ok(Some(output.lines()
+            .filter_map(|line| parse_listed_file(line, !content_only))
+            .collect()));
+    }
+
     fn clear(&mut self) -> Result<(), String> {
         return Ok(());
     }
 }
 
 impl Rsync {
+    /*
+     * An empty source directory or a missing mount would delete everything on the other side
+     */
+    fn check_source(&self) -> Result<(), String> {
+        let source_paths = match &self.module_paths.source {
+            SourcePath::Single(path) => vec![path.as_str()],
+            SourcePath::Multiple(paths) => paths.iter().map(|path| path.path.as_str()).collect()
+        };
+
+        for source_path in source_paths {
+            if !source_path.starts_with('/') {
+                debug!("Source '{}' is a docker volume and is not checked", source_path);
+                continue;
+            }
+
+            if !Path::new(source_path).is_dir() {
+                return Err(format!("Source directory '{}' does not exist, refusing to sync", source_path));
+            }
+
+            if self.config.require_mount_point && !file::is_mount_point(source_path)? {
+                return Err(format!("Source directory '{}' is not a mount point, refusing to sync", source_path));
+            }
+
+            if !self.config.allow_empty_source && file::list_in_dir(source_path)?.is_empty() {
+                return Err(format!("Source directory '{}' is empty, refusing to sync", source_path));
+            }
+        }
+
+        return Ok(());
+    }
+
+    /*
+     * Simulate the sync first and abort if more files would be deleted than allowed
+     */
+    fn check_deletions(&self, from: &str, content_only: bool, to: String) -> Result<(), String> {
+        if self.config.max_deletions.is_none() && self.config.max_deletions_percent.is_none() {
+            return Ok(());
+        }
+
+        let mut command = self.get_base_cmd()?;
+        command.arg_str("--itemize-changes")
+            .arg_str("--stats");
+
+        if !self.args.dry_run {
+            command.arg_str("--dry-run");
+        }
+
+        self.append_from(&mut command, from, content_only);
+        command.arg_string(to);
+
+        if self.args.dry_run {
+            dry_run!(command.to_string());
+            return Ok(());
+        }
+
+        info!("Checking the amount of files that would be deleted...");
+        let output = command.run_get_output()?;
+
+        // Itemized changes of new files have all attributes set to '+', e.g. '>f+++++++++'
+        let deleted = output.lines().filter(|line| line.starts_with("*deleting")).count();
+        let created = output.lines().filter(|line| line.get(2..11) == Some("+++++++++")).count();
+        let total = parse_file_count(output.as_str()).map(|files| (files + deleted).saturating_sub(created));
+
+        let percent = match total {
+            Some(0) | None => 0f64,
+            Some(total) => deleted as f64 * 100f64 / total as f64
+        };
+        debug!("{} of {} file(s) would be deleted ({:.1}%)", deleted, total.map_or(String::from("?"), |total| total.to_string()), percent);
+
+        if let Some(max_deletions) = self.config.max_deletions {
+            if deleted > max_deletions {
+                return Err(format!("Aborting sync, {} file(s) would be deleted, but at most {} are allowed", deleted, max_deletions));
+            }
+        }
+
+        if let Some(max_deletions_percent) = self.config.max_deletions_percent {
+            if percent > max_deletions_percent {
+                return Err(format!("Aborting sync, {:.1}% of the files would be deleted, but at most {}% are allowed", percent, max_deletions_percent));
+            }
+        }
+
+        return Ok(());
+    }
+
+    fn run_sync(&self, mut command: CommandWrapper) -> Result<Option<RunStatistics>, String> {
+        let output = match command.run_get_output_with_args(self.args.as_ref())? {
+            Some(output) => output,
+            None => return Ok(None)
+        };
+
+        let statistics = parse_statistics(output.as_str());
+        info!("Sync statistics: {}", statistics);
+
+        return Ok(Some(statistics));
+    }
+
+    /*
+     * Sync into a new snapshot directory, which is only renamed to its final name once the sync succeeded
+     *  rsync --link-dest=../<previous> <local>/ user@server:<dirname>/<snapshot>.incomplete/
+     *  ssh user@server "cd '<dirname>' && mv '<snapshot>.incomplete' '<snapshot>' && ln -sfn '<snapshot>' latest && rm -rf <expired>"
+     */
+    fn sync_snapshot(&self, mut command: CommandWrapper, snapshot_paths: &SnapshotPaths, timing: &ExecutionTiming) -> Result<Option<RunStatistics>, String> {
+        let snapshot = savefile::format_filename(&timing.execution_time, &timing.time_frame_reference, self.name.as_str(), Some("snapshot"), None);
+        let incomplete = format!("{}.incomplete", snapshot);
+        let (snapshots, mut incomplete_snapshots) = self.list_snapshots(snapshot_paths)?;
+
+        // An interrupted sync is continued in the new snapshot instead of starting over
+        let mut prepare_command = format!("mkdir -p {0} && cd {0}", quote(snapshot_paths.remote_dir.as_str()));
+        if let Some(interrupted) = incomplete_snapshots.pop() {
+            debug!("Continuing the interrupted snapshot '{}'", interrupted);
+            prepare_command.push_str(format!(" && mv {} {}", quote(interrupted.as_str()), quote(incomplete.as_str())).as_str());
+        }
+        if !incomplete_snapshots.is_empty() {
+            prepare_command.push_str(format!(" && rm -rf {}", quote_all(&incomplete_snapshots)).as_str());
+        }
+        self.get_remote_cmd(prepare_command.as_str())?.run_with_args(self.args.as_ref())?;
+
+        // Unchanged files are hard linked to the previous snapshot instead of transferred again
+        if let Some(previous) = snapshots.last() {
+            command.arg_string(format!("--link-dest=../{}", previous));
+        }
+
+        self.append_from(&mut command, snapshot_paths.local.as_str(), true);
+        command.arg_string(format!("{}/{}/", snapshot_paths.remote, incomplete));
+
+        let statistics = self.run_sync(command)?;
+
+        // Keep the amount of snapshots of the interval, including the new one
+        let suffix = format!("_{}_{}_snapshot", timing.time_frame_reference.frame, self.name);
+        let in_timeframe: Vec<&String> = snapshots.iter().filter(|name| name.ends_with(suffix.as_str())).collect();
+        let expired_count = (in_timeframe.len() + 1).saturating_sub(timing.time_frame_reference.amount.max(1));
+        let expired: Vec<String> = in_timeframe.into_iter().take(expired_count).cloned().collect();
+
+        let mut finish_command = format!("cd {0} && mv {1} {2} && ln -sfn {2} latest", quote(snapshot_paths.remote_dir.as_str()), quote(incomplete.as_str()), quote(snapshot.as_str()));
+        if !expired.is_empty() {
+            debug!("Removing expired snapshots <{}>", expired.join(" "));
+            finish_command.push_str(format!(" && rm -rf {}", quote_all(&expired)).as_str());
+        }
+        self.get_remote_cmd(finish_command.as_str())?.run_with_args(self.args.as_ref())?;
+
+        return Ok(statistics);
+    }
+
+    fn restore_snapshot(&self, snapshot_paths: &SnapshotPaths, request: &RestoreRequest) -> Result<(), String> {
+        let selector = match request.archive.as_ref() {
+            Some(archive) => ArchiveSelector::parse(archive.as_str())?,
+            None => ArchiveSelector::Latest
+        };
+
+        // The latest snapshot is referenced by the symlink, there is no need to list them
+        let snapshot = if let ArchiveSelector::Latest = selector {
+            String::from("latest")
+        } else {
+            let snapshots = self.list_archives()?;
+            let index = selector.select(&snapshots, &|entry| entry.id.as_str(), &|entry| entry.time)?;
+            snapshots[index].id.clone()
+        };
+
+        info!("Restoring snapshot '{}'", snapshot);
+
+        if let Some(mappings) = self.sync_paths.mappings.as_ref() {
+            return self.restore_mappings(mappings, format!("{}/{}", snapshot_paths.remote, snapshot).as_str(), request);
+        }
+
+        let mut command = self.get_base_cmd()?;
+
+        if let Some(path) = request.path.as_ref() {
+            // Pull only the subtree: the '/./' marks the part of the path that is recreated at the destination
+            command.arg_str("--relative")
+                .arg_string(format!("{}/{}/./{}", snapshot_paths.remote, snapshot, path))
+                .arg_string(format!("{}/", snapshot_paths.local));
+        } else {
+            command.arg_string(format!("{}/{}/", snapshot_paths.remote, snapshot))
+                .arg_string(format!("{}/", snapshot_paths.local));
+        }
+
+        return command.run_with_args(self.args.as_ref());
+    }
+
+    /*
+     * Without docker, every source path is restored on its own from the directory with its name
+     *  the first part of the restore path is the name of the source path
+     */
+    fn restore_mappings(&self, mappings: &[PathMapping], from: &str, request: &RestoreRequest) -> Result<(), String> {
+        let selected = if let Some(path) = request.path.as_ref() {
+            let (name, rest) = path.split_once('/').unwrap_or((path.as_str(), ""));
+            let mapping = try_option!(mappings.iter().find(|mapping| mapping.name == name), format!("There is no source path with the name '{}'", name));
+            vec![(mapping, Some(rest).filter(|rest| !rest.is_empty()))]
+        } else {
+            mappings.iter().map(|mapping| (mapping, None)).collect()
+        };
+
+        for (mapping, path) in selected {
+            info!("Restoring '{}' to '{}'", mapping.name, mapping.path);
+            let mut command = self.get_base_cmd()?;
+
+            if let Some(path) = path {
+                command.arg_str("--relative")
+                    .arg_string(format!("{}/{}/./{}", from, mapping.name, path));
+            } else {
+                command.arg_string(format!("{}/{}/", from, mapping.name));
+            }
+
+            command.arg_string(format!("{}/", mapping.path.trim_end_matches('/')));
+            command.run_with_args(self.args.as_ref())?;
+        }
+
+        return Ok(());
+    }
+
+    /*
+     * Append the local directory as source, either the directory itself or only its content
+     *  multiple source paths without docker are symlinks in the directory, which are sent with --relative under their names
+     *  <module_data_dir>/sources/./<dirname>/<name>/ or <module_data_dir>/sources/<dirname>/./<name>/ for the content only
+     */
+    fn append_from(&self, command: &mut CommandWrapper, from: &str, content_only: bool) {
+        if let Some(mappings) = self.sync_paths.mappings.as_ref() {
+            command.arg_str("--relative");
+
+            for mapping in mappings {
+                // the trailing slash makes rsync follow the symlink, but not the ones in the source path
+                if content_only {
+                    command.arg_string(format!("{}/./{}/", from, mapping.name));
+                } else {
+                    command.arg_string(format!("{}/sources/./{}/{}/", self.module_paths.module_data_dir, self.config.dirname, mapping.name));
+                }
+            }
+        } else if content_only {
+            command.arg_string(format!("{}/", from));
+        } else {
+            command.arg_str(from);
+        }
+    }
+
+    /*
+     * Link the source paths into a directory under their names, links of removed source paths are removed
+     */
+    fn link_sources(&self, mappings: &[PathMapping]) -> Result<(), String> {
+        let links_dir = get_links_dir(&self.module_paths, self.config.dirname.as_str());
+
+        if self.args.dry_run {
+            dry_run!(format!("Linking {} source path(s) into '{}'", mappings.len(), links_dir));
+            return Ok(());
+        }
+
+        file::create_dir_if_missing(links_dir.as_str(), true)?;
+
+        for existing in file::list_in_dir(links_dir.as_str())? {
+            let name = existing.file_name().and_then(|name| name.to_str()).unwrap_or("");
+            let target = fs::read_link(&existing).ok();
+
+            let is_current = mappings.iter().any(|mapping| mapping.name == name && target.as_ref().map_or(false, |target| target == Path::new(mapping.path.as_str())));
+            if !is_current {
+                file::remove(existing.to_string_lossy().as_ref())?;
+            }
+        }
+
+        for mapping in mappings {
+            let link = format!("{}/{}", links_dir, mapping.name);
+            if fs::symlink_metadata(link.as_str()).is_err() {
+                file::symlink(mapping.path.as_str(), link.as_str())?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    /*
+     * List the names of complete and incomplete snapshots on the remote, both sorted from oldest to newest
+     */
+    fn list_snapshots(&self, snapshot_paths: &SnapshotPaths) -> Result<(Vec<String>, Vec<String>), String> {
+        if self.args.dry_run {
+            debug!("Retrieving the list of snapshots is not possible during a dry-run, assuming there are none");
+            return Ok((vec![], vec![]));
+        }
+
+        // use some random string to indicate the start of command output, in case there is some banner
+        let command_start = "===== THIS IS A SEPARATOR FOR THE ACTUAL COMMAND OUTPUT =====";
+        let list_command = format!("echo {} && if cd {} 2>/dev/null; then for entry in *_snapshot *_snapshot.incomplete; do if [ -d \"$entry\" ] && [ ! -L \"$entry\" ]; then echo \"$entry\"; fi; done; fi", command_start, quote(snapshot_paths.remote_dir.as_str()));
+
+        let output = self.get_remote_cmd(list_command.as_str())?.run_get_output()?;
+        let mut entries: Vec<String> = output.lines()
+            .skip_while(|line| *line != command_start)
+            .skip(1)
+            .map(String::from)
+            .collect();
+
+        // names start with an ISO date and thus can be sorted by name
+        entries.sort();
+
+        return Ok(entries.into_iter().partition(|entry| !entry.ends_with(".incomplete")));
+    }
+
+    fn get_remote_cmd(&self, remote_command: &str) -> Result<CommandWrapper,String> {
+        let mut command = if let Some(docker_paths) = self.sync_paths.volume.as_ref() {
+            CommandWrapper::new_docker(
+                "rsync-vbackup-tmp",
+                self.get_image_name(),
+                Some("sh"),
+                Some(vec!["-c"]),
+                &self.module_paths,
+                (docker_paths, &self.config.dirname),
+                Some(vec![
+                    "--env=SSHPASS"
+                ])
+            )
+        } else {
+            CommandWrapper::new_with_args("sh", vec!["-c"])
+        };
+
+        // the command needs to be wrapped in a single argument when running over SSH
+        command.wrap();
+        command.append_ssh_command(&self.ssh_config, &self.module_paths, !self.args.no_docker, false)?
+            .arg_string(format!("{}@{}", self.ssh_config.user, self.ssh_config.hostname))
+            .arg_string(quote(remote_command));
+        command.wrap();
+
+        return Ok(command);
+    }
+
+    fn get_image_name(&self) -> &str {
+        if self.config.detect_renamed || self.config.detect_renamed_lax || self.config.detect_moved {
+            return "vbackup-rsync-patched";
+        } else {
+            return "vbackup-rsync";
+        }
+    }
+
     fn get_base_cmd(&self) -> Result<CommandWrapper,String> {
         if !self.args.dry_run {
             file::create_dir_if_missing(self.module_paths.module_data_dir.as_str(), true)?;
@@ -209,11 +785,7 @@ impl Rsync {
         // Distinguish run in docker and directly on the machine
         let mut command = if let Some(docker_paths) = self.sync_paths.volume.as_ref() {
             // End docker command with docker image name
-            let image_name = if self.config.detect_renamed || self.config.detect_renamed_lax || self.config.detect_moved {
-                "vbackup-rsync-patched"
-            } else {
-                "vbackup-rsync"
-            };
+            let image_name = self.get_image_name();
 
             let mut source_overwrite = None;
             if self.args.is_restore {
@@ -273,7 +845,35 @@ impl Rsync {
             }
         }
 
-        // Parse include and exclude options
+        self.append_filters(&mut command);
+
+        if let Some(rsync_path) = self.config.remote_rsync.as_ref() {
+            command.arg_string(format!("--rsync-path={}", rsync_path));
+        }
+
+        if self.config.compress {
+            command.arg_str("--compress");
+        }
+
+        if self.args.dry_run {
+            command.arg_str("--dry-run");
+        }
+
+        if self.args.verbose || self.args.show_command_output {
+            command.arg_str("--verbose");
+        }
+
+        if let Some(args) = self.config.additional_args.as_ref() {
+            for arg in args {
+                command.arg_str(arg.as_str());
+            }
+        }
+
+        return Ok(command);
+    }
+
+    // Parse include and exclude options
+    fn append_filters(&self, command: &mut CommandWrapper) {
         if self.config.filter.is_some() || self.config.include.is_some() || self.config.exclude.is_some() {
             if let Some(filter_list) = self.config.filter.as_ref() {
                 filter_list.iter().for_each(|filter_option| {
@@ -300,29 +900,80 @@ impl Rsync {
                 command.arg_str("--exclude=*");
             }
         }
+    }
+}
 
-        if let Some(rsync_path) = self.config.remote_rsync.as_ref() {
-            command.arg_string(format!("--rsync-path={}", rsync_path));
+/**
+ * Parse the summary printed with --stats, numbers may contain thousands separators
+ *  Number of regular files transferred: 2 (older versions: Number of files transferred: 2)
+ *  Total bytes sent: 1,456
+ *  Total bytes received: 89
+ *  total size is 1,234  speedup is 0.80
+ */
+fn parse_statistics(output: &str) -> RunStatistics {
+    let mut statistics = RunStatistics::default();
+
+    for line in output.lines() {
+        if let Some((key, value)) = line.split_once(": ") {
+            let number = value.split_whitespace().next()
+                .and_then(|number| number.replace(',', "").parse::<u64>().ok());
+
+            match key.trim() {
+                "Number of regular files transferred" | "Number of files transferred" => statistics.files_transferred = number,
+                "Total bytes sent" => statistics.bytes_sent = number,
+                "Total bytes received" => statistics.bytes_received = number,
+                _ => {}
+            }
+        } else if let Some((_, speedup)) = line.split_once("speedup is ") {
+            statistics.speedup = speedup.split_whitespace().next()
+                .and_then(|speedup| speedup.replace(',', "").parse::<f64>().ok());
         }
+    }
 
-        if self.config.compress {
-            command.arg_str("--compress");
-        }
+    return statistics;
+}
 
-        if self.args.dry_run {
-            command.arg_str("--dry-run");
-        }
+/**
+ * The number of files in the source from the summary printed with --stats
+ *  Number of files: 1,234 (reg: 1,000, dir: 234)
+ */
+fn parse_file_count(output: &str) -> Option<usize> {
+    return output.lines()
+        .find_map(|line| line.strip_prefix("Number of files: "))
+        .and_then(|value| value.split_whitespace().next())
+        .and_then(|number| number.replace(',', "").parse::<usize>().ok());
+}
 
-        if self.args.verbose || self.args.show_command_output {
-            command.arg_str("--verbose");
-        }
+/**
+ * Get the path of a regular file from the output of --list-only, directories and links are skipped
+ *  -rw-r--r--          1,234 2024/01/01 12:00:00 some/file.txt
+ */
+fn parse_listed_file(line: &str, strip_directory: bool) -> Option<String> {
+    if !line.starts_with('-') {
+        return None;
+    }
 
-        if let Some(args) = self.config.additional_args.as_ref() {
-            for arg in args {
-                command.arg_str(arg.as_str());
-            }
-        }
+    // permissions, size, date and time are separated by (multiple) spaces, the path may contain spaces
+    let mut rest = line;
+    for _ in 0..4 {
+        let end = rest.find(' ')?;
+        rest = rest[end..].trim_start();
+    }
 
-        return Ok(command);
+    if strip_directory {
+        return rest.split_once('/').map(|(_, path)| String::from(path));
+    } else {
+        return Some(String::from(rest));
     }
+}
+
+fn get_links_dir(module_paths: &ModulePaths, dirname: &str) -> String {
+    return format!("{}/sources/{}", module_paths.module_data_dir, dirname);
+}
+
+fn quote_all(names: &[String]) -> String {
+    return names.iter()
+        .map(|name| quote(name.as_str()))
+        .collect::<Vec<String>>()
+        .join(" ");
 }
\ No newline at end of file
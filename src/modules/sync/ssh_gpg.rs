@@ -4,26 +4,100 @@ use crate::Arguments;
 use crate::util::docker;
 use crate::util::io::{json, auth_data, file};
 use crate::modules::shared::ssh::{SshConfig, write_identity_file, write_known_hosts};
-use crate::util::command::CommandWrapper;
+use crate::modules::shared::encryption::{self, EncryptionType, PublicKeyEncryption};
+use crate::modules::shared::archive::{self, checked_pipeline};
+use crate::util::command::{self, CommandWrapper};
 use crate::{try_option,dry_run};
 
 use serde_json::Value;
-use serde::{Deserialize};
+use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
+use std::collections::{HashMap, VecDeque};
 use std::path::Path;
 use std::rc::Rc;
-use crate::util::io::user::ask_user_abort;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use crate::util::io::user::confirm_abort;
+use crate::util::objects::restore::{require_latest, RestoreRequest};
+use crate::util::objects::statistics::RunStatistics;
+use crate::util::objects::time::ExecutionTiming;
+use crate::util::objects::bandwidth::{BandwidthConfiguration, current_limit};
 
 #[derive(Deserialize)]
 struct Configuration {
-    encryption_key: String,
+    encryption_key: Option<String>, // Symmetric encryption with a shared passphrase
+    encryption: Option<PublicKeyEncryption>, // Takes precedence over encryption_key for new files
     remote_path: String,
 
     host: Option<Value>,
     host_reference: Option<String>,
 
     remote_chmod: Option<String>,
-    local_chmod: Option<String>
+    local_chmod: Option<String>,
+
+    #[serde(default="default_verify_mode")]
+    verify_mode: VerifyMode,
+
+    #[serde(default="default_parallel_uploads")]
+    parallel_uploads: usize,
+    #[serde(default="default_upload_retries")]
+    upload_retries: u64
+}
+
+#[derive(Deserialize,PartialEq)]
+#[serde(rename_all="lowercase")]
+enum VerifyMode {
+    Checksum, // Decrypt the remote files and compare their checksums with the local files
+    Size // Only compare the sizes of the remote files with the last upload
+}
+
+fn default_verify_mode() -> VerifyMode { VerifyMode::Checksum }
+fn default_parallel_uploads() -> usize { 1 }
+fn default_upload_retries() -> u64 { 2 }
+
+// Seconds to wait before retrying a failed upload, multiplied with the number of the attempt
+const RETRY_DELAY: u64 = 10;
+
+// Size and modification time of a listed file
+struct FileInfo {
+    size: u64,
+    mtime: i64
+}
+
+struct RemoteFile {
+    file: String,
+    size: u64
+}
+
+// What is known about an uploaded file, kept in the state file to detect changes
+#[derive(Serialize, Deserialize)]
+struct UploadState {
+    size: u64,
+    mtime: i64,
+    sha256: String,
+    remote_file: String,
+    remote_size: u64
+}
+
+// Shell command of an upload, the bandwidth limit is only filled in right before the upload starts
+struct UploadPipeline {
+    stages: Vec<String>,
+    send: String,
+    finish: String
+}
+
+impl UploadPipeline {
+    fn command(&self, rate_limit: Option<u64>) -> String {
+        let mut stages = self.stages.clone();
+        if let Some(limit) = rate_limit {
+            stages.push(format!("pv -q -L {}", limit));
+        }
+        stages.push(self.send.clone());
+
+        return format!("{} && {}", checked_pipeline(&stages), self.finish);
+    }
 }
 
 pub struct SshGpg {
@@ -35,8 +109,8 @@ pub struct SshGpg {
     local_path: String,
     file_extension: String,
     passphrase_file: String,
-    args: Rc<Arguments>,
-    tmp_file: String
+    state_file: String,
+    args: Rc<Arguments>
 }
 
 impl Sync for SshGpg {
@@ -46,8 +120,30 @@ impl Sync for SshGpg {
         let config = json::from_value::<Configuration>(config_json.clone())?; // TODO: - clone
         let ssh_config = auth_data::resolve::<SshConfig>(&config.host_reference, &config.host, paths.base_paths.as_ref())?;
 
+        let file_extension = if let Some(encryption) = config.encryption.as_ref() {
+            encryption.validate()?;
+            if !args.no_docker {
+                encryption.validate_docker()?;
+            }
+            format!(".{}", encryption.encryption_type.extension())
+        } else if config.encryption_key.is_some() {
+            String::from(".gpg")
+        } else {
+            return Err(format!("Either encryption_key or encryption is required for {}", SshGpg::MODULE_NAME));
+        };
+
+        if let Some(identity) = args.identity.as_ref() {
+            if !file::exists(identity) {
+                return Err(format!("The identity file '{}' does not exist", identity));
+            }
+        }
+
+        if config.parallel_uploads == 0 {
+            return Err(String::from("The number of parallel uploads has to be at least 1"));
+        }
+
         if args.is_restore && args.restore_to.is_some() {
-            return Err(format!("The restore-to option is currently not supported for {}", SshGpg::MODULE_NAME));
+            Self::check_restore_to(args)?;
         }
 
         let local_path = if let SourcePath::Single(path) = paths.source.borrow() {
@@ -63,15 +159,19 @@ impl Sync for SshGpg {
             config,
             ssh_config,
             image: String::from("vbackup-gpg"),
-            tmp_file: String::from("transmitting.gpg.tmp"),
             local_path,
-            file_extension: String::from(".gpg"),
+            file_extension,
             passphrase_file: format!("{}/passphrase.txt", paths.module_data_dir),
+            state_file: format!("{}/state.json", paths.module_data_dir),
             module_paths: paths,
             args: args.clone()
         }));
     }
 
+    fn check_restore_to(_args: &Arguments) -> Result<(), String> {
+        return Err(format!("The restore-to option is currently not supported for {}", SshGpg::MODULE_NAME));
+    }
+
     fn init(&mut self) -> Result<(), String> {
         // Build local docker image
         if !self.args.no_docker {
@@ -81,7 +181,9 @@ impl Sync for SshGpg {
 
         file::create_path_dir_if_missing(Path::new(&self.module_paths.module_data_dir), true)?;
 
-        file::write_if_change(&self.passphrase_file, Some("600"), &self.config.encryption_key, true)?;
+        if let Some(encryption_key) = self.config.encryption_key.as_ref() {
+            file::write_if_change(&self.passphrase_file, Some("600"), encryption_key, true)?;
+        }
 
         // prepare files for the SSH connection
         write_known_hosts(&self.ssh_config, &self.module_paths, self.args.dry_run)?;
@@ -95,105 +197,210 @@ impl Sync for SshGpg {
         return Ok(());
     }
 
-    fn sync(&self) -> Result<(), String> {
-        // delete missing local from remote and copy missing remote from local
+    fn sync(&self, _timing: &ExecutionTiming, bandwidth: Option<&BandwidthConfiguration>) -> Result<Option<RunStatistics>, String> {
+        // delete missing local from remote and copy missing remote from local, every file with its own command
         //  cat test.txt | gpg -c --passphrase-file /tmp/password.txt --batch | ssh user@server "cat > test.txt.gpg"
+        //  with public keys: cat test.txt | age --encrypt --recipient age1... | ssh user@server "cat > test.txt.age"
+        // files that changed since their upload are uploaded again
 
-        let (deleted_files, new_files) = self.find_actions()?;
+        let local_files = self.list_local()?;
+        let remote_files = self.get_remote_files()?;
+        let mut state = self.load_state()?;
+
+        let (deleted_files, mut new_files) = self.find_actions(&local_files, &remote_files);
+        let changed_files = self.find_changes(&local_files, &remote_files, &mut state)?;
+        new_files.extend(changed_files);
+        state.retain(|file, _| local_files.contains_key(file));
+
+        let mut statistics = RunStatistics {
+            files_transferred: Some(0),
+            bytes_sent: Some(0),
+            ..RunStatistics::default()
+        };
 
         if deleted_files.is_empty() && new_files.is_empty() {
             info!("Nothing to do");
-            return Ok(());
+            return self.save_state(&state).map(|_| Some(statistics));
         }
 
-        let mut cmd = self.get_base_cmd();
-        let mut cmd_has_first = false;
-
-        cmd.wrap();
+        let mut errors = vec![];
 
+        // a failed deletion does not block the uploads
         if !deleted_files.is_empty() {
-            let deleted_files_string: String = deleted_files
-                .into_iter()
-                .map(|file| {
-                    format!("'{}{}'", file, self.file_extension)
-                })
-                .collect::<Vec<String>>()
-                .join(" ");
-
-            debug!("Files <{}> on the remote server are going to be deleted", deleted_files_string);
+            let deleted_remote_files = deleted_files.into_iter()
+                .map(|(_, remote_file)| remote_file)
+                .collect::<Vec<String>>();
 
-            cmd.append_ssh_command(&self.ssh_config, &self.module_paths, !self.args.no_docker, cmd_has_first)?
-                .arg_string(
-                    format!("{}@{}", self.ssh_config.user, self.ssh_config.hostname)
-                )
-                .arg_string(
-                    format!("\"cd '{}' && rm {}\"", self.config.remote_path, deleted_files_string)
-                );
+            let mut cmd = self.get_base_cmd();
+            cmd.wrap();
+            self.append_remote_delete(&mut cmd, &deleted_remote_files, false)?;
 
-            cmd_has_first = true;
+            if cmd.wrap().run_with_args(self.args.as_ref()).is_err() {
+                errors.push(format!("deleting {} remote file(s) failed", deleted_remote_files.len()));
+            }
         }
 
+        // checksums for the state are taken before the upload, in case a file changes in the meantime
+        let mut checksums = HashMap::new();
         if !new_files.is_empty() {
             debug!("Files <{}> are going to be transferred to the remote server", new_files.join(" "));
 
-            for new_file in new_files.as_slice() {
-                if cmd_has_first {
-                    cmd.arg_str("&&");
-                }
+            if !self.args.dry_run {
+                checksums = self.get_local_checksums(&new_files)?;
+            }
+
+            // a file that can not be read fails only its own upload
+            if !self.args.dry_run && checksums.len() < new_files.len() {
+                let unreadable = new_files.iter()
+                    .filter(|file| !checksums.contains_key(*file))
+                    .map(|file| file.as_str())
+                    .collect::<Vec<&str>>();
+                errors.push(format!("{} file(s) could not be read ({})", unreadable.len(), unreadable.join(", ")));
+                new_files.retain(|file| checksums.contains_key(file));
+            }
+        }
+
+        if !new_files.is_empty() {
+            let uploads = new_files.iter()
+                .enumerate()
+                .map(|(index, file)| {
+                    let (cmd, pipeline) = self.get_upload_cmd(index, file.as_str(), remote_files.get(file));
+                    (file.clone(), cmd, pipeline)
+                })
+                .collect::<Vec<(String, CommandWrapper, UploadPipeline)>>();
+
+            let uploaded = self.run_uploads(uploads, bandwidth);
+
+            if uploaded.len() < new_files.len() {
+                let failed = new_files.iter()
+                    .filter(|file| !uploaded.contains(file))
+                    .map(|file| file.as_str())
+                    .collect::<Vec<&str>>();
+                errors.push(format!("{} of {} upload(s) failed ({})", failed.len(), new_files.len(), failed.join(", ")));
+            }
 
-                cmd.arg_string(
-                        format!("cat '{}/{}' |", self.local_path, new_file)
-                    )
-                    .arg_string(
-                        format!("gpg -c --passphrase-file '{}' --batch |", self.passphrase_file)
-                    )
-                    .append_ssh_command(&self.ssh_config, &self.module_paths, !self.args.no_docker, cmd_has_first)?
-                    .arg_string(
-                        format!("{}@{}", self.ssh_config.user, self.ssh_config.hostname)
-                    )
-                    .arg_string(
-                        format!("\"cat > '{0}/{1}' && mv '{0}/{1}' '{0}/{2}{3}'\"", self.config.remote_path, self.tmp_file, new_file, self.file_extension)
-                    );
-
-                if !cmd_has_first {
-                    cmd_has_first = true;
+            if self.args.dry_run {
+                return Ok(None);
+            }
+
+            // the size of the encrypted files is only known after the upload
+            let remote_files = self.get_remote_files()?;
+            statistics.files_transferred = Some(uploaded.len() as u64);
+            statistics.bytes_sent = Some(uploaded.iter().filter_map(|file| remote_files.get(file)).map(|remote| remote.size).sum());
+
+            for file in uploaded {
+                if let (Some(local), Some(remote), Some(checksum)) = (local_files.get(&file), remote_files.get(&file), checksums.get(&file)) {
+                    state.insert(file, UploadState {
+                        size: local.size,
+                        mtime: local.mtime,
+                        sha256: checksum.clone(),
+                        remote_file: remote.file.clone(),
+                        remote_size: remote.size
+                    });
+                } else {
+                    warn!("Could not store the state of the upload of '{}'", file);
                 }
             }
+        }
+
+        // successful uploads are kept in the state, such that only the failed files are uploaded in the next run
+        self.save_state(&state)?;
+
+        if errors.is_empty() {
+            info!("Sync statistics: {}", statistics);
+            return Ok(Some(statistics));
+        } else {
+            return Err(format!("Sync of '{}' was not complete: {}", self.name, errors.join(", ")));
+        }
+    }
+
+    fn verify(&self) -> Result<(), String> {
+        let local_files = self.list_local()?;
+        let remote_files = self.get_remote_files()?;
+        let (missing_local, missing_remote) = self.find_actions(&local_files, &remote_files);
 
-            // change file mode
-            if let Some(chmod) = self.config.remote_chmod.as_ref() {
-                let remote_files_string = new_files
-                    .into_iter()
-                    .map(|file| {
-                        format!("'{}{}'", file, self.file_extension)
-                    })
-                    .collect::<Vec<String>>()
-                    .join(" ");
-
-                cmd.arg_str("&&")
-                    .append_ssh_command(&self.ssh_config, &self.module_paths, !self.args.no_docker, cmd_has_first)?
-                    .arg_string(
-                        format!("{}@{}", self.ssh_config.user, self.ssh_config.hostname)
-                    )
-                    .arg_string(
-                        format!("\"cd '{}' && chmod {} {}\"", self.config.remote_path, chmod, remote_files_string)
-                    );
+        if !missing_remote.is_empty() || !missing_local.is_empty() {
+            return Err(format!("The file lists differ: {} file(s) missing on the remote, {} file(s) missing locally", missing_remote.len(), missing_local.len()));
+        }
+
+        let mut files: Vec<String> = local_files.keys().cloned().collect();
+        files.sort();
+        if files.is_empty() {
+            info!("Nothing to verify for {}", self.name);
+            return Ok(());
+        }
+
+        // the size of the encrypted files is known from the upload, files without state can not be checked this way
+        let state = self.load_state()?;
+        let size_differences: Vec<&String> = remote_files.iter()
+            .filter(|(file, remote)| state.get(*file).map_or(false, |upload| upload.remote_file == remote.file && upload.remote_size != remote.size))
+            .map(|(_, remote)| &remote.file)
+            .collect();
+
+        if !size_differences.is_empty() {
+            for difference in size_differences.iter() {
+                debug!("Remote size differs from the upload for '{}'", difference);
             }
+
+            return Err(format!("Remote sizes differ from the last upload for {} file(s)", size_differences.len()));
         }
 
-        return cmd.wrap().run_with_args(self.args.as_ref());
+        if self.config.verify_mode == VerifyMode::Size {
+            info!("Compared the file lists and the remote sizes of {} file(s)", files.len());
+            return Ok(());
+        }
+
+        // Files encrypted with public keys can only be decrypted with the private key
+        if self.config.encryption.is_some() && self.args.identity.is_none() {
+            info!("Only compared the file lists, checksums of files encrypted with public keys can only be compared with --identity");
+            return Ok(());
+        }
+
+        if self.args.dry_run {
+            dry_run!(format!("Comparing checksums of {} local and decrypted remote files", files.len()));
+            return Ok(());
+        }
+
+        info!("Comparing checksums of {} local and decrypted remote files...", files.len());
+        let remote_files: HashMap<String, String> = remote_files.into_iter()
+            .map(|(file, remote)| (file, remote.file))
+            .collect();
+        let local_checksums = self.get_local_checksums(&files)?;
+        let remote_checksums = self.get_remote_checksums(&files, &remote_files);
+
+        let differences: Vec<&String> = files.iter()
+            .filter(|file| local_checksums.get(*file).is_none() || local_checksums.get(*file) != remote_checksums.get(*file))
+            .collect();
+
+        if differences.is_empty() {
+            return Ok(());
+        }
+
+        for difference in differences.iter() {
+            debug!("Checksum differs for '{}'", difference);
+        }
+
+        return Err(format!("Checksums differ for {} file(s)", differences.len()));
     }
 
-    fn restore(&self) -> Result<(), String> {
+    fn restore(&self, request: &RestoreRequest) -> Result<(), String> {
 
         // TODO: untested
         warn!("ssh-gpg restore is untested!");
-        ask_user_abort(None)?;
+        require_latest(&request.archive, SshGpg::MODULE_NAME)?;
+        confirm_abort(request, None)?;
 
         // copy missing local from remote and keep everything else for now
-        //  ssh user@server "cat test.txt.gpg" | gpg -d --passphrase-file /tmp/password.txt --batch --output file.txt
+        //  ssh user@server "cat test.txt.gpg" | gpg --batch --passphrase-file /tmp/password.txt --decrypt > file.txt
 
-        let (missing_files, _) = self.find_actions()?;
+        let local_files = self.list_local()?;
+        let remote_files = self.get_remote_files()?;
+        let (mut missing_files, _) = self.find_actions(&local_files, &remote_files);
+
+        // files are stored flat on the remote, so a path selects a single file
+        if let Some(path) = request.path.as_ref() {
+            missing_files.retain(|(file, _)| file == path);
+        }
 
         if missing_files.is_empty() {
             info!("Nothing to restore for {}", self.name);
@@ -205,9 +412,9 @@ impl Sync for SshGpg {
 
         cmd.wrap();
 
-        debug!("Files <{}> are going to be restored from the remote server", missing_files.join(" "));
+        debug!("Files <{}> are going to be restored from the remote server", missing_files.iter().map(|(file, _)| file.as_str()).collect::<Vec<&str>>().join(" "));
 
-        for file in missing_files.as_slice() {
+        for (file, remote_file) in missing_files.iter() {
             if cmd_has_first {
                 cmd.arg_str("&&");
             }
@@ -217,10 +424,10 @@ impl Sync for SshGpg {
                     format!("{}@{}", self.ssh_config.user, self.ssh_config.hostname)
                 )
                 .arg_string(
-                    format!("cat '{}/{}{}'", self.config.remote_path, file, self.file_extension)
+                    format!("cat '{}/{}'", self.config.remote_path, remote_file)
                 )
                 .arg_string(
-                    format!("| gpg -d --passphrase-file '{}' --batch --output '{}/{}'", self.passphrase_file, self.local_path, file)
+                    format!("| {} > '{}/{}'", self.get_decrypt_command(remote_file)?, self.local_path, file)
                 );
 
             if !cmd_has_first {
@@ -235,7 +442,7 @@ impl Sync for SshGpg {
 
             let files_string = missing_files
                 .into_iter()
-                .map(|file| format!("'{}'", file))
+                .map(|(file, _)| format!("'{}'", file))
                 .collect::<Vec<String>>()
                 .join(" ");
 
@@ -256,33 +463,302 @@ impl Sync for SshGpg {
 }
 
 impl SshGpg {
-    fn find_actions(&self) -> Result<(/* missing local */ Vec<String>, /* missing remote */ Vec<String>), String> {
-        let local_files = self.list_local()?;
-        let remote_files = self.list_remote()?.into_iter()
-            .filter_map(|item|
-                item.strip_suffix(&self.file_extension).map(|str| String::from(str))
-            )
-            .collect::<Vec<String>>();
-
-        let local_filtered = local_files.iter().filter_map(|item| {
-            if remote_files.contains(item) {
+    fn find_actions(&self, local_files: &HashMap<String, FileInfo>, remote_files: &HashMap<String, RemoteFile>) -> (/* missing local (name, remote file) */ Vec<(String, String)>, /* missing remote */ Vec<String>) {
+        let mut local_filtered = local_files.keys().filter_map(|item| {
+            if remote_files.contains_key(item) {
                 None
             } else {
                 Some(String::from(item))
             }
         }).collect::<Vec<String>>();
-        let remote_filtered = remote_files.iter().filter_map(|item| {
-            if local_files.contains(item) {
+        let mut remote_filtered = remote_files.iter().filter_map(|(item, remote)| {
+            if local_files.contains_key(item) {
                 None
             } else {
-                Some(String::from(item))
+                Some((String::from(item), remote.file.clone()))
             }
-        }).collect::<Vec<String>>();
+        }).collect::<Vec<(String, String)>>();
+
+        local_filtered.sort();
+        remote_filtered.sort();
 
-        return Ok((remote_filtered, local_filtered));
+        return (remote_filtered, local_filtered);
     }
 
-    fn list_remote(&self) -> Result<Vec<String>, String> {
+    /*
+     * Find files that need to be uploaded again, because their content changed or the remote file does not match the last upload
+     *  Only files with another size or modification time are hashed, files uploaded before there was a state are taken over
+     */
+    fn find_changes(&self, local_files: &HashMap<String, FileInfo>, remote_files: &HashMap<String, RemoteFile>, state: &mut HashMap<String, UploadState>) -> Result<Vec<String>, String> {
+        let mut changed = vec![];
+        let mut unknown = vec![];
+
+        for (file, local) in local_files.iter() {
+            let remote = if let Some(remote) = remote_files.get(file) {
+                remote
+            } else {
+                continue;
+            };
+
+            match state.get(file) {
+                Some(upload) if upload.remote_file != remote.file || upload.remote_size != remote.size => {
+                    warn!("Remote file '{}' does not match the last upload, uploading it again", remote.file);
+                    changed.push(file.clone());
+                },
+                Some(upload) if upload.size != local.size => changed.push(file.clone()),
+                Some(upload) if upload.mtime != local.mtime => unknown.push(file.clone()),
+                Some(_) => {},
+                None => unknown.push(file.clone())
+            }
+        }
+
+        let checksums = if unknown.is_empty() {
+            HashMap::new()
+        } else {
+            self.get_local_checksums(&unknown)?
+        };
+
+        for file in unknown {
+            // the upload of a file that can not be read fails on its own
+            let checksum = if let Some(checksum) = checksums.get(&file) {
+                checksum
+            } else {
+                changed.push(file);
+                continue;
+            };
+            let local = &local_files[&file];
+
+            if let Some(upload) = state.get_mut(&file) {
+                if upload.sha256 == *checksum {
+                    trace!("Only the modification time of '{}' changed", file);
+                    upload.mtime = local.mtime;
+                } else {
+                    changed.push(file);
+                }
+            } else {
+                let remote = &remote_files[&file];
+                debug!("Taking over '{}', which was uploaded before keeping a state", remote.file);
+                state.insert(file, UploadState {
+                    size: local.size,
+                    mtime: local.mtime,
+                    sha256: checksum.clone(),
+                    remote_file: remote.file.clone(),
+                    remote_size: remote.size
+                });
+            }
+        }
+
+        if !changed.is_empty() {
+            info!("{} file(s) changed since their upload", changed.len());
+        }
+
+        changed.sort();
+        return Ok(changed);
+    }
+
+    /*
+     * Map the names of the local files to the encrypted remote files
+     *  The extension tells how a file is encrypted, files from before switching the encryption are kept as they are
+     */
+    fn get_remote_files(&self) -> Result<HashMap<String, RemoteFile>, String> {
+        return Ok(self.list_remote()?.into_iter()
+            .filter_map(|(item, info)| {
+                let encryption_type = EncryptionType::from_file_name(item.as_str())?;
+                let name = item.strip_suffix(format!(".{}", encryption_type.extension()).as_str())?;
+                Some((String::from(name), RemoteFile { file: item.clone(), size: info.size }))
+            })
+            .collect());
+    }
+
+    fn load_state(&self) -> Result<HashMap<String, UploadState>, String> {
+        return json::from_file_checked(Path::new(self.state_file.as_str()))
+            .map(|state| state.unwrap_or_default());
+    }
+
+    fn save_state(&self, state: &HashMap<String, UploadState>) -> Result<(), String> {
+        if self.args.dry_run {
+            dry_run!(format!("Writing the state of {} uploaded file(s) to '{}'", state.len(), self.state_file));
+            return Ok(());
+        }
+
+        return json::to_file(Path::new(self.state_file.as_str()), state);
+    }
+
+    /*
+     * Upload a single file to a temporary name first, it is only renamed once all steps succeeded
+     *  cat 'file' | gpg ... | ssh user@server "cat > '.file.gpg.transmitting'" && ssh user@server "mv '.file.gpg.transmitting' 'file.gpg'"
+     *  a remote file with another encryption (extension) is replaced, a bandwidth limit is applied with 'pv -L' before ssh
+     *  The command line of the pipeline is added to the command once the limit at the start of the upload is known
+     */
+    fn get_upload_cmd(&self, index: usize, file: &str, previous: Option<&RemoteFile>) -> (CommandWrapper, UploadPipeline) {
+        let remote_file = format!("{}{}", file, self.file_extension);
+        let tmp_file = format!(".{}.transmitting", remote_file);
+        let login = format!("{}@{}", self.ssh_config.user, self.ssh_config.hostname);
+
+        // every upload runs in its own container
+        let mut cmd = self.get_named_base_cmd(format!("ssh-encrypt-vbackup-tmp-{}", index).as_str());
+
+        let upload_ssh = cmd.build_ssh_command(&self.ssh_config, &self.module_paths, !self.args.no_docker, false);
+        let stages = vec![
+            format!("cat '{}/{}'", self.local_path, file),
+            self.get_encrypt_command()
+        ];
+        let send = format!("{} {} \"cat > '{}/{}'\"", upload_ssh, login, self.config.remote_path, tmp_file);
+
+        let mut finish = format!("cd '{}' && mv '{}' '{}'", self.config.remote_path, tmp_file, remote_file);
+        if let Some(chmod) = self.config.remote_chmod.as_ref() {
+            finish.push_str(format!(" && chmod {} '{}'", chmod, remote_file).as_str());
+        }
+        if let Some(previous) = previous.filter(|previous| previous.file != remote_file) {
+            finish.push_str(format!(" && rm '{}'", previous.file).as_str());
+        }
+
+        let finish_ssh = cmd.build_ssh_command(&self.ssh_config, &self.module_paths, !self.args.no_docker, true);
+        let finish = format!("{} {} \"{}\"", finish_ssh, login, finish);
+
+        return (cmd, UploadPipeline { stages, send, finish });
+    }
+
+    /*
+     * Run the uploads with the configured parallelism, a failed upload is retried on its own and does not block the others
+     *  Returns the files that were uploaded successfully
+     */
+    fn run_uploads(&self, uploads: Vec<(String, CommandWrapper, UploadPipeline)>, bandwidth: Option<&BandwidthConfiguration>) -> Vec<String> {
+        let total = uploads.len();
+        let parallel_uploads = self.config.parallel_uploads.min(total);
+        let queue = Mutex::new(uploads.into_iter().collect::<VecDeque<(String, CommandWrapper, UploadPipeline)>>());
+        let uploaded = Mutex::new(vec![]);
+        let done = AtomicUsize::new(0);
+
+        let args = self.args.as_ref();
+        let retries = self.config.upload_retries;
+
+        thread::scope(|scope| {
+            for _ in 0..parallel_uploads {
+                scope.spawn(|| {
+                    loop {
+                        let next = queue.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).pop_front();
+                        let (file, mut cmd, pipeline) = if let Some(upload) = next {
+                            upload
+                        } else {
+                            break;
+                        };
+
+                        // the limit is read for every file to follow the schedule, parallel uploads share the bandwidth
+                        let result = current_limit(bandwidth).and_then(|limit| {
+                            cmd.arg_string(pipeline.command(limit.map(|limit| (limit / parallel_uploads as u64).max(1))));
+                            upload_with_retries(&mut cmd, file.as_str(), retries, args)
+                        });
+                        let count = done.fetch_add(1, Ordering::SeqCst) + 1;
+
+                        if result.is_ok() {
+                            info!("Uploaded '{}' ({}/{})", file, count, total);
+                            uploaded.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(file);
+                        } else {
+                            error!("Upload of '{}' failed ({}/{})", file, count, total);
+                        }
+                    }
+                });
+            }
+        });
+
+        return uploaded.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner());
+    }
+
+    fn append_remote_delete(&self, cmd: &mut CommandWrapper, remote_files: &[String], has_previous: bool) -> Result<(), String> {
+        let remote_files_string: String = remote_files
+            .iter()
+            .map(|file| {
+                format!("'{}'", file)
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        debug!("Files <{}> on the remote server are going to be deleted", remote_files_string);
+
+        cmd.append_ssh_command(&self.ssh_config, &self.module_paths, !self.args.no_docker, has_previous)?
+            .arg_string(
+                format!("{}@{}", self.ssh_config.user, self.ssh_config.hostname)
+            )
+            .arg_string(
+                format!("\"cd '{}' && rm {}\"", self.config.remote_path, remote_files_string)
+            );
+
+        return Ok(());
+    }
+
+    fn get_encrypt_command(&self) -> String {
+        if let Some(encryption) = self.config.encryption.as_ref() {
+            return encryption.encrypt_command(&|path| encryption.key_file_path(path, !self.args.no_docker));
+        } else {
+            return format!("gpg -c --passphrase-file '{}' --batch", self.passphrase_file);
+        }
+    }
+
+    // gpg decrypts symmetrically encrypted files with the passphrase and others with the private key
+    fn get_decrypt_command(&self, remote_file: &str) -> Result<String, String> {
+        let encryption_type = try_option!(EncryptionType::from_file_name(remote_file), format!("Could not get the encryption of '{}'", remote_file));
+        let passphrase_file = self.config.encryption_key.as_ref().map(|_| self.passphrase_file.as_str());
+
+        let identity = encryption::identity_path(self.args.identity.as_ref(), !self.args.no_docker);
+
+        return encryption_type.decrypt_command(identity.as_deref(), passphrase_file);
+    }
+
+    /*
+     * Get the sha256 checksums of the local files, files that can not be read are left out
+     */
+    fn get_local_checksums(&self, files: &[String]) -> Result<HashMap<String, String>, String> {
+        let local_dir = docker::host_path(self.local_path.as_str(), self.args.no_docker)?;
+
+        return Ok(files.iter().filter_map(|file| {
+            match archive::get_checksum(format!("{}/{}", local_dir, file).as_str()) {
+                Ok(checksum) => Some((file.clone(), checksum)),
+                Err(err) => {
+                    error!("{}", err);
+                    None
+                }
+            }
+        }).collect());
+    }
+
+    /*
+     * Get the sha256 checksums of the decrypted remote files, every file with its own command
+     *  ssh user@server "cat 'file.gpg'" | gpg -d --batch | sha256sum
+     */
+    fn get_remote_checksums(&self, files: &[String], remote_files: &HashMap<String, String>) -> HashMap<String, String> {
+        return files.iter().filter_map(|file| {
+            match self.get_remote_checksum(file, remote_files) {
+                Ok(checksum) => Some((file.clone(), checksum)),
+                Err(err) => {
+                    error!("Could not get the checksum of the remote file of '{}': {}", file, err);
+                    None
+                }
+            }
+        }).collect();
+    }
+
+    fn get_remote_checksum(&self, file: &str, remote_files: &HashMap<String, String>) -> Result<String, String> {
+        let remote_file = try_option!(remote_files.get(file), format!("There is no remote file for '{}'", file));
+        let remote_command = format!("cat {}", command::quote(format!("{}/{}", self.config.remote_path, remote_file).as_str()));
+
+        let mut cmd = self.get_base_cmd();
+        cmd.wrap()
+            .append_ssh_command(&self.ssh_config, &self.module_paths, !self.args.no_docker, false)?
+            .arg_string(
+                format!("{}@{}", self.ssh_config.user, self.ssh_config.hostname)
+            )
+            .arg_string(command::quote(remote_command.as_str()))
+            .arg_string(
+                format!("| {} | sha256sum", self.get_decrypt_command(remote_file)?)
+            );
+
+        let output = cmd.wrap().run_get_output()?;
+        let checksum = try_option!(output.split_whitespace().next(), "The checksum command did not return a checksum");
+        return Ok(String::from(checksum));
+    }
+
+    fn list_remote(&self) -> Result<HashMap<String, FileInfo>, String> {
         let mut cmd = self.get_base_cmd();
 
         cmd.wrap()
@@ -293,36 +769,41 @@ impl SshGpg {
             .map_err(|_| String::from("Getting a list of remote files failed"))
     }
 
-    fn list_local(&self) -> Result<Vec<String>, String> {
+    fn list_local(&self) -> Result<HashMap<String, FileInfo>, String> {
         let cmd = self.get_base_cmd();
 
         self.list_helper(cmd, self.local_path.as_str(), true)
             .map_err(|_| String::from("Getting a list of local files failed"))
     }
 
-    fn list_helper(&self, mut base_cmd: CommandWrapper, path: &str, local: bool) -> Result<Vec<String>, String> {
+    fn list_helper(&self, mut base_cmd: CommandWrapper, path: &str, local: bool) -> Result<HashMap<String, FileInfo>, String> {
         // use some random string to indicate the start of command output, in case there is some banner
         let command_start = "===== THIS IS A SEPARATOR FOR THE ACTUAL COMMAND OUTPUT =====";
 
-        base_cmd.arg_string(format!("{2}echo {0} && mkdir -p '{1}' && ls '{1}'{2}", command_start, path, if local { "" } else { "\"" }));
-        if !local {
+        // list size, modification time and name of every file, the same as 'ls' would (no hidden files)
+        let list_command = format!("echo {0} && mkdir -p '{1}' && cd '{1}' && for file in *; do if [ -e \"$file\" ]; then stat -c '%s %Y %n' \"$file\"; fi; done", command_start, path);
+
+        if local {
+            base_cmd.arg_string(list_command);
+        } else {
             // the command needs to be wrapped in a single argument when running over SSH
+            base_cmd.arg_string(format!("\"{}\"", list_command.replace('"', "\\\"").replace('$', "\\$")));
             base_cmd.wrap();
         }
 
         if !local && self.args.dry_run {
             debug!("Retrieving a list of remote files is not possible during a dry-run, assuming an empty remote directory");
             dry_run!(base_cmd.to_string());
-            Ok(vec![])
+            Ok(HashMap::new())
         } else {
             if self.args.dry_run {
                 dry_run!(base_cmd.to_string());
             }
 
             let command_output = base_cmd.run_get_output()?;
-            let result: Option<Vec<String>> = command_output.lines().fold(None, |acc,line|
+            let result: Option<Vec<&str>> = command_output.lines().fold(None, |acc,line|
                 if let Some(mut values) = acc {
-                    values.push(String::from(line));
+                    values.push(line);
                     Some(values)
                 } else {
                     if line == command_start {
@@ -333,25 +814,58 @@ impl SshGpg {
                 }
             );
 
-            Ok(try_option!(result, "The listing of remote files did not return the expected separator"))
+            let mut files = HashMap::new();
+            for line in try_option!(result, "The listing of remote files did not return the expected separator") {
+                let mut parts = line.splitn(3, ' ');
+                let size = parts.next().and_then(|size| size.parse::<u64>().ok());
+                let mtime = parts.next().and_then(|mtime| mtime.parse::<i64>().ok());
+
+                if let (Some(size), Some(mtime), Some(name)) = (size, mtime, parts.next()) {
+                    files.insert(String::from(name), FileInfo { size, mtime });
+                } else {
+                    return Err(format!("Could not parse the file listing line '{}'", line));
+                }
+            }
+
+            Ok(files)
         }
     }
 
     fn get_base_cmd(&self) -> CommandWrapper {
+        return self.get_named_base_cmd("ssh-encrypt-vbackup-tmp");
+    }
+
+    fn get_named_base_cmd(&self, container_name: &str) -> CommandWrapper {
         if self.args.no_docker {
             CommandWrapper::new_with_args("sh", vec!["-c"])
         } else {
+            let mut options = vec![String::from("--env=SSHPASS")];
+            options.extend(encryption::key_volume_options(self.config.encryption.as_ref(), self.args.identity.as_ref()));
+
             CommandWrapper::new_docker(
-                "ssh-encrypt-vbackup-tmp",
+                container_name,
                 &self.image,
                 Some("sh"),
                 Some(vec!["-c"]),
                 &self.module_paths,
                 (&self.module_paths.source, &self.local_path.as_str()),
-                Some(vec![
-                    "--env=SSHPASS"
-                ])
+                Some(options.iter().map(|option| option.as_str()).collect())
             )
         }
     }
-}
\ No newline at end of file
+}
+
+fn upload_with_retries(cmd: &mut CommandWrapper, file: &str, retries: u64, args: &Arguments) -> Result<(), String> {
+    let mut attempt = 0;
+
+    loop {
+        let result = cmd.run_with_args(args);
+        if result.is_ok() || attempt >= retries || command::is_cancelled() {
+            return result;
+        }
+
+        attempt += 1;
+        warn!("Upload of '{}' failed, retrying ({}/{})", file, attempt, retries);
+        thread::sleep(Duration::from_secs(RETRY_DELAY * attempt));
+    }
+}
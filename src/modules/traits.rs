@@ -1,6 +1,10 @@
 use crate::util::objects::paths::{Paths, ModulePaths};
 use crate::util::objects::time::ExecutionTiming;
 use crate::util::objects::reporting::ReportEvent;
+use crate::util::objects::restore::{RestoreRequest, ArchiveEntry, FileEntry};
+use crate::util::objects::statistics::RunStatistics;
+use crate::util::objects::recovery::RecoveryInformation;
+use crate::util::objects::bandwidth::BandwidthConfiguration;
 use crate::Arguments;
 
 use serde_json::Value;
@@ -11,9 +15,33 @@ pub trait Backup {
     fn get_module_name(&self) -> &str { Self::MODULE_NAME }
 
     fn new(name: &str, config_json: &Value, paths: ModulePaths, args: &Rc<Arguments>) -> Result<Box<Self>, String>;
+    // Fails if the module can not restore into another directory (--restore-to) with these arguments
+    fn check_restore_to(_args: &Arguments) -> Result<(), String> {
+        Ok(())
+    }
     fn init(&mut self) -> Result<(), String>;
-    fn backup(&self, time_frames: &Vec<ExecutionTiming>) -> Result<(), String>;
-    fn restore(&self) -> Result<(), String>;
+    fn backup(&self, time_frames: &Vec<ExecutionTiming>) -> Result<Option<RunStatistics>, String>;
+    fn verify(&self) -> Result<(), String>;
+    fn restore(&self, request: &RestoreRequest) -> Result<(), String>;
+    fn list_archives(&self) -> Result<Vec<ArchiveEntry>, String> {
+        Err(format!("Listing archives is not supported by the {} backup module", Self::MODULE_NAME))
+    }
+    fn list_files(&self, _archive: &ArchiveEntry) -> Result<Vec<FileEntry>, String> {
+        Err(format!("Listing files is not supported by the {} backup module", Self::MODULE_NAME))
+    }
+    fn included_files(&self) -> Result<Option<Vec<String>>, String> {
+        Ok(None)
+    }
+    fn maintain(&self) -> Result<(), String> {
+        Err(format!("Maintenance is not supported by the {} backup module", Self::MODULE_NAME))
+    }
+    // Fails if the module has no maintenance, checked when loading the configuration
+    fn check_maintenance() -> Result<(), String> {
+        Err(format!("Maintenance is not supported by the {} backup module", Self::MODULE_NAME))
+    }
+    fn recovery_information(&self) -> Result<Option<RecoveryInformation>, String> {
+        Ok(None)
+    }
     fn clear(&mut self) -> Result<(), String>;
 }
 
@@ -49,9 +77,33 @@ pub trait Sync {
     fn get_module_name(&self) -> &str { Self::MODULE_NAME }
 
     fn new(name: &str, config_json: &Value, paths: ModulePaths, args: &Rc<Arguments>) -> Result<Box<Self>, String>;
+    // Fails if the module can not restore into another directory (--restore-to) with these arguments
+    fn check_restore_to(_args: &Arguments) -> Result<(), String> {
+        Ok(())
+    }
     fn init(&mut self) -> Result<(), String>;
-    fn sync(&self) -> Result<(), String>;
-    fn restore(&self) -> Result<(), String>;
+    fn sync(&self, timing: &ExecutionTiming, bandwidth: Option<&BandwidthConfiguration>) -> Result<Option<RunStatistics>, String>;
+    fn verify(&self) -> Result<(), String>;
+    fn restore(&self, request: &RestoreRequest) -> Result<(), String>;
+    fn list_archives(&self) -> Result<Vec<ArchiveEntry>, String> {
+        Err(format!("Listing archives is not supported by the {} sync module", Self::MODULE_NAME))
+    }
+    fn list_files(&self, _archive: &ArchiveEntry) -> Result<Vec<FileEntry>, String> {
+        Err(format!("Listing files is not supported by the {} sync module", Self::MODULE_NAME))
+    }
+    fn included_files(&self) -> Result<Option<Vec<String>>, String> {
+        Ok(None)
+    }
+    fn maintain(&self) -> Result<(), String> {
+        Err(format!("Maintenance is not supported by the {} sync module", Self::MODULE_NAME))
+    }
+    // Fails if the module has no maintenance, checked when loading the configuration
+    fn check_maintenance() -> Result<(), String> {
+        Err(format!("Maintenance is not supported by the {} sync module", Self::MODULE_NAME))
+    }
+    fn recovery_information(&self) -> Result<Option<RecoveryInformation>, String> {
+        Ok(None)
+    }
     fn clear(&mut self) -> Result<(), String>;
 }
 
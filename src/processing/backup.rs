@@ -3,7 +3,9 @@ use crate::util::helper::{check as check_helper};
 use crate::util::io::savefile::{time_format};
 use crate::util::objects::time::TimeEntry;
 use crate::util::objects::savedata::SaveData;
+use crate::util::objects::statistics::RunStatistics;
 use crate::processing::preprocessor::BackupUnit;
+use crate::util::logging;
 use crate::Arguments;
 
 use crate::{dry_run};
@@ -12,7 +14,7 @@ use chrono::{Duration};
 use std::ops::Add;
 use std::rc::Rc;
 
-pub fn backup(args: &Rc<Arguments>, unit: &mut BackupUnit, savedata: &mut SaveData) -> Result<bool,String> {
+pub fn backup(args: &Rc<Arguments>, unit: &mut BackupUnit, savedata: &mut SaveData) -> Result<(bool, Option<RunStatistics>),String> {
     // Get the backup module that should be used
     // TODO: clone
     let mut module = BackupModule::new(unit.backup_config.backup_type.as_str(), &unit.config.name, &unit.backup_config.config, unit.module_paths.clone(), args)?;
@@ -20,10 +22,11 @@ pub fn backup(args: &Rc<Arguments>, unit: &mut BackupUnit, savedata: &mut SaveDa
     // Is any backup required?
     if unit.timeframes.is_empty() {
         // No backup is required (for this configuration)
-        return Ok(false);
+        return Ok((false, None));
     }
 
     // For traceability in the log
+    logging::set_module(Some(module.get_module_name()));
     info!("Executing backup for '{}'", unit.config.name.as_str());
 
     // Set up backup module now
@@ -59,6 +62,13 @@ pub fn backup(args: &Rc<Arguments>, unit: &mut BackupUnit, savedata: &mut SaveDa
                 date: Some(time_format(&next_save))
             });
         }
+
+        // Statistics of an earlier backup do not belong to this one
+        if let Ok(Some(statistics)) = backup_result.as_ref() {
+            savedata.laststatistics.insert(String::from("backup"), statistics.clone());
+        } else {
+            savedata.laststatistics.remove("backup");
+        }
     } else {
         error!("Backup failed, cleaning up");
     }
@@ -85,5 +95,6 @@ pub fn backup(args: &Rc<Arguments>, unit: &mut BackupUnit, savedata: &mut SaveDa
         error!("Could not clear backup module: {}", err);
     }
 
-    return backup_result.map(|_| true);
+    // Return Ok(true) for backup was executed, together with its statistics, or Err(error) for failed backup
+    return backup_result.map(|statistics| (true, statistics));
 }
\ No newline at end of file
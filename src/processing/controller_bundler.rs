@@ -3,7 +3,7 @@ use crate::util::objects::reporting::{RunType,Status};
 use crate::modules::controller::{ControllerModule, BundleableWrapper, ControllerWrapper};
 use crate::modules::controller::bundle::ControllerBundle;
 use crate::modules::reporting::ReportingModule;
-use crate::processing::preprocessor::{ConfigurationUnit, SyncControllerBundle, SyncUnit};
+use crate::processing::preprocessor::{ConfigurationUnit, SyncControllerBundle, SyncUnit, VerifyUnit};
 use crate::Arguments;
 
 use crate::{try_option};
@@ -32,6 +32,19 @@ pub fn load_controllers(mut configurations: Vec<ConfigurationUnit>, args: &Rc<Ar
                     // There are no bundles at this point, so just keep it as is for now
                     done.push(ConfigurationUnit::SyncControllerBundle(sync))
                 },
+                ConfigurationUnit::Verify(verify) => {
+                    let name = verify.config.name.clone();
+                    let operation = verify.operation.clone();
+                    let result = load_verify_controller(verify, paths, args);
+
+                    match result {
+                        Ok(verify) => done.push(ConfigurationUnit::Verify(verify)),
+                        Err(err) => {
+                            error!("Could not load controller for '{}', skipping this {}: {}", &name, operation, err);
+                            report_error(reporter, operation, &name);
+                        }
+                    }
+                },
                 ConfigurationUnit::Sync(sync) => {
                     let name = sync.config.name.clone();
                     let result = handle_controller_bundle(sync, done.borrow_mut(), bundle_types.borrow_mut(), paths, args);
@@ -124,6 +137,19 @@ fn handle_controller_bundle(mut sync: SyncUnit, done: &mut Vec<ConfigurationUnit
     return Ok(());
 }
 
+// Verifications run separately from syncs, so their controllers are not bundled
+fn load_verify_controller(mut verify: VerifyUnit, paths: &Rc<Paths>, args: &Rc<Arguments>) -> Result<VerifyUnit,String> {
+    if let Some(controller_config) = verify.controller_config.as_ref() {
+        let controller_type_opt = try_option!(controller_config.get("type"), "Controller config contains no field 'type'");
+        let controller_type = try_option!(controller_type_opt.as_str(), "Could not get controller type as string");
+        let module_paths = ModulePaths::for_sync_module(paths, "controller", &verify.config);
+
+        verify.controller = Some(ControllerModule::new(controller_type, verify.config.name.as_str(), controller_config, module_paths, args)?);
+    }
+
+    return Ok(verify);
+}
+
 fn report_error(reporter: &mut ReportingModule, run_type: RunType, name: &String) {
     reporter.report_status(run_type, Some(name.clone()), Status::ERROR);
 }
\ No newline at end of file
@@ -3,6 +3,7 @@ pub mod preprocessor;
 pub mod scheduler;
 pub mod backup;
 pub mod sync;
+pub mod verify;
 
 mod timeframe_check;
 mod controller_bundler;
\ No newline at end of file
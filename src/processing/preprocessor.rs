@@ -4,7 +4,7 @@ use crate::modules::controller::ControllerModule;
 use crate::modules::reporting::ReportingModule;
 use crate::util::io::savefile::get_savedata;
 use crate::util::helper::{check as check_helper};
-use crate::util::objects::time::ExecutionTiming;
+use crate::util::objects::time::{ExecutionTiming, TimeFrameReference};
 use crate::util::objects::savedata::SaveDataCollection;
 use crate::util::objects::configuration::{Configuration, BackupConfiguration, SyncConfiguration};
 use crate::util::objects::paths::{ModulePaths, Paths};
@@ -12,6 +12,7 @@ use crate::util::objects::reporting::{RunType,Status};
 use crate::processing::{timeframe_check,controller_bundler};
 
 use std::rc::Rc;
+use serde_json::Value;
 use core::borrow::Borrow;
 use std::borrow::BorrowMut;
 use std::ops::Not;
@@ -19,7 +20,8 @@ use std::ops::Not;
 pub enum ConfigurationUnit {
     Backup(BackupUnit),
     Sync(SyncUnit),
-    SyncControllerBundle(SyncControllerBundle)
+    SyncControllerBundle(SyncControllerBundle),
+    Verify(VerifyUnit)
 }
 
 pub struct SyncControllerBundle {
@@ -29,7 +31,8 @@ pub struct SyncControllerBundle {
 
 enum ConfigurationUnitBuilder {
     Backup(BackupUnitBuilder),
-    Sync(SyncUnitBuilder)
+    Sync(SyncUnitBuilder),
+    Verify(VerifyUnitBuilder)
 }
 
 struct ConfigurationSplit {
@@ -77,6 +80,30 @@ struct SyncUnitBuilder {
     has_backup: bool
 }
 
+pub struct VerifyUnit {
+    pub config: Rc<Configuration>,
+    pub operation: RunType, // verify or maintenance, both are scheduled the same way
+    pub module: RunType, // the verified module, backup or sync
+    pub module_type: String,
+    pub module_config: Value,
+    pub controller_config: Option<Value>,
+    pub controller: Option<ControllerModule>,
+    pub module_paths: ModulePaths,
+    pub timeframe: Option<ExecutionTiming> // not set if the verification is not scheduled
+}
+
+struct VerifyUnitBuilder {
+    config: Rc<Configuration>,
+    operation: RunType,
+    module: RunType,
+    module_type: String,
+    module_config: Value,
+    controller_config: Option<Value>,
+    module_paths: ModulePaths,
+    timeframe_reference: Option<TimeFrameReference>,
+    timeframe: Option<ExecutionTiming>
+}
+
 pub struct PreprocessorResult {
     pub configurations: Vec<ConfigurationUnit>,
     pub savedata: SaveDataCollection
@@ -87,7 +114,8 @@ pub fn preprocess(configurations: Vec<Configuration>,
                   paths: &Rc<Paths>,
                   reporter: &mut ReportingModule,
                   do_backup: bool,
-                  do_sync: bool) -> Result<PreprocessorResult,String> {
+                  do_sync: bool,
+                  verify_only: bool) -> Result<PreprocessorResult,String> {
     if !do_backup && !do_sync {
         return Err(String::from("Preprocessor called for neither backup nor sync"));
     }
@@ -97,7 +125,7 @@ pub fn preprocess(configurations: Vec<Configuration>,
     let with_setup = load_default_setup_strategy(manual_filtered);
     let with_module_paths = load_module_paths(with_setup, paths);
     let savedata = load_savedata(&with_module_paths, reporter);
-    let split = flatten_processing_list(with_module_paths, do_backup, do_sync);
+    let split = flatten_processing_list(with_module_paths, do_backup, do_sync, verify_only);
     let with_time_constraints = filter_time_constraints(split, args, paths, &savedata, reporter)?;
     let with_checks = load_checks(with_time_constraints, args, paths, reporter);
     let with_additional_check = filter_additional_check(with_checks, args, reporter);
@@ -253,8 +281,10 @@ fn load_savedata(configurations: &Vec<ConfigurationSplit>, reporter: &mut Report
         .collect();
 }
 
-fn flatten_processing_list(mut configurations: Vec<ConfigurationSplit>, do_backup: bool, do_sync: bool) -> Vec<ConfigurationUnitBuilder> {
+fn flatten_processing_list(mut configurations: Vec<ConfigurationSplit>, do_backup: bool, do_sync: bool, verify_only: bool) -> Vec<ConfigurationUnitBuilder> {
     // step 4
+    //  verifications are added after the actual run if they are scheduled, or only them for the verify operation
+    //  maintenance is added after the run of its module, so it covers the archives pruned in this run
     let mut result = vec![];
     configurations
         .drain(..)
@@ -265,28 +295,102 @@ fn flatten_processing_list(mut configurations: Vec<ConfigurationSplit>, do_backu
             let has_backup = config.backup_config.is_some();
 
             if do_backup {
+                if let Some(backup_config) = config.backup_config.as_ref() {
+                    if verify_only || backup_config.verify.is_some() {
+                        result.push(ConfigurationUnitBuilder::Verify(VerifyUnitBuilder {
+                            config: config_rc.clone(),
+                            operation: RunType::VERIFY,
+                            module: RunType::BACKUP,
+                            module_type: backup_config.backup_type.clone(),
+                            module_config: backup_config.config.clone(),
+                            controller_config: None,
+                            module_paths: config.backup_paths.clone().unwrap(),
+                            timeframe_reference: if verify_only { None } else { backup_config.verify.clone() },
+                            timeframe: None
+                        }));
+                    }
+                }
+            }
+
+            if do_backup && !verify_only {
                 if let Some(backup_config) = config.backup_config.take() {
+                    let module_paths = config.backup_paths.unwrap();
+                    let maintenance = backup_config.maintenance.clone().map(|reference| {
+                        ConfigurationUnitBuilder::Verify(VerifyUnitBuilder {
+                            config: config_rc.clone(),
+                            operation: RunType::MAINTENANCE,
+                            module: RunType::BACKUP,
+                            module_type: backup_config.backup_type.clone(),
+                            module_config: backup_config.config.clone(),
+                            controller_config: None,
+                            module_paths: module_paths.clone(),
+                            timeframe_reference: Some(reference),
+                            timeframe: None
+                        })
+                    });
+
                     result.push(ConfigurationUnitBuilder::Backup(BackupUnitBuilder {
                         config: config_rc.clone(),
                         backup_config,
                         check: None,
-                        module_paths: config.backup_paths.unwrap(),
+                        module_paths,
                         timeframes: None,
                         has_sync
-                    }))
+                    }));
+
+                    if let Some(maintenance) = maintenance {
+                        result.push(maintenance);
+                    }
                 }
             }
 
             if do_sync {
+                if let Some(sync_config) = config.sync_config.as_ref() {
+                    if verify_only || sync_config.verify.is_some() {
+                        result.push(ConfigurationUnitBuilder::Verify(VerifyUnitBuilder {
+                            config: config_rc.clone(),
+                            operation: RunType::VERIFY,
+                            module: RunType::SYNC,
+                            module_type: sync_config.sync_type.clone(),
+                            module_config: sync_config.config.clone(),
+                            controller_config: sync_config.controller.clone(),
+                            module_paths: config.sync_paths.clone().unwrap(),
+                            timeframe_reference: if verify_only { None } else { sync_config.verify.clone() },
+                            timeframe: None
+                        }));
+                    }
+                }
+            }
+
+            if do_sync && !verify_only {
                 if let Some(sync_config) = config.sync_config.take() {
+                    let module_paths = config.sync_paths.unwrap();
+                    let maintenance = sync_config.maintenance.clone().map(|reference| {
+                        ConfigurationUnitBuilder::Verify(VerifyUnitBuilder {
+                            config: config_rc.clone(),
+                            operation: RunType::MAINTENANCE,
+                            module: RunType::SYNC,
+                            module_type: sync_config.sync_type.clone(),
+                            module_config: sync_config.config.clone(),
+                            controller_config: sync_config.controller.clone(),
+                            module_paths: module_paths.clone(),
+                            timeframe_reference: Some(reference),
+                            timeframe: None
+                        })
+                    });
+
                     result.push(ConfigurationUnitBuilder::Sync(SyncUnitBuilder {
                         config: config_rc.clone(),
                         sync_config,
                         check: None,
-                        module_paths: config.sync_paths.unwrap(),
+                        module_paths,
                         timeframes: None,
                         has_backup
-                    }))
+                    }));
+
+                    if let Some(maintenance) = maintenance {
+                        result.push(maintenance);
+                    }
                 }
             }
         });
@@ -315,7 +419,8 @@ fn filter_time_constraints(mut configurations: Vec<ConfigurationUnitBuilder>,
         .filter_map(|configuration| {
             let (name,run_type) = match &configuration {
                 ConfigurationUnitBuilder::Backup(backup) => (backup.config.name.borrow(),RunType::BACKUP),
-                ConfigurationUnitBuilder::Sync(sync) => (sync.config.name.borrow(),RunType::SYNC)
+                ConfigurationUnitBuilder::Sync(sync) => (sync.config.name.borrow(),RunType::SYNC),
+                ConfigurationUnitBuilder::Verify(verify) => (verify.config.name.borrow(),verify.operation.clone())
             };
 
             let savedata = if let Some(savedata) = savedata_collection.get(name) {
@@ -326,12 +431,24 @@ fn filter_time_constraints(mut configurations: Vec<ConfigurationUnitBuilder>,
                 return None;
             };
 
-            let timeframes = match &configuration {
+            let mut timeframes = match &configuration {
                 ConfigurationUnitBuilder::Backup(backup) => {
                     timeframe_checker.check_backup_timeframes(backup.config.name.as_str(), backup.backup_config.timeframes.clone(), savedata)
                 },
                 ConfigurationUnitBuilder::Sync(sync) => {
                     timeframe_checker.check_sync_timeframes(sync.config.name.as_str(), vec![sync.sync_config.interval.clone()], savedata)
+                },
+                ConfigurationUnitBuilder::Verify(verify) => {
+                    if let Some(reference) = verify.timeframe_reference.as_ref() {
+                        if let RunType::MAINTENANCE = verify.operation {
+                            timeframe_checker.check_maintenance_timeframe(verify.config.name.as_str(), verify.module.to_string().as_str(), reference.clone(), savedata)
+                        } else {
+                            timeframe_checker.check_verify_timeframe(verify.config.name.as_str(), verify.module.to_string().as_str(), reference.clone(), savedata)
+                        }
+                    } else {
+                        // Verification is not scheduled, but requested explicitly
+                        return Some(configuration);
+                    }
                 }
             };
 
@@ -350,6 +467,10 @@ fn filter_time_constraints(mut configurations: Vec<ConfigurationUnitBuilder>,
                 ConfigurationUnitBuilder::Sync(mut sync) => {
                     sync.timeframes = Some(timeframes);
                     ConfigurationUnitBuilder::Sync(sync)
+                },
+                ConfigurationUnitBuilder::Verify(mut verify) => {
+                    verify.timeframe = timeframes.pop();
+                    ConfigurationUnitBuilder::Verify(verify)
                 }
             };
 
@@ -404,6 +525,10 @@ fn load_checks(mut configurations: Vec<ConfigurationUnitBuilder>,
                             return None;
                         }
                     }
+                },
+                ConfigurationUnitBuilder::Verify(verify) => {
+                    // Additional checks only apply to backup and sync runs
+                    return Some(ConfigurationUnitBuilder::Verify(verify));
                 }
             }
         })
@@ -496,6 +621,9 @@ fn filter_additional_check(mut configurations: Vec<ConfigurationUnitBuilder>, ar
                     } else {
                         return Some(ConfigurationUnitBuilder::Sync(sync));
                     }
+                },
+                ConfigurationUnitBuilder::Verify(verify) => {
+                    return Some(ConfigurationUnitBuilder::Verify(verify));
                 }
             }
         })
@@ -547,6 +675,19 @@ fn assemble_from_builders(mut configurations: Vec<ConfigurationUnitBuilder>, rep
                         timeframe: timeframe_option.unwrap(),
                         has_backup: sync_builder.has_backup
                     }))
+                },
+                ConfigurationUnitBuilder::Verify(verify_builder) => {
+                    Some(ConfigurationUnit::Verify(VerifyUnit {
+                        config: verify_builder.config,
+                        operation: verify_builder.operation,
+                        module: verify_builder.module,
+                        module_type: verify_builder.module_type,
+                        module_config: verify_builder.module_config,
+                        controller_config: verify_builder.controller_config,
+                        controller: None,
+                        module_paths: verify_builder.module_paths,
+                        timeframe: verify_builder.timeframe
+                    }))
                 }
             }
         })
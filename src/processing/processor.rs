@@ -2,9 +2,11 @@ use crate::Arguments;
 use crate::modules::reporting::ReportingModule;
 use crate::util::objects::savedata::{SaveDataCollection};
 use crate::util::objects::reporting::{RunType,Status};
+use crate::util::objects::statistics::RunStatistics;
 use crate::processing::backup::backup;
 use crate::processing::sync::sync;
-use crate::processing::preprocessor::{ConfigurationUnit, SyncControllerBundle, SyncUnit, BackupUnit};
+use crate::processing::verify::verify;
+use crate::processing::preprocessor::{ConfigurationUnit, SyncControllerBundle, SyncUnit, BackupUnit, VerifyUnit};
 use crate::processing::timeframe_check;
 use crate::modules::controller::ControllerModule;
 
@@ -14,7 +16,8 @@ use core::borrow::{BorrowMut, Borrow};
 use std::rc::Rc;
 use chrono::{DateTime, Local};
 use crate::util::objects::configuration::StrategyConfiguration;
-use crate::util::command::CommandWrapper;
+use crate::util::command::{self, CommandWrapper};
+use crate::util::logging;
 
 pub fn process_configurations(args: &Rc<Arguments>,
                               reporter: &mut ReportingModule,
@@ -22,6 +25,12 @@ pub fn process_configurations(args: &Rc<Arguments>,
                               mut savedata_collection: SaveDataCollection) -> Result<(),String> {
     for configuration in configurations {
 
+        // Do not start any further configurations after the run was cancelled
+        if command::is_cancelled() {
+            warn!("Run was cancelled, skipping all remaining configurations");
+            return Err(String::from("Run was cancelled"));
+        }
+
         // TODO: Maybe execution time update should be improved
         let current_time : DateTime<Local> = chrono::Local::now();
 
@@ -41,6 +50,12 @@ pub fn process_configurations(args: &Rc<Arguments>,
                     sync.timeframe.execution_time = current_time.clone()
                 });
                 process_sync_controller_bundle(&mut sync_controller_bundle, &mut savedata_collection, args, reporter)
+            },
+            ConfigurationUnit::Verify(mut verify) => {
+                if let Some(timeframe) = verify.timeframe.as_mut() {
+                    timeframe.execution_time = current_time;
+                }
+                process_verify(&mut verify, &mut savedata_collection, args, reporter)
             }
         };
 
@@ -59,6 +74,8 @@ fn process_backup(config: &mut BackupUnit,
         .get_mut(config.config.name.as_str())
         .ok_or(format!("No savedata is present for '{}' backup", config.config.name.as_str()))?;
 
+    logging::set_context(Some(config.config.name.as_str()), Some("backup"));
+
     // Announce that this backup is starting
     reporter.report_status(RunType::BACKUP, Some(config.config.name.clone()), Status::START);
 
@@ -70,10 +87,13 @@ fn process_backup(config: &mut BackupUnit,
     let result = setup_result.and_then(|()| {
         backup(args, config, savedata)
     });
-    result_reporter(RunType::BACKUP, result, config.config.name.borrow(), reporter);
+    let statistics = result.as_ref().ok().and_then(|(_, statistics)| statistics.clone());
+    result_reporter(RunType::BACKUP, result.map(|(executed, _)| executed), config.config.name.borrow(), reporter, statistics);
 
     // run after
-    try_result!(run_after(config.backup_config.setup.as_ref(), args.dry_run, args.debug || args.verbose), "Script after backup failed");
+    let after_result = run_after(config.backup_config.setup.as_ref(), args.dry_run, args.debug || args.verbose);
+    logging::clear_context();
+    try_result!(after_result, "Script after backup failed");
 
     return Ok(());
 }
@@ -87,10 +107,13 @@ fn process_sync(config: &mut SyncUnit,
         .get_mut(config.config.name.as_str())
         .ok_or(format!("No savedata is present for '{}' backup", config.config.name.as_str()))?;
 
+    logging::set_context(Some(config.config.name.as_str()), Some("sync"));
+
     if !args.force {
         if !timeframe_check::check_sync_after_backup(&config.timeframe, savedata, config.has_backup) {
             info!("Sync for '{}' is not executed as there is no new backup since the last sync", config.config.name.as_str());
             reporter.report_status(RunType::SYNC, Some(config.config.name.clone()), Status::SKIP);
+            logging::clear_context();
             return Ok(());
         } else {
             debug!("Sync for '{}' is executed as there was a recent backup", config.config.name.as_str());
@@ -109,11 +132,35 @@ fn process_sync(config: &mut SyncUnit,
     let result = setup_result.and_then(|()| {
         sync(args, config, savedata, controller_override)
     });
-    result_reporter(RunType::SYNC, result, config.config.name.borrow(), reporter);
+    let statistics = result.as_ref().ok().and_then(|(_, statistics)| statistics.clone());
+    result_reporter(RunType::SYNC, result.map(|(executed, _)| executed), config.config.name.borrow(), reporter, statistics);
 
     // run after
-    try_result!(run_after(config.sync_config.setup.as_ref(), args.dry_run, args.debug || args.verbose), "Script after sync failed");
+    let after_result = run_after(config.sync_config.setup.as_ref(), args.dry_run, args.debug || args.verbose);
+    logging::clear_context();
+    try_result!(after_result, "Script after sync failed");
+
+    return Ok(());
+}
+
+fn process_verify(config: &mut VerifyUnit,
+                  savedata_collection: &mut SaveDataCollection,
+                  args: &Rc<Arguments>,
+                  reporter: &mut ReportingModule) -> Result<(), String> {
+    let savedata = savedata_collection
+        .get_mut(config.config.name.as_str())
+        .ok_or(format!("No savedata is present for '{}' {}", config.config.name.as_str(), config.operation))?;
 
+    logging::set_context(Some(config.config.name.as_str()), Some(config.operation.to_string().as_str()));
+
+    // Announce that this verification or maintenance is starting
+    reporter.report_status(config.operation.clone(), Some(config.config.name.clone()), Status::START);
+
+    // The source data is not touched, so there is no need for the setup scripts
+    let result = verify(args, config, savedata);
+    result_reporter(config.operation.clone(), result, config.config.name.borrow(), reporter, None);
+
+    logging::clear_context();
     return Ok(());
 }
 
@@ -196,11 +243,17 @@ fn run_after(setup_opt: Option<&StrategyConfiguration>, dry_run: bool, print: bo
 fn result_reporter(run_type: RunType,
                    result: Result<bool,String>,
                    config_name: &String,
-                   reporter: &mut ReportingModule) {
+                   reporter: &mut ReportingModule,
+                   statistics: Option<RunStatistics>) {
     match result {
         Ok(true) => {
-            info!("{} for '{}' was successfully executed", run_type, config_name);
-            reporter.report_status(run_type, Some(config_name.clone()), Status::DONE);
+            let status = Status::from_outcome(statistics.as_ref());
+            if let Status::PARTIAL = status {
+                warn!("{} for '{}' was only partially executed", run_type, config_name);
+            } else {
+                info!("{} for '{}' was successfully executed", run_type, config_name);
+            }
+            reporter.report_status_with_statistics(run_type, Some(config_name.clone()), status, statistics);
         },
         Ok(false) => {
             info!("{} for '{}' was not executed", run_type, config_name);
@@ -4,6 +4,7 @@ pub fn get_exec_order(config_list: Vec<ConfigurationUnit>) -> Result<Vec<Configu
     let mut sync_bundles = vec![];
     let mut backup_list = vec![];
     let mut sync_list = vec![];
+    let mut verify_list = vec![];
 
     for configuration in config_list {
         match configuration {
@@ -15,6 +16,9 @@ pub fn get_exec_order(config_list: Vec<ConfigurationUnit>) -> Result<Vec<Configu
             },
             ConfigurationUnit::SyncControllerBundle(sync) => {
                 sync_bundles.push(ConfigurationUnit::SyncControllerBundle(sync));
+            },
+            ConfigurationUnit::Verify(verify) => {
+                verify_list.push(ConfigurationUnit::Verify(verify));
             }
         }
     }
@@ -30,5 +34,8 @@ pub fn get_exec_order(config_list: Vec<ConfigurationUnit>) -> Result<Vec<Configu
     // Then syncs
     configuration_list.append( sync_list.as_mut());
 
+    // Verify last, such that the results of this run are included
+    configuration_list.append(verify_list.as_mut());
+
     return Ok(configuration_list);
 }
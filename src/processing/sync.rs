@@ -5,16 +5,20 @@ use crate::util::helper::{controller as controller_helper,check as check_helper}
 use crate::util::io::savefile::{time_format};
 use crate::util::objects::time::TimeEntry;
 use crate::util::objects::savedata::SaveData;
+use crate::util::objects::statistics::{RunStatistics, RunOutcome};
 use crate::processing::preprocessor::SyncUnit;
+use crate::util::logging;
+use crate::util::objects::bandwidth::{current_limit, to_kibibytes};
 use crate::Arguments;
 
 use crate::{dry_run};
 
-pub fn sync(args: &Rc<Arguments>, unit: &mut SyncUnit, savedata: &mut SaveData, controller_override: Option<&mut ControllerModule>) -> Result<bool,String> {
+pub fn sync(args: &Rc<Arguments>, unit: &mut SyncUnit, savedata: &mut SaveData, controller_override: Option<&mut ControllerModule>) -> Result<(bool, Option<RunStatistics>),String> {
     // Get the sync module that should be used
     let mut controller_module = controller_override.or(unit.controller.as_mut());
     let mut module = SyncModule::new(unit.sync_config.sync_type.as_str(), &unit.config.name, &unit.sync_config.config, unit.module_paths.clone(), args)?;
 
+    logging::set_module(Some(module.get_module_name()));
     trace!("Initializing sync for '{}'", unit.config.name.as_str());
 
     // init controller module
@@ -36,16 +40,26 @@ pub fn sync(args: &Rc<Arguments>, unit: &mut SyncUnit, savedata: &mut SaveData,
         } else {
             // Device did not start before timeout or is not available
             warn!("Remote device is not available, aborting sync");
-            return Ok(false);
+            return Ok((false, None));
         }
     }
 
     // Run sync
     info!("Executing sync for '{}'", unit.config.name.as_str());
-    let sync_result = module.sync();
+    // Modules running a single command keep the limit at the start, ssh-gpg reads it again for every file
+    if let Some(limit) = current_limit(unit.sync_config.bandwidth.as_ref())? {
+        info!("Limiting the bandwidth of the sync to {} KiB/s", to_kibibytes(limit));
+    }
+    let sync_result = module.sync(&unit.timeframe, unit.sync_config.bandwidth.as_ref());
+
+    // A partial sync is not complete yet, it is continued and recorded with the next run
+    let completed = match sync_result.as_ref() {
+        Ok(statistics) => statistics.as_ref().and_then(|statistics| statistics.outcome) != Some(RunOutcome::Partial),
+        Err(_) => false
+    };
 
     // Check result of sync and act accordingly
-    if sync_result.is_ok() {
+    if completed {
         trace!("Sync module is done");
 
         // Update internal state of check
@@ -59,6 +73,15 @@ pub fn sync(args: &Rc<Arguments>, unit: &mut SyncUnit, savedata: &mut SaveData,
             timestamp: unit.timeframe.execution_time.timestamp(),
             date: Some(time_format(&unit.timeframe.execution_time))
         });
+
+        // Statistics of an earlier sync do not belong to this one
+        if let Ok(Some(statistics)) = sync_result.as_ref() {
+            savedata.laststatistics.insert(String::from("sync"), statistics.clone());
+        } else {
+            savedata.laststatistics.remove("sync");
+        }
+    } else if sync_result.is_ok() {
+        debug!("Sync was only partially completed, not updating the savedata");
     } else {
         trace!("Sync failed, cleaning up");
     }
@@ -69,7 +92,7 @@ pub fn sync(args: &Rc<Arguments>, unit: &mut SyncUnit, savedata: &mut SaveData,
     }
 
     // Write savedata update only if sync was successful
-    if sync_result.is_ok() {
+    if completed {
         if !args.dry_run {
             savedata.create_directory_if_missing()?;
             if let Err(err) = savedata.write() {
@@ -95,6 +118,6 @@ pub fn sync(args: &Rc<Arguments>, unit: &mut SyncUnit, savedata: &mut SaveData,
         error!("Could no clear sync module: {}", err);
     }
 
-    // Return Ok(true) for sync was executed or Err(error) for failed sync
-    return sync_result.map(|_| true);
+    // Return Ok(true) for sync was executed, together with its statistics, or Err(error) for failed sync
+    return sync_result.map(|statistics| (true, statistics));
 }
\ No newline at end of file
@@ -17,7 +17,7 @@ pub struct TimeframeChecker {
 }
 
 #[derive(PartialEq, Eq)]
-enum Type { Backup, Sync }
+enum Type<'a> { Backup, Sync, Verify(&'a str), Maintenance(&'a str) }
 
 pub fn check_sync_after_backup(timing: &ExecutionTiming, savedata: &SaveData, has_backup: bool) -> bool {
     if has_backup {
@@ -62,6 +62,24 @@ impl TimeframeChecker {
         return self.check_timeframes(Type::Sync, config_name, configured_timeframes, savedata);
     }
 
+    // the last verification is stored per module (backup or sync) instead of per timeframe
+    pub fn check_verify_timeframe(&self,
+                                  config_name: &str,
+                                  module: &str,
+                                  configured_timeframe: TimeFrameReference,
+                                  savedata: &SaveData) -> Vec<ExecutionTiming> {
+        return self.check_timeframes(Type::Verify(module), config_name, vec![configured_timeframe], savedata);
+    }
+
+    // the same goes for the last maintenance, which is tracked separately from the saves
+    pub fn check_maintenance_timeframe(&self,
+                                       config_name: &str,
+                                       module: &str,
+                                       configured_timeframe: TimeFrameReference,
+                                       savedata: &SaveData) -> Vec<ExecutionTiming> {
+        return self.check_timeframes(Type::Maintenance(module), config_name, vec![configured_timeframe], savedata);
+    }
+
     fn check_timeframes(&self,
                             run_type: Type,
                             config_name: &str,
@@ -69,7 +87,9 @@ impl TimeframeChecker {
                             savedata: &SaveData) -> Vec<ExecutionTiming> {
         let run_type_str = match run_type {
             Type::Backup => "backup",
-            Type::Sync => "sync"
+            Type::Sync => "sync",
+            Type::Verify(_) => "verify",
+            Type::Maintenance(_) => "maintenance"
         };
 
         // Prepare current timestamp (for consistency) and queue of timeframes for backup
@@ -97,7 +117,9 @@ impl TimeframeChecker {
             // Get last backup (option as there might not be a last one)
             let last_option = match run_type {
                 Type::Backup => savedata.lastsave.get(&timeframe.identifier),
-                Type::Sync => savedata.lastsync.get(&timeframe.identifier)
+                Type::Sync => savedata.lastsync.get(&timeframe.identifier),
+                Type::Verify(module) => savedata.lastverify.get(module).map(|entry| &entry.time),
+                Type::Maintenance(module) => savedata.lastmaintenance.get(module).map(|entry| &entry.time)
             };
 
             // Only actually do check if the run is not forced
@@ -0,0 +1,117 @@
+use std::rc::Rc;
+use crate::modules::backup::{BackupModule, BackupWrapper};
+use crate::modules::sync::{SyncModule, SyncWrapper};
+use crate::util::helper::{controller as controller_helper};
+use crate::util::io::savefile::{time_format};
+use crate::util::objects::time::TimeEntry;
+use crate::util::objects::savedata::{SaveData, VerifyEntry};
+use crate::util::objects::reporting::RunType;
+use crate::processing::preprocessor::VerifyUnit;
+use crate::util::logging;
+use crate::Arguments;
+
+use crate::{dry_run};
+
+/*
+ * Run the verification or maintenance of a module, both are scheduled on their own and store their last result in the savedata
+ */
+pub fn verify(args: &Rc<Arguments>, unit: &mut VerifyUnit, savedata: &mut SaveData) -> Result<bool,String> {
+    info!("Executing {} of the {} for '{}'", unit.operation, unit.module, unit.config.name.as_str());
+
+    let verify_result = match unit.module {
+        RunType::BACKUP => verify_backup(args, unit),
+        RunType::SYNC => verify_sync(args, unit),
+        _ => Err(format!("Can not run {} for a {} run", unit.operation, unit.module))
+    };
+
+    // The remote device was not available, so there is no result
+    if let Ok(false) = verify_result {
+        return Ok(false);
+    }
+
+    let execution_time = unit.timeframe.as_ref().map(|timing| timing.execution_time).unwrap_or(chrono::Local::now());
+
+    let last_results = if let RunType::MAINTENANCE = unit.operation {
+        &mut savedata.lastmaintenance
+    } else {
+        &mut savedata.lastverify
+    };
+
+    // Store failed verifications as well, the result is the interesting part
+    last_results.insert(unit.module.to_string(), VerifyEntry {
+        time: TimeEntry {
+            timestamp: execution_time.timestamp(),
+            date: Some(time_format(&execution_time))
+        },
+        success: verify_result.is_ok(),
+        error: verify_result.as_ref().err().cloned()
+    });
+
+    if !args.dry_run {
+        savedata.create_directory_if_missing()?;
+        if let Err(err) = savedata.write() {
+            error!("Could not update savedata for '{}' {} ({})", unit.config.name.as_str(), unit.operation, err);
+        }
+    } else {
+        dry_run!(format!("Updating savedata: {}", savedata.path.as_str()));
+    }
+
+    return verify_result;
+}
+
+fn verify_backup(args: &Rc<Arguments>, unit: &mut VerifyUnit) -> Result<bool,String> {
+    let mut module = BackupModule::new(unit.module_type.as_str(), &unit.config.name, &unit.module_config, unit.module_paths.clone(), args)?;
+
+    logging::set_module(Some(module.get_module_name()));
+    module.init()?;
+
+    let verify_result = run_operation(&unit.operation, || module.verify(), || module.maintain());
+
+    // Free backup module now
+    if let Err(err) = module.clear() {
+        error!("Could not clear backup module: {}", err);
+    }
+
+    return verify_result.map(|_| true);
+}
+
+fn verify_sync(args: &Rc<Arguments>, unit: &mut VerifyUnit) -> Result<bool,String> {
+    let mut controller_module = unit.controller.as_mut();
+    let mut module = SyncModule::new(unit.module_type.as_str(), &unit.config.name, &unit.module_config, unit.module_paths.clone(), args)?;
+
+    logging::set_module(Some(module.get_module_name()));
+
+    controller_helper::init(&mut controller_module)?;
+    module.init()?;
+
+    // The remote copy can only be verified if the remote device is available
+    let verify_result = if controller_helper::start(&mut controller_module)? {
+        run_operation(&unit.operation, || module.verify(), || module.maintain()).map(|_| true)
+    } else {
+        warn!("Remote device is not available, aborting {}", unit.operation);
+        Ok(false)
+    };
+
+    // Run controller end (result is irrelevant here)
+    if let Err(err) = controller_helper::end(&mut controller_module) {
+        error!("Stopping the remote device after use failed: {}", err);
+    }
+
+    if let Err(err) = controller_helper::clear(&mut controller_module) {
+        error!("Could not clear the controller module: {}", err);
+    }
+
+    // Free sync module
+    if let Err(err) = module.clear() {
+        error!("Could no clear sync module: {}", err);
+    }
+
+    return verify_result;
+}
+
+fn run_operation(operation: &RunType, verify: impl FnOnce() -> Result<(), String>, maintain: impl FnOnce() -> Result<(), String>) -> Result<(), String> {
+    return match operation {
+        RunType::MAINTENANCE => maintain(),
+        _ => verify()
+    };
+}
@@ -6,9 +6,10 @@ use crate::modules::controller::bundle::BundleableControllerWrapper;
 use crate::modules::controller::ControllerModule;
 use crate::modules::sync::{SyncModule, SyncWrapper};
 use crate::util::io::json;
-use crate::util::io::user::ask_user_boolean;
-use crate::util::objects::configuration::Configuration;
+use crate::util::io::user::confirm;
+use crate::util::objects::configuration::{BackupConfiguration, Configuration, SyncConfiguration};
 use crate::util::objects::paths::{ModulePaths, Paths};
+use crate::util::objects::restore::RestoreRequest;
 
 pub fn main(args: Rc<Arguments>, paths: Rc<Paths>) -> Result<(),String> {
     let (name, file_path) = if let Some(name) = args.name.as_ref() {
@@ -17,6 +18,11 @@ pub fn main(args: Rc<Arguments>, paths: Rc<Paths>) -> Result<(),String> {
         return Err(String::from("Please set an volume to restore, batch restore is not supported"));
     };
 
+    let (restore_backup, restore_sync) = parse_only(args.as_ref())?;
+
+    // Check the archive selection and path before anything is done
+    let request = RestoreRequest::from_args(args.as_ref())?;
+
     info!("Running restore for '{}'", name);
 
     let config = json::from_file::<Configuration>(Path::new(&file_path))?;
@@ -25,90 +31,25 @@ pub fn main(args: Rc<Arguments>, paths: Rc<Paths>) -> Result<(),String> {
         warn!("Configuration is disabled...")
     }
 
-    if let Some(sync_config) = config.sync.as_ref() {
+    if !restore_sync {
+        debug!("Skipping sync restore due to --only");
+    } else if let Some(sync_config) = config.sync.as_ref() {
         debug!("Checking sync configuration...");
 
         let confirmation = if config.disabled {
             warn!("Configuration is disabled...");
-            ask_user_boolean("Run sync restore anyway?", true)?
+            confirm(args.assume_yes, "Run sync restore anyway?", true)?
         } else if sync_config.disabled {
             warn!("Sync configuration is disabled...");
-            ask_user_boolean("Run sync restore anyway?", true)?
+            confirm(args.assume_yes, "Run sync restore anyway?", true)?
         } else {
-            ask_user_boolean("Run sync restore?", true)?
+            confirm(args.assume_yes, "Run sync restore?", true)?
         };
 
         if !confirmation {
             info!("Not running sync restore.");
         } else {
-            debug!("Setting up sync module...");
-
-            let module_paths = ModulePaths::for_sync_module(&paths, "sync", &config);
-            let mut module = SyncModule::new(sync_config.sync_type.as_str(), name, &sync_config.config, module_paths, &args)?;
-            module.init()?;
-
-            debug!("Setting up controller...");
-
-            // check for controller
-            let controller = if let Some(controller_config) = sync_config.controller.as_ref() {
-                let controller_type_opt = try_option!(controller_config.get("type"), "Controller config contains no field 'type'");
-                let controller_type = try_option!(controller_type_opt.as_str(), "Could not get controller type as string");
-                let module_paths = ModulePaths::for_sync_module(&paths, "controller", &config);
-
-                let mut controller = ControllerModule::new(controller_type, config.name.as_str(), &controller_config, module_paths, &args)?;
-
-                debug!("Starting controller init...");
-                controller.as_mut_controller().init()?;
-                info!("Starting controller...");
-                let started = controller.as_mut_controller().begin()?;
-
-                if started {
-                    info!("Remote device is online.")
-                } else {
-                    let err = "Remote device is not online and/or can not be started.";
-                    error!("{}", err);
-                    return Err(String::from(err));
-                }
-
-                Some(controller)
-            } else {
-                debug!("No controller found.... Skipping.");
-                None
-            };
-
-            info!("Starting sync restore...");
-
-            let restore_result = module.restore();
-            log_error!(&restore_result);
-
-            let clear_result = module.clear();
-            log_error!(&clear_result);
-
-            // controller should be terminated regardless of sync restore result
-            let result = if let Some(mut controller) = controller {
-                debug!("Running controller end procedure...");
-                let end_result = controller.as_mut_controller().end();
-                log_error!(&end_result);
-
-                if let Ok(ended) = end_result.as_ref() {
-                    if !ended {
-                        // anything we can do here?
-                        warn!("Controller did not end properly...");
-                    }
-                }
-
-                debug!("Running controller clear procedure...");
-                let clear_result = controller.as_mut_controller().clear();
-                log_error!(&clear_result);
-
-                restore_result.and(end_result).and(clear_result)
-            } else {
-                restore_result
-            };
-
-            if result.is_err() {
-                return result;
-            }
+            run_sync_restore(&args, &paths, &config, sync_config, &request)?;
 
             info!("Sync restore successful.")
         }
@@ -116,33 +57,25 @@ pub fn main(args: Rc<Arguments>, paths: Rc<Paths>) -> Result<(),String> {
         info!("No sync configuration found...");
     };
 
-    if let Some(backup_config) = config.backup.as_ref() {
+    if !restore_backup {
+        debug!("Skipping backup restore due to --only");
+    } else if let Some(backup_config) = config.backup.as_ref() {
         debug!("Checking backup configuration");
 
         let confirmation = if config.disabled {
             warn!("Configuration is disabled...");
-            ask_user_boolean("Run backup restore anyway?", true)?
+            confirm(args.assume_yes, "Run backup restore anyway?", true)?
         } else if backup_config.disabled {
             warn!("Backup configuration is disabled...");
-            ask_user_boolean("Run backup restore anyway?", true)?
+            confirm(args.assume_yes, "Run backup restore anyway?", true)?
         } else {
-            ask_user_boolean("Run backup restore?", true)?
+            confirm(args.assume_yes, "Run backup restore?", true)?
         };
 
         if !confirmation {
             info!("Not running backup restore.");
         } else {
-            let module_paths = ModulePaths::for_backup_module(&paths, "backup", &config);
-            let mut module = BackupModule::new(backup_config.backup_type.as_str(), name, &backup_config.config, module_paths, &args)?;
-            module.init()?;
-
-            info!("Starting backup restore...");
-
-            let restore_result = module.restore();
-            let clear_result = module.clear();
-
-            log_error!(clear_result);
-            try_result!(restore_result, "Backup restore failed...");
+            try_result!(run_backup_restore(&args, &paths, &config, backup_config, &request), "Backup restore failed...");
 
             info!("Backup restore successful");
         }
@@ -151,4 +84,119 @@ pub fn main(args: Rc<Arguments>, paths: Rc<Paths>) -> Result<(),String> {
     };
 
     return Ok(());
-}
\ No newline at end of file
+}
+
+/**
+ * Restore the sync of a configuration without asking, including the handling of the controller
+ */
+pub fn run_sync_restore(args: &Rc<Arguments>, paths: &Rc<Paths>, config: &Configuration, sync_config: &SyncConfiguration, request: &RestoreRequest) -> Result<(),String> {
+    return with_sync_module(args, paths, config, sync_config, &|module| {
+        info!("Starting sync restore...");
+        module.restore(request)
+    });
+}
+
+/**
+ * Run an action with the sync module of a configuration, while the controller keeps the remote device online
+ */
+pub fn with_sync_module<T>(args: &Rc<Arguments>, paths: &Rc<Paths>, config: &Configuration, sync_config: &SyncConfiguration, action: &dyn Fn(&SyncModule) -> Result<T,String>) -> Result<T,String> {
+    debug!("Setting up sync module...");
+
+    let module_paths = ModulePaths::for_sync_module(paths, "sync", config);
+    let mut module = SyncModule::new(sync_config.sync_type.as_str(), config.name.as_str(), &sync_config.config, module_paths, args)?;
+    module.init()?;
+
+    debug!("Setting up controller...");
+
+    // check for controller
+    let controller = if let Some(controller_config) = sync_config.controller.as_ref() {
+        let controller_type_opt = try_option!(controller_config.get("type"), "Controller config contains no field 'type'");
+        let controller_type = try_option!(controller_type_opt.as_str(), "Could not get controller type as string");
+        let module_paths = ModulePaths::for_sync_module(paths, "controller", config);
+
+        let mut controller = ControllerModule::new(controller_type, config.name.as_str(), &controller_config, module_paths, args)?;
+
+        debug!("Starting controller init...");
+        controller.as_mut_controller().init()?;
+        info!("Starting controller...");
+        let started = controller.as_mut_controller().begin()?;
+
+        if started {
+            info!("Remote device is online.")
+        } else {
+            let err = "Remote device is not online and/or can not be started.";
+            error!("{}", err);
+            return Err(String::from(err));
+        }
+
+        Some(controller)
+    } else {
+        debug!("No controller found.... Skipping.");
+        None
+    };
+
+    let action_result = action(&module);
+    log_error!(&action_result);
+
+    let clear_result = module.clear();
+    log_error!(&clear_result);
+
+    // controller should be terminated regardless of sync restore result
+    return if let Some(mut controller) = controller {
+        debug!("Running controller end procedure...");
+        let end_result = controller.as_mut_controller().end();
+        log_error!(&end_result);
+
+        if let Ok(ended) = end_result.as_ref() {
+            if !ended {
+                // anything we can do here?
+                warn!("Controller did not end properly...");
+            }
+        }
+
+        debug!("Running controller clear procedure...");
+        let clear_result = controller.as_mut_controller().clear();
+        log_error!(&clear_result);
+
+        action_result.and_then(|value| end_result.and(clear_result).map(|_| value))
+    } else {
+        action_result
+    };
+}
+
+/**
+ * Restore the backup of a configuration without asking
+ */
+pub fn run_backup_restore(args: &Rc<Arguments>, paths: &Rc<Paths>, config: &Configuration, backup_config: &BackupConfiguration, request: &RestoreRequest) -> Result<(),String> {
+    return with_backup_module(args, paths, config, backup_config, &|module| {
+        info!("Starting backup restore...");
+        module.restore(request)
+    });
+}
+
+/**
+ * Run an action with the backup module of a configuration
+ */
+pub fn with_backup_module<T>(args: &Rc<Arguments>, paths: &Rc<Paths>, config: &Configuration, backup_config: &BackupConfiguration, action: &dyn Fn(&BackupModule) -> Result<T,String>) -> Result<T,String> {
+    let module_paths = ModulePaths::for_backup_module(paths, "backup", config);
+    let mut module = BackupModule::new(backup_config.backup_type.as_str(), config.name.as_str(), &backup_config.config, module_paths, args)?;
+    module.init()?;
+
+    let action_result = action(&module);
+    let clear_result = module.clear();
+
+    log_error!(clear_result);
+    return action_result;
+}
+
+/**
+ * Get whether the backup and / or sync part should be used according to the --only option
+ */
+pub fn parse_only(args: &Arguments) -> Result<(bool, bool), String> {
+    return match args.only.as_ref().map(|only| only.as_str()) {
+        None => Ok((true, true)),
+        Some("backup") => Ok((true, false)),
+        Some("sync") => Ok((false, true)),
+        Some(unknown) => Err(format!("Unknown value for --only: '{}', expected backup or sync", unknown))
+    };
+}
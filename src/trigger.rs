@@ -0,0 +1,161 @@
+use crate::util::io::{auth_data,json,lock};
+use crate::util::objects::paths::{Paths,PathBase};
+use crate::util::objects::trigger::{RunRequest,RunResponse};
+use crate::modules::shared::mqtt::{MqttConfiguration,get_client,decode_payload,qos_from_u8};
+use crate::{Arguments, vbackup};
+
+use crate::{try_option,try_result,try_result_debug,log_error};
+
+use std::path::Path;
+use serde::{Deserialize};
+use serde_json::Value;
+use rumqttc::{Client, QoS};
+
+#[derive(Deserialize)]
+struct Configuration {
+    base_topic: Option<String>,
+    auth_reference: Option<String>,
+    auth: Option<Value>
+}
+
+struct Topics {
+    command: String,
+    response: String,
+    state: String
+}
+
+/**
+ * Listen for run requests on the command topic and execute them one after another
+ */
+pub fn main(args: Arguments) -> Result<(),String> {
+    let base_paths = json::from_file::<PathBase>(Path::new(args.base_config.as_str()))?;
+    let paths = Paths::from(base_paths);
+
+    let config_option = json::from_file_checked::<Configuration>(Path::new(paths.trigger_file.as_str()))?;
+    let config = try_option!(config_option, format!("No trigger configuration found at '{}'", paths.trigger_file.as_str()));
+    let mqtt_config = auth_data::resolve::<MqttConfiguration>(&config.auth_reference, &config.auth, &paths)?;
+    let qos = qos_from_u8(mqtt_config.qos)?;
+
+    let base_topic = config.base_topic.clone().unwrap_or(format!("device/{}/vbackup/trigger", mqtt_config.user));
+    let topics = Topics {
+        command: format!("{}/command", base_topic),
+        response: format!("{}/response", base_topic),
+        state: format!("{}/state", base_topic)
+    };
+
+    let (mut client, receiver, join_handle) = try_result!(get_client(&mqtt_config, topics.state.as_str(), "offline", Some(vec![topics.command.clone()])), "Could not create mqtt client and receiver");
+    publish_state(&mut client, &topics, qos, mqtt_config.retain, "idle");
+
+    info!("Listening for run requests on '{}'", topics.command.as_str());
+
+    // Requests that are received during a run are queued in the receiver and handled afterwards
+    while let Ok(message) = receiver.recv() {
+        if message.topic != topics.command {
+            continue;
+        }
+
+        let payload = match decode_payload(message.payload.as_ref()) {
+            Ok(payload) => payload,
+            Err(err) => {
+                warn!("Ignoring run request: {}", err);
+                continue;
+            }
+        };
+
+        debug!("Received run request: {}", payload);
+
+        let response = match serde_json::from_str::<RunRequest>(payload.as_str()) {
+            Ok(request) => {
+                publish_state(&mut client, &topics, qos, mqtt_config.retain, "running");
+                let response = handle_request(&request, &args);
+                publish_state(&mut client, &topics, qos, mqtt_config.retain, "idle");
+                response
+            },
+            Err(err) => {
+                warn!("Could not parse run request: {}", err);
+                RunResponse {
+                    id: None,
+                    operation: String::new(),
+                    name: None,
+                    status: "invalid",
+                    error: Some(format!("Could not parse run request: {}", err)),
+                    timestamp: chrono::Local::now().timestamp()
+                }
+            }
+        };
+
+        match serde_json::to_string(&response) {
+            Ok(response_string) => {
+                if let Err(err) = client.publish(topics.response.as_str(), qos, false, response_string) {
+                    error!("Could not publish the response to the run request: {}", err);
+                }
+            },
+            Err(err) => error!("Could not serialize the response to the run request: {}", err)
+        }
+    }
+
+    // The receiver only closes if the connection thread is terminated
+    log_error!(client.disconnect());
+    try_result_debug!(join_handle.join(), "Error when trying to wait for the MQTT thread");
+    return Err(String::from("Connection to the mqtt broker was closed"));
+}
+
+fn handle_request(request: &RunRequest, base_args: &Arguments) -> RunResponse {
+    let (status, error) = match request.to_arguments(base_args) {
+        Ok(args) => run_locked(args),
+        Err(err) => {
+            warn!("Rejecting run request: {}", err);
+            ("invalid", Some(err))
+        }
+    };
+
+    return RunResponse {
+        id: request.id.clone(),
+        operation: request.operation.clone(),
+        name: request.name.clone(),
+        status,
+        error,
+        timestamp: chrono::Local::now().timestamp()
+    };
+}
+
+fn run_locked(args: Arguments) -> (&'static str, Option<String>) {
+    let operation = args.operation.clone();
+
+    // Use the same lock as runs from the command line, such that they can not overlap
+    let lock_file = match lock::try_acquire() {
+        Ok(Some(file)) => file,
+        Ok(None) => {
+            warn!("Could not acquire file lock for vbackup, another run is in progress");
+            return ("busy", Some(String::from("Another run is in progress")));
+        },
+        Err(err) => {
+            error!("{}", err);
+            return ("failed", Some(err));
+        }
+    };
+
+    info!("Starting triggered '{}'", operation.as_str());
+    let result = vbackup::main(args);
+
+    if let Err(err) = lock::release(lock_file) {
+        error!("{}", err);
+    }
+
+    return match result {
+        Ok(()) => {
+            info!("Done with triggered '{}'", operation.as_str());
+            ("done", None)
+        },
+        Err(err) => {
+            error!("Triggered run failed: {}", err);
+            ("failed", Some(err))
+        }
+    };
+}
+
+fn publish_state(client: &mut Client, topics: &Topics, qos: QoS, retain: bool, state: &str) {
+    if let Err(err) = client.publish(topics.state.as_str(), qos, retain, state) {
+        error!("Could not publish trigger state: {}", err);
+    }
+}
@@ -1,15 +1,99 @@
 use std::os::unix::prelude::ExitStatusExt;
+use std::os::unix::process::CommandExt;
 use crate::util::objects::paths::{SourcePath, ModulePaths};
 use crate::{change_error, try_result, dry_run, Arguments};
 
-use std::process::{Command, Child, ExitStatus};
+use std::process::{Command, Child, ExitStatus, Output, Stdio};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Processes that are currently executed and the name of their docker container, in order to be able to terminate them on cancel
+static RUNNING: Mutex<Vec<(u32, Option<String>)>> = Mutex::new(vec![]);
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+// Start commands in their own process group, such that a cancel also reaches the other processes of a pipeline
+//  only used by the api, in a terminal Ctrl-C would not reach the commands anymore
+static PROCESS_GROUPS: AtomicBool = AtomicBool::new(false);
+
+pub fn use_process_groups() {
+    PROCESS_GROUPS.store(true, Ordering::SeqCst);
+}
+
+/**
+ * Terminate all running commands and refuse to start any new ones until the cancel flag is reset
+ */
+pub fn cancel_all() -> usize {
+    CANCELLED.store(true, Ordering::SeqCst);
+
+    let running = match RUNNING.lock() {
+        Ok(running) => running.clone(),
+        Err(_) => vec![]
+    };
+
+    for (pid, container) in running.iter() {
+        debug!("Terminating process {} due to cancel", pid);
+        unsafe {
+            if PROCESS_GROUPS.load(Ordering::SeqCst) {
+                // the id of the process group is the one of its first process
+                libc::kill(-(*pid as libc::pid_t), libc::SIGTERM);
+            } else {
+                libc::kill(*pid as libc::pid_t, libc::SIGTERM);
+            }
+        }
+
+        // Terminating the docker client does not stop the container, sh as its first process ignores SIGTERM
+        if let Some(container) = container {
+            debug!("Killing container '{}' due to cancel", container);
+            let result = Command::new("docker")
+                .arg("kill")
+                .arg(container)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+
+            match result {
+                Ok(status) if !status.success() => warn!("Could not kill container '{}' ({})", container, status),
+                Err(err) => warn!("Could not kill container '{}' ({})", container, err),
+                Ok(_) => {}
+            }
+        }
+    }
+
+    return running.len();
+}
+
+pub fn is_cancelled() -> bool {
+    return CANCELLED.load(Ordering::SeqCst);
+}
+
+pub fn reset_cancel() {
+    CANCELLED.store(false, Ordering::SeqCst);
+}
+
+// Single quotes for the shell, quotes in the value end the quoting and are escaped
+pub fn quote(value: &str) -> String {
+    return format!("'{}'", value.replace('\'', "'\\''"));
+}
+
+fn register(pid: u32, container: Option<String>) {
+    if let Ok(mut running) = RUNNING.lock() {
+        running.push((pid, container));
+    }
+}
+
+fn unregister(pid: u32) {
+    if let Ok(mut running) = RUNNING.lock() {
+        running.retain(|(running_pid, _)| *running_pid != pid);
+    }
+}
 
 pub struct CommandWrapper {
     command: Command,
     base: String,
     args: Vec<String>,
     envs: Vec<String>,
-    wrapped: Option<Vec<String>>
+    wrapped: Option<Vec<String>>,
+    container: Option<String>
 }
 
 impl CommandWrapper {
@@ -19,7 +103,8 @@ impl CommandWrapper {
             base: cmd.to_string(),
             args: vec![],
             envs: vec![],
-            wrapped: None
+            wrapped: None,
+            container: None
         }
     }
 
@@ -48,7 +133,8 @@ impl CommandWrapper {
             base: String::from(executable),
             args: vec![],
             envs: vec![],
-            wrapped: None
+            wrapped: None,
+            container: None
         };
 
         cmd.arg_str("run");
@@ -97,6 +183,13 @@ impl CommandWrapper {
         if let Some(wrapped) = self.wrapped.as_mut() {
             wrapped.push(option);
         } else {
+            // Remember the name of the container to be able to kill it on cancel
+            if self.base == "docker" {
+                if let Some(container) = option.strip_prefix("--name=") {
+                    self.container = Some(String::from(container));
+                }
+            }
+
             self.command.arg(&option);
             self.args.push(option);
         }
@@ -126,9 +219,34 @@ impl CommandWrapper {
     }
 
     pub fn spawn(&mut self) -> Result<Child,String> {
+        if is_cancelled() {
+            return Err(String::from("Run was cancelled"));
+        }
+
+        if PROCESS_GROUPS.load(Ordering::SeqCst) {
+            self.command.process_group(0);
+        }
+
         change_error!(self.command.spawn(), "Failed spawning command")
     }
 
+    // Same as Command::output, but the process can be terminated by a cancel
+    fn output(&mut self) -> Result<Output, String> {
+        return self.output_with_stderr(Stdio::piped());
+    }
+
+    fn output_with_stderr(&mut self, stderr: Stdio) -> Result<Output, String> {
+        self.command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(stderr);
+        let process = self.spawn()?;
+
+        let pid = process.id();
+        register(pid, self.container.clone());
+        let result = process.wait_with_output();
+        unregister(pid);
+
+        return result.map_err(|err| format!("Failed executing command: {}", err));
+    }
+
     pub fn run(&mut self) -> Result<(), String> {
         let exit_status = self.run_get_status()?;
         if !exit_status.success() {
@@ -187,6 +305,47 @@ impl CommandWrapper {
         }
     }
 
+    /**
+     * Run the command and return its output, the command and its output are shown according to the arguments
+     *  error messages are shown with the output or logged on failure
+     */
+    pub fn run_get_output_with_args(&mut self, args: &Arguments) -> Result<Option<String>,String> {
+        if args.dry_run {
+            dry_run!(self.to_string());
+            return Ok(None);
+        }
+
+        if args.show_command {
+            println!("-> {}", self.to_string());
+        }
+
+        let stderr = if args.show_command_output { Stdio::inherit() } else { Stdio::piped() };
+        let output = self.output_with_stderr(stderr)?;
+        let output_str: String = try_result!(String::from_utf8(output.stdout), "Command output can't be converted from UTF-8");
+        if args.show_command_output {
+            print!("{}", output_str);
+        }
+
+        if !output.status.success() {
+            let error_output = String::from_utf8_lossy(&output.stderr);
+            if !error_output.trim().is_empty() {
+                error!("{}", error_output.trim());
+            }
+
+            let msg;
+            if let Some(rc) = output.status.code() {
+                msg = format!("Exit code {} indicates failure of command: {}", rc, self.to_string());
+            } else {
+                msg = format!("Exit code indicates failure of command: {}", self.to_string());
+            }
+
+            error!("{}", msg);
+            return Err(msg);
+        }
+
+        return Ok(Some(output_str));
+    }
+
     pub fn run_without_output(&mut self) -> Result<(), String> {
         let exit_status = self.run_get_status_without_output()?;
         if !exit_status.success() {
@@ -224,23 +383,24 @@ impl CommandWrapper {
     }
 
     pub fn run_get_status_without_output(&mut self) -> Result<ExitStatus, String> {
-        let result = self.command.output();
-
-        if let Ok(output) = result {
-            return Ok(output.status);
-        } else {
-            return Err(format!("Failed executing command: {}", result.unwrap_err().to_string()))
-        }
+        let output = self.output()?;
+        return Ok(output.status);
     }
 
     pub fn run_get_status(&mut self) -> Result<ExitStatus, String> {
         let mut process: Child = try_result!(self.spawn(), "Failed to start command execution");
-        let exit_status: ExitStatus = try_result!(process.wait(), "Failed to run command");
+
+        let pid = process.id();
+        register(pid, self.container.clone());
+        let result = process.wait();
+        unregister(pid);
+
+        let exit_status: ExitStatus = try_result!(result, "Failed to run command");
         return Ok(exit_status);
     }
 
     pub fn run_get_output(&mut self) -> Result<String,String> {
-        let result = self.command.output();
+        let result = self.output();
 
         if let Ok(output) = result {
             if output.status.success() {
@@ -261,7 +421,7 @@ impl CommandWrapper {
                 return Err(msg);
             }
         } else {
-            return Err(format!("Failed executing command: {}", result.unwrap_err().to_string()))
+            return Err(result.unwrap_err());
         }
     }
 }
@@ -37,4 +37,31 @@ pub fn build_image_if_missing(paths: &Paths, image_file: &str, image_name: &str)
     } else {
         return build_image(paths, image_file, image_name).map(|_| true);
     }
-}
\ No newline at end of file
+}
+
+pub fn volume_mountpoint(volume_name: &str) -> Result<String,String> {
+    let mut cmd = CommandWrapper::new("docker");
+    cmd.arg_str("volume")
+        .arg_str("inspect")
+        .arg_str("--format={{ .Mountpoint }}")
+        .arg_str(volume_name);
+
+    let output = cmd.run_get_output()?;
+    let mountpoint = output.trim();
+    if mountpoint.is_empty() {
+        return Err(format!("Could not get the mountpoint of docker volume '{}'", volume_name));
+    }
+
+    return Ok(String::from(mountpoint));
+}
+
+// Docker volumes are accessed using their mountpoint on the host
+pub fn host_path(path: &str, no_docker: bool) -> Result<String,String> {
+    if path.starts_with('/') {
+        return Ok(String::from(path));
+    } else if no_docker {
+        return Err(format!("Source '{}' is not an absolute path", path));
+    } else {
+        return volume_mountpoint(path);
+    }
+}
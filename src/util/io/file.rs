@@ -6,7 +6,8 @@ use std::process::{Command, Child, ExitStatus};
 use std::io::{Write, Read};
 use std::fs;
 use std::fs::{OpenOptions, File, read_dir, remove_file, rename};
-use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+use std::os::unix::io::AsRawFd;
 use std::path::{Path, PathBuf};
 
 pub fn write_with_perm(file_name: &str, mode: &str, to_write: &str, overwrite: bool) -> Result<(), String>{
@@ -89,6 +90,42 @@ pub fn move_file(from: &str, to: &str) -> Result<(),String> {
     }
 }
 
+// ioctl request to share the data of a file on copy-on-write file systems, see linux/fs.h
+const FICLONE: libc::c_ulong = 0x40049409;
+
+pub fn copy_file(from: &str, to: &str) -> Result<(),String> {
+    return fs::copy(from, to)
+        .map(|_| ())
+        .map_err(|err| format!("Could not copy '{}' to '{}': {}", from, to, err));
+}
+
+pub fn hard_link(from: &str, to: &str) -> Result<(),String> {
+    return fs::hard_link(from, to)
+        .map_err(|err| format!("Could not hard-link '{}' to '{}': {}", from, to, err));
+}
+
+pub fn symlink(target: &str, link: &str) -> Result<(),String> {
+    return std::os::unix::fs::symlink(target, link)
+        .map_err(|err| format!("Could not create symlink '{}' to '{}': {}", link, target, err));
+}
+
+pub fn reflink(from: &str, to: &str) -> Result<(),String> {
+    let source = try_result!(File::open(from), format!("Could not open file '{}' for reading", from));
+    let target = try_result!(OpenOptions::new().write(true).create_new(true).open(to), format!("Could not create file '{}'", to));
+
+    let result = unsafe { libc::ioctl(target.as_raw_fd(), FICLONE as _, source.as_raw_fd()) };
+    if result != 0 {
+        let err = std::io::Error::last_os_error();
+        drop(target);
+        if let Err(remove_err) = remove_file(to) {
+            warn!("Could not remove file '{}' after failed reflink ({})", to, remove_err);
+        }
+        return Err(format!("Could not reflink '{}' to '{}': {}", from, to, err));
+    }
+
+    return Ok(());
+}
+
 pub fn checked_remove(file_name: &str) -> Result<bool, String> {
     let path = Path::new(file_name);
     return if path.exists() {
@@ -152,6 +189,30 @@ pub fn create_path_dir_if_missing(path: &Path, also_parent: bool) -> Result<bool
     }
 }
 
+/**
+ * A mount point is on another device than its parent or listed in the mount table (bind mounts)
+ */
+pub fn is_mount_point(dir_name: &str) -> Result<bool, String> {
+    let path = try_result!(fs::canonicalize(dir_name), format!("Could not resolve path '{}'", dir_name));
+    let metadata = try_result!(fs::metadata(&path), format!("Could not read metadata of '{}'", dir_name));
+    let parent_metadata = try_result!(fs::metadata(path.join("..")), format!("Could not read metadata of the parent of '{}'", dir_name));
+
+    if metadata.dev() != parent_metadata.dev() || metadata.ino() == parent_metadata.ino() {
+        return Ok(true);
+    }
+
+    // spaces and other special characters are escaped as octal numbers in the mount table
+    let escaped = path.to_string_lossy()
+        .replace('\\', "\\134")
+        .replace(' ', "\\040")
+        .replace('\t', "\\011")
+        .replace('\n', "\\012");
+
+    return Ok(fs::read_to_string("/proc/self/mounts")
+        .map(|mounts| mounts.lines().any(|line| line.split(' ').nth(1) == Some(escaped.as_str())))
+        .unwrap_or(false));
+}
+
 pub fn list_in_dir(dir_name: &str) -> Result<Vec<PathBuf>, String> {
     let path = Path::new(dir_name);
 
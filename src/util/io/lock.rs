@@ -0,0 +1,33 @@
+use std::fs::{File, OpenOptions};
+use std::os::unix::fs::OpenOptionsExt;
+use fs2::FileExt;
+
+const LOCK_FILE: &str = "/run/vbackup.lock";
+
+/**
+ * Try to acquire the global lock that ensures only one instance of vbackup is running.
+ * Returns an error if the lock file is not accessible and None if the lock is held by another process.
+ */
+pub fn try_acquire() -> Result<Option<File>, String> {
+    let lock_file = match OpenOptions::new()
+        .create(true) // Create file if it does not exist
+        .read(true)
+        .write(true)
+        .mode(u32::from_str_radix("600", 8).unwrap()) // Only sets mode when creating the file...
+        .open(LOCK_FILE) {
+        Ok(file) => file,
+        Err(err) => {
+            return Err(format!("Could not access lock file for vbackup: {}", err));
+        }
+    };
+
+    if lock_file.try_lock_exclusive().is_err() {
+        return Ok(None);
+    }
+
+    return Ok(Some(lock_file));
+}
+
+pub fn release(lock_file: File) -> Result<(), String> {
+    return lock_file.unlock().map_err(|err| format!("Releasing file lock failed: {}", err));
+}
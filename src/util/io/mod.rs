@@ -2,4 +2,5 @@ pub mod file;
 pub mod savefile;
 pub mod json;
 pub mod auth_data;
-pub mod user;
\ No newline at end of file
+pub mod user;
+pub mod lock;
\ No newline at end of file
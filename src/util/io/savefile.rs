@@ -22,7 +22,10 @@ pub fn get_savedata(path: &str) -> Result<SaveData, String> {
         SaveDataDeserialized {
             lastsave: HashMap::new(),
             nextsave: HashMap::new(),
-            lastsync: HashMap::new()
+            lastsync: HashMap::new(),
+            lastverify: HashMap::new(),
+            laststatistics: HashMap::new(),
+            lastmaintenance: HashMap::new()
         }
     };
 
@@ -53,12 +56,14 @@ pub fn format_filename(time: &DateTime<Local>, timeframe: &TimeFrameReference, n
     }
 }
 
-pub fn prune(directory: &str, identifier: &str, amount: &usize) -> Result<bool, String> {
-    // Delete oldest savefiles if more than amount
+pub fn prune(directory: &str, identifier: &str, amount: &usize, filter: &dyn Fn(&str) -> bool) -> Result<bool, String> {
+    // Delete oldest savefiles if more than amount, only files the filter accepts are counted (e.g. no manifests)
     let pattern = format!("{}/*_{}_*", directory, identifier);
     let paths: Paths = try_result!(glob::glob(pattern.as_str()), "Could not read file list");
 
-    let mut list: Vec<PathBuf> = paths.filter_map(Result::ok).collect();
+    let mut list: Vec<PathBuf> = paths.filter_map(Result::ok)
+        .filter(|path| path.file_name().map_or(false, |file_name| filter(file_name.to_string_lossy().as_ref())))
+        .collect();
     if list.len().gt(amount) {
         // Unstable sort works as file paths are unique and file names are prefixed with the ISO date
         list.sort_unstable();
@@ -68,6 +73,16 @@ pub fn prune(directory: &str, identifier: &str, amount: &usize) -> Result<bool,
             if remove_file(oldest_file_path).is_err() {
                 return Err(format!("Could not remove oldest file in '{}'", directory));
             }
+
+            // Files belonging to the removed file are named like it with an additional extension
+            let sidecar_pattern = format!("{}.*", glob::Pattern::escape(oldest_file_path.to_string_lossy().as_ref()));
+            let sidecars: Paths = try_result!(glob::glob(sidecar_pattern.as_str()), "Could not read file list");
+            for sidecar in sidecars.filter_map(Result::ok) {
+                debug!("Removing file belonging to the oldest file: {:?}", sidecar);
+                if remove_file(&sidecar).is_err() {
+                    error!("Could not remove {:?}", sidecar);
+                }
+            }
         } else {
             // This should never happen... Backup is not executed if amount equals zero
             warn!("There is no oldest file to remove, the amount of saves seems to be zero, but still the backup was executed");
@@ -76,4 +91,4 @@ pub fn prune(directory: &str, identifier: &str, amount: &usize) -> Result<bool,
     } else {
         return Ok(false);
     };
-}
\ No newline at end of file
+}
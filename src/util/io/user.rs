@@ -1,5 +1,8 @@
 use std::io;
+use std::io::IsTerminal;
 use std::str::FromStr;
+use chrono::{DateTime, Local};
+use crate::util::objects::restore::{ArchiveSelector, RestoreRequest};
 use crate::try_result;
 
 fn get_user_input_line() -> Result<String,String> {
@@ -86,35 +89,69 @@ pub fn ask_user_option_list_index<T>(
     };
 }
 
-pub fn ask_user_option_list<'a,T>(
+fn apply_mapper<'a,T>(fun: &'a dyn Fn(&T) -> &str, input: &'a T) -> &'a str {
+    fun(input)
+}
+
+/**
+ * Ask for confirmation, unless confirmations are given upfront with --yes
+ */
+pub fn confirm(assume_yes: bool, question: &str, default: bool) -> Result<bool, String> {
+    if assume_yes {
+        info!("{} yes (--yes)", question);
+        return Ok(true);
+    }
+
+    require_terminal(question)?;
+    return ask_user_boolean(question, default);
+}
+
+pub fn confirm_abort(request: &RestoreRequest, text: Option<&str>) -> Result<(), String> {
+    return if confirm(request.assume_yes, text.unwrap_or("Continue?"), true)? {
+        Ok(())
+    } else {
+        Err(String::from("Aborted by user"))
+    };
+}
+
+/**
+ * Select an archive from a list sorted from oldest to newest, using --archive if it is set
+ */
+pub fn select_archive<T>(
+    request: &RestoreRequest,
     string_before_list: Option<&str>,
     string_after_list: Option<&str>,
-    input_list: &'a Vec<T>,
-    list_mapper: &'a dyn Fn(&T) -> &str,
-    default_index: usize
-) -> Result<&'a T, String> {
+    input_list: &Vec<T>,
+    id_mapper: &dyn Fn(&T) -> &str,
+    time_mapper: &dyn Fn(&T) -> Option<DateTime<Local>>
+) -> Result<usize, String> {
+    if let Some(archive) = request.archive.as_ref() {
+        let index = ArchiveSelector::parse(archive.as_str())?.select(input_list, id_mapper, time_mapper)?;
+        info!("Selected archive '{}' for '{}'", apply_mapper(id_mapper, &input_list[index]), archive);
+        return Ok(index);
+    }
+
+    require_terminal("Which archive should be restored?")?;
+
     let index = ask_user_option_list_index(
         string_before_list,
         string_after_list,
         input_list,
-        list_mapper,
-        default_index
+        id_mapper,
+        input_list.len().saturating_sub(1)
     )?;
 
-    return input_list
-        .get(index)
-        .ok_or(String::from("Selected index is out of bounds"));
-}
-
-fn apply_mapper<'a,T>(fun: &'a dyn Fn(&T) -> &str, input: &'a T) -> &'a str {
-    fun(input)
+    return if index < input_list.len() {
+        Ok(index)
+    } else {
+        Err(String::from("Selected index is out of bounds"))
+    };
 }
 
-pub fn ask_user_abort(text: Option<&str>) -> Result<(),String> {
-    let confirm = ask_user_boolean(text.unwrap_or("Continue?"), true)?;
-    return if !confirm {
-        Err(String::from("Aborted by user"))
-    } else {
+fn require_terminal(question: &str) -> Result<(), String> {
+    return if io::stdin().is_terminal() {
         Ok(())
-    }
-}
\ No newline at end of file
+    } else {
+        Err(format!("Can not ask '{}' without a terminal, use --yes and --archive for non-interactive runs", question))
+    };
+}
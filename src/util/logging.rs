@@ -0,0 +1,351 @@
+use chrono::Local;
+use env_logger::Builder;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use std::fs::{File, OpenOptions, rename};
+use std::io::Write;
+use std::os::unix::net::UnixDatagram;
+use std::sync::Mutex;
+
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+const SYSLOG_SOCKET: &str = "/dev/log";
+const SYSLOG_IDENTIFIER: &str = "vbackup";
+
+// RFC 5424: facility daemon, private enterprise number for the structured data element
+const SYSLOG_FACILITY: u8 = 3;
+const SYSLOG_SD_ID: &str = "vbackup@32473";
+
+pub enum LogTarget {
+    Stderr,
+    Journald,
+    Syslog
+}
+
+impl LogTarget {
+    pub fn from_str(target: &str) -> Result<LogTarget, String> {
+        match target.to_lowercase().as_str() {
+            "stderr" => Ok(LogTarget::Stderr),
+            "journald" | "journal" => Ok(LogTarget::Journald),
+            "syslog" => Ok(LogTarget::Syslog),
+            unknown => Err(format!("Unknown log target: '{}'", unknown))
+        }
+    }
+}
+
+pub struct LogFileOptions {
+    pub path: String,
+    pub max_size: u64, // rotate once the file exceeds this size in bytes, zero disables rotation
+    pub keep: usize // number of rotated files to keep
+}
+
+// Structured information about what is currently executed, attached to every log entry
+struct LogContext {
+    config: Option<String>,
+    run_type: Option<String>,
+    module: Option<String>
+}
+
+static CONTEXT: Mutex<LogContext> = Mutex::new(LogContext {
+    config: None,
+    run_type: None,
+    module: None
+});
+
+pub fn set_context(config: Option<&str>, run_type: Option<&str>) {
+    if let Ok(mut context) = CONTEXT.lock() {
+        context.config = config.map(String::from);
+        context.run_type = run_type.map(String::from);
+        context.module = None;
+    }
+}
+
+pub fn set_module(module: Option<&str>) {
+    if let Ok(mut context) = CONTEXT.lock() {
+        context.module = module.map(String::from);
+    }
+}
+
+pub fn clear_context() {
+    set_context(None, None);
+}
+
+pub fn init(target: LogTarget, level: LevelFilter, file_options: Option<LogFileOptions>) -> Result<(), String> {
+    let stderr = Builder::new()
+        .filter_level(LevelFilter::Warn)
+        .filter_module("vbackup", level)
+        .format(|buf, record| {
+            let timestamp = Local::now().format("%Y-%m-%dT%H:%M:%S%:z");
+            writeln!(buf, "[{} {:<5} {}] {}", timestamp, buf.default_styled_level(record.level()), record.target(), record.args())
+        })
+        .build();
+
+    let socket_result = match target {
+        LogTarget::Stderr => Ok(None),
+        LogTarget::Journald => connect_socket(JOURNALD_SOCKET).map(Some),
+        LogTarget::Syslog => connect_socket(SYSLOG_SOCKET).map(Some)
+    };
+
+    // Logging is not set up yet, so just print that the fallback to stderr is used
+    let socket = match socket_result {
+        Ok(socket) => socket,
+        Err(err) => {
+            eprintln!("{}, logging to stderr instead", err);
+            None
+        }
+    };
+
+    let file = if let Some(options) = file_options {
+        Some(Mutex::new(RotatingFile::open(options)?))
+    } else {
+        None
+    };
+
+    let max_level = stderr.filter();
+    let logger = Logger {
+        stderr,
+        target,
+        socket,
+        file
+    };
+
+    if let Err(err) = log::set_boxed_logger(Box::new(logger)) {
+        return Err(format!("Could not set up logging: {}", err));
+    }
+    log::set_max_level(max_level);
+
+    return Ok(());
+}
+
+fn connect_socket(path: &str) -> Result<UnixDatagram, String> {
+    let socket = UnixDatagram::unbound().map_err(|err| format!("Could not create logging socket: {}", err))?;
+    socket.connect(path).map_err(|err| format!("Could not connect to logging socket '{}': {}", path, err))?;
+    return Ok(socket);
+}
+
+struct Logger {
+    stderr: env_logger::Logger,
+    target: LogTarget,
+    socket: Option<UnixDatagram>,
+    file: Option<Mutex<RotatingFile>>
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.stderr.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.stderr.matches(record) {
+            return;
+        }
+
+        let (config, run_type, module) = if let Ok(context) = CONTEXT.lock() {
+            (context.config.clone(), context.run_type.clone(), context.module.clone())
+        } else {
+            (None, None, None)
+        };
+
+        let sent = match (&self.target, self.socket.as_ref()) {
+            (LogTarget::Journald, Some(socket)) => {
+                let message = format_journald(record, &config, &run_type, &module);
+                socket.send(message.as_slice()).is_ok()
+            },
+            (LogTarget::Syslog, Some(socket)) => {
+                let message = format_syslog(record, &config, &run_type, &module);
+                socket.send(message.as_bytes()).is_ok()
+            },
+            _ => false
+        };
+
+        // Fall back to stderr if the socket is not available (anymore)
+        if !sent {
+            self.stderr.log(record);
+        }
+
+        if let Some(file) = self.file.as_ref() {
+            if let Ok(mut file) = file.lock() {
+                let line = format_line(record, &config, &run_type, &module);
+                if let Err(err) = file.write_line(line.as_str()) {
+                    eprintln!("Could not write to log file: {}", err);
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+    }
+}
+
+fn get_priority(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7
+    }
+}
+
+fn format_journald(record: &Record, config: &Option<String>, run_type: &Option<String>, module: &Option<String>) -> Vec<u8> {
+    fn append_field(buffer: &mut Vec<u8>, key: &str, value: &str) {
+        buffer.extend_from_slice(key.as_bytes());
+
+        // Values containing a newline need to be sent in the binary format of the journal protocol
+        if value.contains('\n') {
+            buffer.push(b'\n');
+            buffer.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            buffer.push(b'=');
+        }
+
+        buffer.extend_from_slice(value.as_bytes());
+        buffer.push(b'\n');
+    }
+
+    let mut buffer = vec![];
+    append_field(&mut buffer, "MESSAGE", record.args().to_string().as_str());
+    append_field(&mut buffer, "PRIORITY", get_priority(record.level()).to_string().as_str());
+    append_field(&mut buffer, "SYSLOG_IDENTIFIER", SYSLOG_IDENTIFIER);
+    append_field(&mut buffer, "VBACKUP_TARGET", record.target());
+
+    if let Some(file) = record.file() {
+        append_field(&mut buffer, "CODE_FILE", file);
+    }
+
+    if let Some(line) = record.line() {
+        append_field(&mut buffer, "CODE_LINE", line.to_string().as_str());
+    }
+
+    if let Some(config) = config.as_ref() {
+        append_field(&mut buffer, "VBACKUP_CONFIG", config);
+    }
+
+    if let Some(run_type) = run_type.as_ref() {
+        append_field(&mut buffer, "VBACKUP_RUN_TYPE", run_type);
+    }
+
+    if let Some(module) = module.as_ref() {
+        append_field(&mut buffer, "VBACKUP_MODULE", module);
+    }
+
+    return buffer;
+}
+
+fn format_syslog(record: &Record, config: &Option<String>, run_type: &Option<String>, module: &Option<String>) -> String {
+    fn escape(value: &str) -> String {
+        value.replace('\\', "\\\\").replace('"', "\\\"").replace(']', "\\]")
+    }
+
+    let priority = SYSLOG_FACILITY * 8 + get_priority(record.level());
+    let timestamp = Local::now().to_rfc3339();
+    let hostname = std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|name| String::from(name.trim()))
+        .unwrap_or(String::from("-"));
+
+    let mut structured_data = vec![];
+    if let Some(config) = config.as_ref() {
+        structured_data.push(format!("config=\"{}\"", escape(config)));
+    }
+    if let Some(run_type) = run_type.as_ref() {
+        structured_data.push(format!("run_type=\"{}\"", escape(run_type)));
+    }
+    if let Some(module) = module.as_ref() {
+        structured_data.push(format!("module=\"{}\"", escape(module)));
+    }
+
+    let structured_data_string = if structured_data.is_empty() {
+        String::from("-")
+    } else {
+        format!("[{} {}]", SYSLOG_SD_ID, structured_data.join(" "))
+    };
+
+    return format!("<{}>1 {} {} {} {} - {} {}",
+                   priority,
+                   timestamp,
+                   hostname,
+                   SYSLOG_IDENTIFIER,
+                   std::process::id(),
+                   structured_data_string,
+                   record.args());
+}
+
+fn format_line(record: &Record, config: &Option<String>, run_type: &Option<String>, module: &Option<String>) -> String {
+    let timestamp = Local::now().format("%Y-%m-%dT%H:%M:%S%:z");
+
+    let context = [config, run_type, module].iter()
+        .filter_map(|value| value.as_ref().map(|value| value.as_str()))
+        .collect::<Vec<&str>>()
+        .join("/");
+
+    return if context.is_empty() {
+        format!("[{} {:<5} {}] {}", timestamp, record.level(), record.target(), record.args())
+    } else {
+        format!("[{} {:<5} {}] [{}] {}", timestamp, record.level(), record.target(), context, record.args())
+    };
+}
+
+struct RotatingFile {
+    options: LogFileOptions,
+    file: File,
+    size: u64
+}
+
+impl RotatingFile {
+    fn open(options: LogFileOptions) -> Result<RotatingFile, String> {
+        let file = open_append(options.path.as_str())?;
+        let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+
+        return Ok(RotatingFile {
+            options,
+            file,
+            size
+        });
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), String> {
+        if self.options.max_size > 0 && self.size + line.len() as u64 >= self.options.max_size {
+            self.rotate()?;
+        }
+
+        if let Err(err) = writeln!(self.file, "{}", line) {
+            return Err(format!("Could not write to '{}': {}", self.options.path, err));
+        }
+
+        self.size += line.len() as u64 + 1;
+        return Ok(());
+    }
+
+    fn rotate(&mut self) -> Result<(), String> {
+        let path = self.options.path.as_str();
+
+        // Shift all rotated files by one: log.1 -> log.2, ..., the oldest one is overwritten
+        if self.options.keep > 0 {
+            for index in (1..self.options.keep).rev() {
+                let from = format!("{}.{}", path, index);
+                if std::path::Path::new(&from).exists() {
+                    if let Err(err) = rename(&from, format!("{}.{}", path, index + 1)) {
+                        return Err(format!("Could not rotate log file '{}': {}", from, err));
+                    }
+                }
+            }
+
+            if let Err(err) = rename(path, format!("{}.1", path)) {
+                return Err(format!("Could not rotate log file '{}': {}", path, err));
+            }
+        } else if let Err(err) = self.file.set_len(0) {
+            return Err(format!("Could not truncate log file '{}': {}", path, err));
+        }
+
+        self.file = open_append(path)?;
+        self.size = 0;
+        return Ok(());
+    }
+}
+
+fn open_append(path: &str) -> Result<File, String> {
+    return OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| format!("Could not open log file '{}': {}", path, err));
+}
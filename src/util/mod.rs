@@ -4,4 +4,5 @@ pub mod objects;
 
 pub mod macros;
 pub mod command;
-pub mod docker;
\ No newline at end of file
+pub mod docker;
+pub mod logging;
\ No newline at end of file
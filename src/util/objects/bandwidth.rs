@@ -0,0 +1,122 @@
+use chrono::{Local, NaiveTime};
+use serde::{Deserialize};
+
+/**
+ * Bandwidth limit for syncs, either a fixed rate or a schedule of rates for times of the day
+ *  "2 MB/s" or [{ "limit": "2 MB/s", "from": "08:00", "to": "22:00" }]
+ */
+#[derive(Deserialize,Clone)]
+#[serde(untagged)]
+pub enum BandwidthConfiguration {
+    Fixed(String),
+    Schedule(Vec<BandwidthWindow>)
+}
+
+#[derive(Deserialize,Clone)]
+pub struct BandwidthWindow {
+    pub limit: String,
+    pub from: String,
+    pub to: String
+}
+
+impl BandwidthConfiguration {
+    /**
+     * Parse the rates and times of all windows, the limit at a time only parses the windows up to the first match
+     */
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            BandwidthConfiguration::Fixed(limit) => {
+                parse_rate(limit.as_str())?;
+            },
+            BandwidthConfiguration::Schedule(windows) => {
+                for window in windows {
+                    parse_rate(window.limit.as_str())?;
+                    parse_time_of_day(window.from.as_str())?;
+                    parse_time_of_day(window.to.as_str())?;
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    /**
+     * The limit in bytes per second at the given time of the day, None if unlimited
+     *  the first matching window is used, there is no limit outside of all windows
+     */
+    pub fn limit_at(&self, time: &NaiveTime) -> Result<Option<u64>, String> {
+        match self {
+            BandwidthConfiguration::Fixed(limit) => {
+                return parse_rate(limit.as_str()).map(Some);
+            },
+            BandwidthConfiguration::Schedule(windows) => {
+                for window in windows {
+                    let from = parse_time_of_day(window.from.as_str())?;
+                    let to = parse_time_of_day(window.to.as_str())?;
+
+                    // a window ending before it starts spans midnight
+                    let matches = if from <= to {
+                        from <= *time && *time < to
+                    } else {
+                        from <= *time || *time < to
+                    };
+
+                    if matches {
+                        return parse_rate(window.limit.as_str()).map(Some);
+                    }
+                }
+
+                return Ok(None);
+            }
+        }
+    }
+}
+
+/**
+ * The limit in bytes per second right now, None if there is no bandwidth configuration or no window matches
+ */
+pub fn current_limit(bandwidth: Option<&BandwidthConfiguration>) -> Result<Option<u64>, String> {
+    return match bandwidth {
+        Some(bandwidth) => bandwidth.limit_at(&Local::now().time()),
+        None => Ok(None)
+    };
+}
+
+/**
+ * Convert a limit in bytes per second to KiB per second as used by most tools, but never to zero (which means unlimited)
+ */
+pub fn to_kibibytes(limit: u64) -> u64 {
+    return ((limit + 512) / 1024).max(1);
+}
+
+/**
+ * Parse a rate like '500K', '2 MB/s' or '1.5MiB', units are binary prefixes and default to bytes
+ */
+fn parse_rate(input: &str) -> Result<u64, String> {
+    let normalized = input.replace(' ', "").to_lowercase();
+    let without_time = normalized.strip_suffix("/s").unwrap_or(normalized.as_str());
+    let without_byte = without_time.strip_suffix('b').unwrap_or(without_time);
+    let without_binary = without_byte.strip_suffix('i').unwrap_or(without_byte);
+
+    let (number, factor) = match without_binary.chars().last() {
+        Some('k') => (&without_binary[..without_binary.len() - 1], 1024f64),
+        Some('m') => (&without_binary[..without_binary.len() - 1], 1024f64 * 1024f64),
+        Some('g') => (&without_binary[..without_binary.len() - 1], 1024f64 * 1024f64 * 1024f64),
+        _ => (without_binary, 1f64)
+    };
+
+    let value = number.parse::<f64>()
+        .map_err(|_| format!("Could not parse bandwidth limit '{}', expected a rate like '500K' or '2 MB/s'", input))?;
+
+    let bytes = (value * factor).round();
+    if !(bytes >= 1f64) {
+        return Err(format!("Bandwidth limit '{}' has to be greater than zero", input));
+    }
+
+    return Ok(bytes as u64);
+}
+
+fn parse_time_of_day(input: &str) -> Result<NaiveTime, String> {
+    return NaiveTime::parse_from_str(input, "%H:%M")
+        .map_err(|_| format!("Could not parse time of day '{}' of the bandwidth schedule, expected format HH:MM", input));
+}
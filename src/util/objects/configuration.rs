@@ -1,5 +1,6 @@
 use crate::util::objects::time::TimeFrameReference;
 use crate::util::objects::paths::SourcePath;
+use crate::util::objects::bandwidth::BandwidthConfiguration;
 
 use serde_json::Value;
 use serde::{Deserialize};
@@ -37,6 +38,8 @@ pub struct BackupConfiguration {
     pub config: Value,
     pub check: Option<Value>,
     pub timeframes: Vec<TimeFrameReference>,
+    pub verify: Option<TimeFrameReference>,
+    pub maintenance: Option<TimeFrameReference>,
     pub setup: Option<StrategyConfiguration>
 }
 
@@ -50,5 +53,8 @@ pub struct SyncConfiguration {
     pub config: Value,
     pub check: Option<Value>,
     pub controller: Option<Value>,
-    pub setup: Option<StrategyConfiguration>
+    pub verify: Option<TimeFrameReference>,
+    pub maintenance: Option<TimeFrameReference>,
+    pub setup: Option<StrategyConfiguration>,
+    pub bandwidth: Option<BandwidthConfiguration>
 }
\ No newline at end of file
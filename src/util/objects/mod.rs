@@ -2,4 +2,9 @@ pub mod time;
 pub mod configuration;
 pub mod paths;
 pub mod reporting;
-pub mod savedata;
\ No newline at end of file
+pub mod savedata;
+pub mod trigger;
+pub mod restore;
+pub mod statistics;
+pub mod bandwidth;
+pub mod recovery;
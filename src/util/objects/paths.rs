@@ -22,6 +22,8 @@ pub struct PathBase {
     #[serde(default="default_bool_false")]
     pub savedata_in_store: bool,
     pub reporting_file: Option<String>,
+    pub trigger_file: Option<String>,
+    pub api_file: Option<String>,
     pub docker_images: Option<String>,
     #[serde(default="default_bool_false")]
     pub no_docker: bool
@@ -36,6 +38,8 @@ pub struct Paths {
     pub auth_data_file: String, // File containing shared authentication information
     pub savedata_in_store: bool,
     pub reporting_file: String,
+    pub trigger_file: String,
+    pub api_file: String,
     pub docker_images: String
 }
 
@@ -67,6 +71,8 @@ impl Paths {
             timeframes_file: base.timeframes_file.unwrap_or(format!("{}/timeframes.json", &base.config_dir)),
             auth_data_file: base.auth_data_file.unwrap_or(format!("{}/auth_data.json", &base.config_dir)),
             reporting_file: base.reporting_file.unwrap_or(format!("{}/reporting.json", &base.config_dir)),
+            trigger_file: base.trigger_file.unwrap_or(format!("{}/trigger.json", &base.config_dir)),
+            api_file: base.api_file.unwrap_or(format!("{}/api.json", &base.config_dir)),
             docker_images: base.docker_images.unwrap_or(format!("{}/images", &base.config_dir)),
             config_dir: base.config_dir,
             save_dir: base.save_dir,
@@ -0,0 +1,8 @@
+/**
+ * What is needed to access a backup if the host is lost, collected by the export-keys operation
+ */
+pub struct RecoveryInformation {
+    pub repository: String, // Where the backup is stored
+    pub key_files: Vec<String>, // Files with keys that are required to decrypt the backup
+    pub passphrase_option: Option<String> // Option of the module configuration containing the passphrase, the passphrase itself is not exported
+}
@@ -1,3 +1,5 @@
+use crate::util::objects::statistics::{RunStatistics, RunOutcome};
+
 #[derive(Clone)]
 pub enum ReportEvent {
     Operation(OperationStatus),
@@ -15,13 +17,17 @@ pub enum OperationStatus {
 pub struct StatusReport {
     pub module: Option<String>,
     pub status: Status,
-    pub run_type: RunType
+    pub run_type: RunType,
+    pub statistics: Option<RunStatistics>
 }
 
 #[derive(Clone)]
 pub enum Status {
     START,
     DONE,
+    UNCHANGED,
+    WARNING,
+    PARTIAL,
     ERROR,
     SKIP,
     DISABLED,
@@ -32,7 +38,24 @@ pub enum Status {
 pub enum RunType {
     RUN,
     BACKUP,
-    SYNC
+    SYNC,
+    VERIFY,
+    MAINTENANCE,
+    DRILL
+}
+
+impl Status {
+    /**
+     * The status of an executed run, depending on the outcome reported by the module
+     */
+    pub fn from_outcome(statistics: Option<&RunStatistics>) -> Status {
+        return match statistics.and_then(|statistics| statistics.outcome) {
+            None => Status::DONE,
+            Some(RunOutcome::Unchanged) => Status::UNCHANGED,
+            Some(RunOutcome::Warnings) => Status::WARNING,
+            Some(RunOutcome::Partial) => Status::PARTIAL
+        };
+    }
 }
 
 impl std::fmt::Display for Status {
@@ -40,6 +63,9 @@ impl std::fmt::Display for Status {
         match self {
             Status::START => write!(f, "start"),
             Status::DONE => write!(f, "done"),
+            Status::UNCHANGED => write!(f, "done (no changes)"),
+            Status::WARNING => write!(f, "done (with warnings)"),
+            Status::PARTIAL => write!(f, "partial"),
             Status::ERROR => write!(f, "failure"),
             Status::SKIP => write!(f, "skip"),
             Status::DISABLED => write!(f, "disabled"),
@@ -53,7 +79,10 @@ impl std::fmt::Display for RunType {
         match self {
             RunType::RUN => write!(f, "run"),
             RunType::BACKUP => write!(f, "backup"),
-            RunType::SYNC => write!(f, "sync")
+            RunType::SYNC => write!(f, "sync"),
+            RunType::VERIFY => write!(f, "verify"),
+            RunType::MAINTENANCE => write!(f, "maintenance"),
+            RunType::DRILL => write!(f, "drill")
         }
     }
 }
\ No newline at end of file
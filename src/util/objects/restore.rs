@@ -0,0 +1,238 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use std::path::{Component, Path};
+use crate::Arguments;
+
+/**
+ * Everything a module needs to know about what should be restored
+ */
+pub struct RestoreRequest {
+    pub archive: Option<String>, // Selection of the archive, see ArchiveSelector
+    pub path: Option<String>, // Only restore this path, relative to the source directory
+    pub assume_yes: bool
+}
+
+impl RestoreRequest {
+    pub fn from_args(args: &Arguments) -> Result<RestoreRequest, String> {
+        if let Some(archive) = args.archive.as_ref() {
+            ArchiveSelector::parse(archive.as_str())?;
+        }
+
+        let path = if let Some(path) = args.restore_path.as_ref() {
+            Some(normalize_restore_path(path.as_str())?)
+        } else {
+            None
+        };
+
+        return Ok(RestoreRequest {
+            archive: args.archive.clone(),
+            path,
+            assume_yes: args.assume_yes
+        });
+    }
+}
+
+// The restore path is relative to the source, so it must not contain anything that leaves it
+fn normalize_restore_path(input: &str) -> Result<String, String> {
+    let mut parts = vec![];
+
+    for component in Path::new(input).components() {
+        match component {
+            Component::Normal(part) => {
+                parts.push(part.to_str().ok_or(format!("Restore path '{}' is not valid UTF-8", input))?);
+            },
+            Component::RootDir | Component::CurDir => {},
+            _ => {
+                return Err(format!("Restore path '{}' must not leave the source directory", input));
+            }
+        }
+    }
+
+    if parts.is_empty() {
+        return Err(String::from("Restore path must not be empty"));
+    }
+
+    return Ok(parts.join("/"));
+}
+
+/**
+ * Selection of the archive to restore, given by the --archive option:
+ *  latest, before:<date> or the identifier of the archive (file or archive name)
+ */
+pub enum ArchiveSelector {
+    Latest,
+    Before(DateTime<Local>),
+    Id(String)
+}
+
+impl ArchiveSelector {
+    pub fn parse(input: &str) -> Result<ArchiveSelector, String> {
+        if input == "latest" {
+            return Ok(ArchiveSelector::Latest);
+        }
+
+        if let Some(date) = input.strip_prefix("before:") {
+            return parse_date(date).map(ArchiveSelector::Before);
+        }
+
+        if input.is_empty() {
+            return Err(String::from("The archive selection must not be empty"));
+        }
+
+        return Ok(ArchiveSelector::Id(String::from(input)));
+    }
+
+    /**
+     * Select the index of an entry from a list that is sorted from oldest to newest
+     */
+    pub fn select<T>(&self,
+                     entries: &Vec<T>,
+                     id_mapper: &dyn Fn(&T) -> &str,
+                     time_mapper: &dyn Fn(&T) -> Option<DateTime<Local>>) -> Result<usize, String> {
+        if entries.is_empty() {
+            return Err(String::from("There is no archive to select from"));
+        }
+
+        return match self {
+            ArchiveSelector::Latest => Ok(entries.len() - 1),
+            ArchiveSelector::Id(id) => {
+                // Accept either the full identifier or the last path component (file name)
+                entries.iter().position(|entry| {
+                    let entry_id = id_mapper(entry);
+                    entry_id == id.as_str() || entry_id.rsplit('/').next() == Some(id.as_str())
+                }).ok_or(format!("There is no archive '{}'", id))
+            },
+            ArchiveSelector::Before(date) => {
+                entries.iter().enumerate()
+                    .filter_map(|(index, entry)| time_mapper(entry).map(|time| (index, time)))
+                    .filter(|(_, time)| time < date)
+                    .max_by_key(|(_, time)| *time)
+                    .map(|(index, _)| index)
+                    .ok_or(format!("There is no archive before {}", date.format("%Y-%m-%d %H:%M:%S")))
+            }
+        };
+    }
+}
+
+// A date without time refers to the start of the day
+fn parse_date(input: &str) -> Result<DateTime<Local>, String> {
+    let formats = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"];
+
+    let naive = formats.iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(input, "%Y-%m-%d").ok().and_then(|date| date.and_hms_opt(0, 0, 0)));
+
+    return match naive {
+        Some(naive) => local_from_naive(&naive).ok_or(format!("Date '{}' does not exist in the local timezone", input)),
+        None => Err(format!("Could not parse date '{}', expected format YYYY-MM-DD[ HH:MM[:SS]]", input))
+    };
+}
+
+pub fn local_from_naive(naive: &NaiveDateTime) -> Option<DateTime<Local>> {
+    return Local.from_local_datetime(naive).earliest();
+}
+
+/**
+ * Parse the time from a string starting (after skipping the prefix) with a timestamp in the given format
+ */
+pub fn parse_time_prefix(input: &str, skip: usize, format: &str, length: usize) -> Option<DateTime<Local>> {
+    let time_string = input.get(skip..skip + length)?;
+    let naive = NaiveDateTime::parse_from_str(time_string, format).ok()?;
+    return local_from_naive(&naive);
+}
+
+/**
+ * For modules that can only restore the current state
+ */
+pub fn require_latest(archive: &Option<String>, module_name: &str) -> Result<(), String> {
+    if let Some(archive) = archive.as_ref() {
+        if let ArchiveSelector::Latest = ArchiveSelector::parse(archive.as_str())? {
+            return Ok(());
+        }
+
+        return Err(format!("Selecting an archive is not supported by the {} module, only 'latest'", module_name));
+    }
+
+    return Ok(());
+}
+
+/**
+ * An archive (backup file, borg archive, duplicati version) that can be restored
+ */
+pub struct ArchiveEntry {
+    pub id: String,
+    pub time: Option<DateTime<Local>>
+}
+
+/**
+ * A file inside of an archive, the path is relative to the source directory
+ */
+pub struct FileEntry {
+    pub path: String,
+    pub size: Option<u64>,
+    pub mtime: Option<DateTime<Local>>,
+    pub is_dir: bool
+}
+
+/**
+ * Paths of the files in the latest archive, the archives are sorted from oldest to newest
+ *  Used by the drill to leave out files that are excluded by the configuration
+ */
+pub fn latest_archive_files(archives: Vec<ArchiveEntry>, list_files: &dyn Fn(&ArchiveEntry) -> Result<Vec<FileEntry>, String>) -> Result<Option<Vec<String>>, String> {
+    let latest = if let Some(latest) = archives.last() {
+        latest
+    } else {
+        return Ok(None);
+    };
+
+    return Ok(Some(list_files(latest)?.into_iter()
+        .filter(|entry| !entry.is_dir)
+        .map(|entry| entry.path)
+        .collect()));
+}
+
+/**
+ * Parse a time in one of the given formats, used for the output of external tools
+ */
+pub fn parse_time_formats(input: &str, formats: &[&str]) -> Option<DateTime<Local>> {
+    return formats.iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+        .and_then(|naive| local_from_naive(&naive));
+}
+
+/**
+ * Parse a single line of 'tar -tv' output (GNU or busybox tar)
+ *  -rw-r--r-- user/group    1234 2024-01-05 10:00[:00] ./path/to/file
+ */
+pub fn parse_tar_listing_line(line: &str) -> Option<FileEntry> {
+    let mut rest = line.trim_start();
+    let mut fields = vec![];
+
+    // permissions, owner, size, date and time are separated by (multiple) spaces, the path may contain spaces
+    for _ in 0..5 {
+        let end = rest.find(' ')?;
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+
+    let mode = fields[0];
+    let mut path = rest;
+
+    // links show their target after the path
+    if mode.starts_with('l') {
+        path = path.split(" -> ").next()?;
+    } else if mode.starts_with('h') {
+        path = path.split(" link to ").next()?;
+    }
+
+    let path = path.trim_start_matches("./").trim_end_matches('/');
+    if path.is_empty() || path == "." {
+        return None;
+    }
+
+    return Some(FileEntry {
+        path: String::from(path),
+        size: fields[2].parse::<u64>().ok(),
+        mtime: parse_time_formats(format!("{} {}", fields[3], fields[4]).as_str(), &["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]),
+        is_dir: mode.starts_with('d')
+    });
+}
@@ -3,13 +3,29 @@ use std::path::Path;
 use serde::{Deserialize,Serialize};
 
 use crate::util::objects::time::TimeEntry;
+use crate::util::objects::statistics::RunStatistics;
 use crate::util::io::{file, json};
 
 #[derive(Clone,Deserialize)]
 pub struct SaveDataDeserialized {
     pub lastsave: HashMap<String,TimeEntry>,
     pub nextsave: HashMap<String,TimeEntry>,
-    pub lastsync: HashMap<String,TimeEntry>
+    pub lastsync: HashMap<String,TimeEntry>,
+    #[serde(default)]
+    pub lastverify: HashMap<String,VerifyEntry>,
+    #[serde(default)]
+    pub laststatistics: HashMap<String,RunStatistics>,
+    #[serde(default)]
+    pub lastmaintenance: HashMap<String,VerifyEntry>
+}
+
+// result of the last verification or maintenance, stored for the backup and sync module respectively
+#[derive(Clone,Deserialize,Serialize)]
+pub struct VerifyEntry {
+    #[serde(flatten)]
+    pub time: TimeEntry,
+    pub success: bool,
+    pub error: Option<String>
 }
 
 impl SaveDataDeserialized {
@@ -23,6 +39,9 @@ pub struct SaveData {
     pub lastsave: HashMap<String,TimeEntry>,
     pub nextsave: HashMap<String,TimeEntry>,
     pub lastsync: HashMap<String,TimeEntry>,
+    pub lastverify: HashMap<String,VerifyEntry>,
+    pub laststatistics: HashMap<String,RunStatistics>,
+    pub lastmaintenance: HashMap<String,VerifyEntry>,
     #[serde(skip)]
     pub path: String
 }
@@ -33,6 +52,9 @@ impl SaveData {
             lastsave: deserialized.lastsave,
             nextsave: deserialized.nextsave,
             lastsync: deserialized.lastsync,
+            lastverify: deserialized.lastverify,
+            laststatistics: deserialized.laststatistics,
+            lastmaintenance: deserialized.lastmaintenance,
             path: String::from(path)
         }
     }
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+/**
+ * What a run did, as far as the module can tell
+ */
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct RunStatistics {
+    pub files_transferred: Option<u64>,
+    pub bytes_sent: Option<u64>,
+    pub bytes_received: Option<u64>,
+    pub speedup: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<RunOutcome>
+}
+
+/**
+ * How a run ended, for modules that can tell more than success or failure
+ */
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunOutcome {
+    Unchanged, // there was nothing to do
+    Warnings, // done, but there were warnings
+    Partial // only partially done, continued with the next run
+}
+
+impl std::fmt::Display for RunStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut parts = vec![];
+
+        if let Some(files_transferred) = self.files_transferred {
+            parts.push(format!("{} file(s) transferred", files_transferred));
+        }
+        if let Some(bytes_sent) = self.bytes_sent {
+            parts.push(format!("{} byte(s) sent", bytes_sent));
+        }
+        if let Some(bytes_received) = self.bytes_received {
+            parts.push(format!("{} byte(s) received", bytes_received));
+        }
+        if let Some(speedup) = self.speedup {
+            parts.push(format!("speedup {:.2}", speedup));
+        }
+        if let Some(outcome) = self.outcome {
+            parts.push(String::from(match outcome {
+                RunOutcome::Unchanged => "no changes",
+                RunOutcome::Warnings => "with warnings",
+                RunOutcome::Partial => "partial"
+            }));
+        }
+
+        write!(f, "{}", parts.join(", "))
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{Arguments, normalize_arguments};
+use serde::{Deserialize,Serialize};
+
+/**
+ * Payload of a run request received by the trigger listener, maps onto the command line arguments
+ */
+#[derive(Deserialize)]
+pub struct RunRequest {
+    pub id: Option<String>, // Passed through to the response to correlate request and result
+    pub operation: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub force: bool,
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub no_reporting: bool,
+    #[serde(default)]
+    pub override_disabled: bool,
+    #[serde(default)]
+    pub manual: bool,
+    #[serde(default)]
+    pub all: bool,
+    #[serde(default)]
+    pub ignore_checks: bool,
+    #[serde(default)]
+    pub ignore_time_check: bool,
+    #[serde(default)]
+    pub ignore_additional_check: bool
+}
+
+#[derive(Serialize)]
+pub struct RunResponse {
+    pub id: Option<String>,
+    pub operation: String,
+    pub name: Option<String>,
+    pub status: &'static str,
+    pub error: Option<String>,
+    pub timestamp: i64
+}
+
+impl RunRequest {
+    /**
+     * Create the arguments for this run based on the arguments of the listener
+     */
+    pub fn to_arguments(&self, base: &Arguments) -> Result<Arguments, String> {
+        match self.operation.as_str() {
+            "run" | "backup" | "save" | "sync" | "verify" => {},
+            unknown => {
+                return Err(format!("Operation '{}' can not be triggered", unknown));
+            }
+        }
+
+        if let Some(name) = self.name.as_ref() {
+            // The name is used to build a path, so do not allow to escape the configuration directory
+            if name.is_empty() || name.contains('/') || name.starts_with('.') {
+                return Err(format!("Invalid configuration name: '{}'", name));
+            }
+        }
+
+        let mut args = base.clone();
+        args.operation = self.operation.clone();
+        args.name = self.name.clone();
+        args.force = self.force;
+        args.dry_run = base.dry_run || self.dry_run;
+        args.no_reporting = base.no_reporting || self.no_reporting;
+        args.override_disabled = self.override_disabled;
+        args.run_manual = self.manual;
+        args.run_all = self.all;
+        args.run_manual_only = false;
+        args.ignore_checks = self.ignore_checks;
+        args.ignore_time_check = self.ignore_time_check;
+        args.ignore_additional_check = self.ignore_additional_check;
+        args.is_restore = false;
+        args.restore_to = None;
+        args.archive = None;
+        args.restore_path = None;
+        args.only = None;
+        args.pattern = None;
+        args.drill_sample = None;
+        args.identity = None;
+
+        normalize_arguments(&mut args);
+        return Ok(args);
+    }
+}
@@ -3,9 +3,11 @@ use crate::modules::reporting::{ReportingModule, ReportingWrapper};
 use crate::util::objects::time::{TimeFrameReference};
 use crate::util::objects::paths::{Paths,PathBase,ModulePaths,SourcePath};
 use crate::util::objects::configuration::Configuration;
+use crate::modules::backup::BackupModule;
+use crate::modules::sync::SyncModule;
 use crate::util::objects::reporting::{OperationStatus, ReportEvent};
 use crate::processing::{preprocessor,scheduler,processor};
-use crate::{Arguments, restore};
+use crate::{Arguments, restore, browse, drill, keys};
 
 use crate::{log_error};
 
@@ -30,6 +32,16 @@ pub fn main(mut args: Arguments) -> Result<(),String> {
         return list(&Rc::new(args), &paths);
     }
 
+    // Browsing archives does not report anything either
+    if args.operation == "ls" || args.operation == "find" {
+        return browse::main(Rc::new(args), paths);
+    }
+
+    // Exporting keys does not report anything either
+    if args.operation == "export-keys" {
+        return keys::main(Rc::new(args), paths);
+    }
+
     // Set up reporter (if existing)
     let mut reporter = if args.no_reporting {
         ReportingModule::new_empty()
@@ -60,20 +72,24 @@ pub fn main(mut args: Arguments) -> Result<(),String> {
     let result = if args.operation == "restore" {
         args.is_restore = true;
         restore::main(Rc::new(args), paths)
+    } else if args.operation == "drill" {
+        drill::main(Rc::new(args), paths, &mut reporter)
     } else {
         let (do_backup, do_sync) = match args.operation.as_str() {
             "run" => Ok((true, true)),
             "backup" | "save" => Ok((true, false)),
             "sync" => Ok((false, true)),
+            "verify" => restore::parse_only(&args),
             unknown => {
                 Err(format!("Unknown operation: '{}'", unknown))
             }
         }?;
+        let verify_only = args.operation == "verify";
 
         let args = Rc::new(args);
 
         let config_list = get_config_list(&args, paths.as_ref())?;
-        let preprocessed = preprocessor::preprocess(config_list, &args, &paths, &mut reporter, do_backup, do_sync)?;
+        let preprocessed = preprocessor::preprocess(config_list, &args, &paths, &mut reporter, do_backup, do_sync, verify_only)?;
         let scheduled = scheduler::get_exec_order(preprocessed.configurations)?;
         let result = processor::process_configurations(&args, &mut reporter, scheduled, preprocessed.savedata);
         result
@@ -103,7 +119,8 @@ pub fn get_config_list(args: &Rc<Arguments>, paths: &Paths) -> Result<Vec<Config
     // Load all the configuration files parsed as Configuration
     // TODO: Only logs inaccessible files and then disregards the error
     let configs = files.iter().filter_map(|file_path| {
-        let result = json::from_file::<Configuration>(file_path);
+        let result = json::from_file::<Configuration>(file_path)
+            .and_then(|config| check_configuration(&config).map(|_| config));
         if result.is_ok() {
             Some(result.unwrap())
         } else {
@@ -115,6 +132,26 @@ pub fn get_config_list(args: &Rc<Arguments>, paths: &Paths) -> Result<Vec<Config
     return Ok(configs);
 }
 
+// Settings the modules do not support are rejected, instead of failing on every scheduled run
+fn check_configuration(config: &Configuration) -> Result<(), String> {
+    if let Some(backup) = config.backup.as_ref().filter(|backup| backup.maintenance.is_some()) {
+        BackupModule::check_maintenance(backup.backup_type.as_str())
+            .map_err(|err| format!("Invalid maintenance of '{}': {}", config.name, err))?;
+    }
+
+    if let Some(sync) = config.sync.as_ref().filter(|sync| sync.maintenance.is_some()) {
+        SyncModule::check_maintenance(sync.sync_type.as_str())
+            .map_err(|err| format!("Invalid maintenance of '{}': {}", config.name, err))?;
+    }
+
+    if let Some(bandwidth) = config.sync.as_ref().and_then(|sync| sync.bandwidth.as_ref()) {
+        bandwidth.validate()
+            .map_err(|err| format!("Invalid bandwidth of '{}': {}", config.name, err))?;
+    }
+
+    return Ok(());
+}
+
 pub fn list(args: &Rc<Arguments>, paths: &Rc<Paths>) -> Result<(), String> {
 
     // Helper to output an additional check nicely formatted